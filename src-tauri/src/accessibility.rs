@@ -0,0 +1,107 @@
+use lopdf::{Dictionary, Document, Object, ObjectId, StringFormat};
+
+use crate::{page_fit::wrap_page_contents, MergeError};
+
+/// 程序生成的页面在结构树中的角色。
+#[derive(Debug, Clone)]
+pub enum PageTag {
+    /// 由图片转换的页面，`alt` 为读屏软件朗读的替代文字
+    Figure { alt: String },
+    /// 占位页、失败清单、分隔页等纯文字页面，`text` 为页面上的文字
+    Text { text: String },
+}
+
+/// 为生成的页面建立基本的结构树（Document → Figure / P），并把文档标记为带标签的 PDF。
+/// 每个页面的全部内容包成一个标记内容序列（MCID 0），页面通过 /StructParents 指回结构元素。
+/// 源 PDF 的页面保持原样，其自身的结构树不保留。
+pub fn tag_pages(
+    doc: &mut Document,
+    catalog_id: ObjectId,
+    pages: &[(ObjectId, &PageTag)],
+    lang: &str,
+) -> Result<(), MergeError> {
+    // 合并时对象是直接插入的，max_id 可能未同步
+    doc.max_id = doc
+        .objects
+        .keys()
+        .map(|(id, _)| *id)
+        .max()
+        .unwrap_or(0)
+        .max(doc.max_id);
+    let root_id = doc.new_object_id();
+    let document_id = doc.new_object_id();
+
+    let mut kids = Vec::with_capacity(pages.len());
+    let mut parent_tree = Vec::with_capacity(pages.len() * 2);
+    for (index, (page_id, tag)) in pages.iter().enumerate() {
+        let (role, text_key, text) = match tag {
+            PageTag::Figure { alt } => ("Figure", "Alt", alt),
+            PageTag::Text { text } => ("P", "ActualText", text),
+        };
+        wrap_page_contents(
+            doc,
+            *page_id,
+            format!("/{role} <</MCID 0>> BDC\n").into_bytes(),
+            b"\nEMC\n".to_vec(),
+        )?;
+
+        let mut element = Dictionary::new();
+        element.set("Type", Object::Name(b"StructElem".to_vec()));
+        element.set("S", Object::Name(role.as_bytes().to_vec()));
+        element.set("P", Object::Reference(document_id));
+        element.set("Pg", Object::Reference(*page_id));
+        element.set("K", 0);
+        element.set(text_key, text_string(text));
+        let element_id = doc.add_object(element);
+        kids.push(Object::Reference(element_id));
+        parent_tree.push(Object::Integer(index as i64));
+        parent_tree.push(Object::Array(vec![Object::Reference(element_id)]));
+
+        doc.get_object_mut(*page_id)
+            .and_then(|obj| obj.as_dict_mut())
+            .map_err(|err| MergeError::Pdf(err.to_string()))?
+            .set("StructParents", index as i64);
+    }
+
+    let mut document = Dictionary::new();
+    document.set("Type", Object::Name(b"StructElem".to_vec()));
+    document.set("S", Object::Name(b"Document".to_vec()));
+    document.set("P", Object::Reference(root_id));
+    document.set("K", kids);
+    doc.objects.insert(document_id, Object::Dictionary(document));
+
+    let mut nums = Dictionary::new();
+    nums.set("Nums", parent_tree);
+    let mut root = Dictionary::new();
+    root.set("Type", Object::Name(b"StructTreeRoot".to_vec()));
+    root.set("K", Object::Reference(document_id));
+    root.set("ParentTree", nums);
+    root.set("ParentTreeNextKey", pages.len() as i64);
+    doc.objects.insert(root_id, Object::Dictionary(root));
+
+    let mut mark_info = Dictionary::new();
+    mark_info.set("Marked", true);
+    let mut viewer = Dictionary::new();
+    viewer.set("DisplayDocTitle", true);
+    let catalog = doc
+        .get_object_mut(catalog_id)
+        .and_then(|obj| obj.as_dict_mut())
+        .map_err(|err| MergeError::Pdf(err.to_string()))?;
+    catalog.set("MarkInfo", mark_info);
+    catalog.set("StructTreeRoot", Object::Reference(root_id));
+    catalog.set("Lang", Object::string_literal(lang));
+    catalog.set("ViewerPreferences", viewer);
+    Ok(())
+}
+
+/// PDF 文本字符串：含非 ASCII 字符时按 UTF-16BE（带 BOM）编码。
+pub(crate) fn text_string(text: &str) -> Object {
+    if text.is_ascii() {
+        return Object::string_literal(text);
+    }
+    let mut bytes = vec![0xFE, 0xFF];
+    for unit in text.encode_utf16() {
+        bytes.extend(unit.to_be_bytes());
+    }
+    Object::String(bytes, StringFormat::Hexadecimal)
+}
@@ -0,0 +1,32 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use tempfile::TempPath;
+
+use crate::{sniff, MergeError};
+
+pub const TEMP_PREFIX: &str = "mc-append-";
+
+/// 校验要追加的已有合并文件，返回规范化后的路径。
+pub fn resolve_base(path: &str) -> Result<PathBuf, MergeError> {
+    let base = Path::new(path)
+        .canonicalize()
+        .map_err(|_| MergeError::Pdf(format!("追加目标不存在: {path}")))?;
+    if !base.is_file() || sniff::sniff_extension(&base) != Some("pdf") {
+        return Err(MergeError::Pdf(format!("追加目标不是 PDF 文件: {path}")));
+    }
+    Ok(base)
+}
+
+/// 输出会写回原文件，先把它复制到工作目录作为合并的第一份输入，
+/// 封面和原有页序随之保留。
+pub fn copy_base(base: &Path, work_dir: &Path) -> Result<(PathBuf, TempPath), MergeError> {
+    let temp = tempfile::Builder::new()
+        .prefix(TEMP_PREFIX)
+        .suffix(".pdf")
+        .tempfile_in(work_dir)?
+        .into_temp_path();
+    fs::copy(base, &temp)?;
+    Ok((temp.to_path_buf(), temp))
+}
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use std::{io::BufWriter, path::Path};
+
+use crate::{
+    mmap_pdf, pdf_writer,
+    stamp::{self, StampPosition},
+    MergeError,
+};
+
+/// Bates 编号：为输出的每一页加盖唯一编号，如 `HT000001`，供法务、审计归档引用。
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct BatesNumbering {
+    pub prefix: String,
+    pub start: u64,
+    /// 编号位数，不足时前补 0
+    pub digits: usize,
+    pub position: StampPosition,
+    pub font_size: f64,
+}
+
+impl Default for BatesNumbering {
+    fn default() -> Self {
+        Self {
+            prefix: String::new(),
+            start: 1,
+            digits: 6,
+            position: StampPosition::BottomRight,
+            font_size: 9.0,
+        }
+    }
+}
+
+impl BatesNumbering {
+    /// 第 `offset` 页（从 0 开始）的编号
+    pub fn label(&self, offset: usize) -> String {
+        format!(
+            "{}{:0width$}",
+            self.prefix,
+            self.start + offset as u64,
+            width = self.digits
+        )
+    }
+}
+
+/// 给输出文件的页面加盖编号并写回原文件，返回最后一页的编号。
+/// 前 `numbered` 页来自已编号的文件（追加模式的原文件、复用的上次输出），只续编其后的页面。
+pub fn apply(path: &Path, options: &BatesNumbering, numbered: usize) -> Result<Option<String>, MergeError> {
+    let mut doc = mmap_pdf::load(path)?;
+    let pages: Vec<_> = doc.get_pages().into_values().collect();
+    let labels: Vec<_> = pages
+        .iter()
+        .enumerate()
+        .skip(numbered)
+        .map(|(index, page_id)| (*page_id, options.label(index)))
+        .collect();
+    if labels.is_empty() {
+        return Ok(None);
+    }
+    stamp::stamp_pages(&mut doc, &labels, options.font_size, options.position)?;
+
+    // 先写到同目录的临时文件再替换，中途失败不会留下半份输出
+    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+    let temp_file = tempfile::NamedTempFile::new_in(parent)?;
+    {
+        let mut writer = BufWriter::new(temp_file.as_file());
+        pdf_writer::save_compact(&doc, &mut writer, |_, _| {})?;
+    }
+    temp_file.persist(path).map_err(|err| err.error)?;
+    Ok(Some(options.label(pages.len() - 1)))
+}
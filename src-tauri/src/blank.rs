@@ -0,0 +1,22 @@
+use image::{imageops::FilterType, DynamicImage};
+
+/// 亮度标准差低于该值视为几乎纯色（口袋误拍的全黑、过曝的全白等）
+const BLANK_STDDEV_THRESHOLD: f64 = 6.0;
+
+pub fn is_near_blank(image: &DynamicImage) -> bool {
+    let small = image.resize(64, 64, FilterType::Triangle).to_luma8();
+    let count = small.pixels().len() as f64;
+    if count == 0.0 {
+        return true;
+    }
+    let mean = small.pixels().map(|p| p.0[0] as f64).sum::<f64>() / count;
+    let variance = small
+        .pixels()
+        .map(|p| {
+            let diff = p.0[0] as f64 - mean;
+            diff * diff
+        })
+        .sum::<f64>()
+        / count;
+    variance.sqrt() < BLANK_STDDEV_THRESHOLD
+}
@@ -0,0 +1,84 @@
+use sha2::{Digest, Sha256};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::jobs::hex_prefix;
+
+pub const DEFAULT_CACHE_LIMIT_MB: u64 = 512;
+
+/// 跨次合并复用的转换缓存：以源文件路径 + 修改时间 + 大小为键保存转换后的 PDF，
+/// 目录总大小超过上限时按最近使用时间淘汰。
+pub struct ConversionCache {
+    dir: PathBuf,
+    limit_bytes: u64,
+}
+
+impl ConversionCache {
+    pub fn open(dir: PathBuf, limit_mb: u64) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            limit_bytes: limit_mb * 1024 * 1024,
+        })
+    }
+
+    /// `variant` 为转换选项的指纹，同一源文件在不同选项下分别缓存。
+    pub fn lookup(&self, source: &Path, variant: &str) -> Option<PathBuf> {
+        let entry = self.entry_path(source, variant)?;
+        if !entry.is_file() {
+            return None;
+        }
+        // 命中时刷新修改时间，淘汰时据此判断最近使用
+        if let Ok(file) = fs::File::options().write(true).open(&entry) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(entry)
+    }
+
+    pub fn store(&self, source: &Path, variant: &str, converted: &Path) -> io::Result<PathBuf> {
+        let entry = self
+            .entry_path(source, variant)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "source metadata unavailable"))?;
+        let partial = entry.with_extension("partial");
+        fs::copy(converted, &partial)?;
+        fs::rename(&partial, &entry)?;
+        Ok(entry)
+    }
+
+    /// 合并结束后调用：删除最久未使用的条目直到总大小不超过上限。
+    pub fn prune(&self) {
+        let Ok(read_dir) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut entries: Vec<(SystemTime, u64, PathBuf)> = read_dir
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let meta = entry.metadata().ok()?;
+                meta.is_file()
+                    .then(|| (meta.modified().unwrap_or(UNIX_EPOCH), meta.len(), entry.path()))
+            })
+            .collect();
+        let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        entries.sort_by_key(|(modified, _, _)| *modified);
+
+        for (_, size, path) in entries {
+            if total <= self.limit_bytes {
+                break;
+            }
+            if fs::remove_file(&path).is_ok() {
+                total = total.saturating_sub(size);
+            }
+        }
+    }
+
+    fn entry_path(&self, source: &Path, variant: &str) -> Option<PathBuf> {
+        let meta = fs::metadata(source).ok()?;
+        let modified = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_nanos();
+        let key = format!("{}|{}|{}|{variant}", source.display(), modified, meta.len());
+        let digest = Sha256::digest(key.as_bytes());
+        Some(self.dir.join(format!("{}.pdf", hex_prefix(&digest))))
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::MergeError;
+
+/// 当前合并的取消标记。同一时间只运行一个合并，因此全局一个标记即可；
+/// 与 `MERGING` 一样不放在托管状态里，以插件形式嵌入时取消同样生效。
+static CANCEL_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// 是否有合并在进行。界面、托盘、HTTP 接口、stdio 等入口共用；以插件形式嵌入时也要可用，因此不放在托管状态里
+static MERGING: AtomicBool = AtomicBool::new(false);
+
+/// 持有期间占用合并，释放时清除标记。
+pub struct MergeGuard(());
+
+impl Drop for MergeGuard {
+    fn drop(&mut self) {
+        MERGING.store(false, Ordering::SeqCst);
+    }
+}
+
+/// 开始一次合并；已有合并在进行时返回 `Busy`，不能让两次合并共用同一个取消标记和工作目录。
+pub fn begin() -> Result<MergeGuard, MergeError> {
+    if MERGING.swap(true, Ordering::SeqCst) {
+        return Err(MergeError::Busy);
+    }
+    Ok(MergeGuard(()))
+}
+
+pub fn in_progress() -> bool {
+    MERGING.load(Ordering::Relaxed)
+}
+
+/// 新的合并开始前清除上一次遗留的取消请求。
+pub fn reset() {
+    CANCEL_REQUESTED.store(false, Ordering::Relaxed);
+}
+
+pub fn request() {
+    CANCEL_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// 合并循环在文件之间调用；已请求取消时返回 `Cancelled`，
+/// 调用方随之返回，已生成的临时 PDF 随 `TempPath` 一起删除。
+pub fn check() -> Result<(), MergeError> {
+    if CANCEL_REQUESTED.load(Ordering::Relaxed) {
+        Err(MergeError::Cancelled)
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,430 @@
+use image::GrayImage;
+
+// ITU-T T.4 码表：终止码对应 0–63 的游程，组合码对应 64 的倍数。
+const WHITE_TERMINATING: [&str; 64] = [
+    "00110101", "000111", "0111", "1000", "1011", "1100", "1110", "1111", "10011", "10100", "00111", "01000",
+    "001000", "000011", "110100", "110101", "101010", "101011", "0100111", "0001100", "0001000", "0010111",
+    "0000011", "0000100", "0101000", "0101011", "0010011", "0100100", "0011000", "00000010", "00000011",
+    "00011010", "00011011", "00010010", "00010011", "00010100", "00010101", "00010110", "00010111",
+    "00101000", "00101001", "00101010", "00101011", "00101100", "00101101", "00000100", "00000101",
+    "00001010", "00001011", "01010010", "01010011", "01010100", "01010101", "00100100", "00100101",
+    "01011000", "01011001", "01011010", "01011011", "01001010", "01001011", "00110010", "00110011",
+    "00110100",
+];
+
+const BLACK_TERMINATING: [&str; 64] = [
+    "0000110111",
+    "010",
+    "11",
+    "10",
+    "011",
+    "0011",
+    "0010",
+    "00011",
+    "000101",
+    "000100",
+    "0000100",
+    "0000101",
+    "0000111",
+    "00000100",
+    "00000111",
+    "000011000",
+    "0000010111",
+    "0000011000",
+    "0000001000",
+    "00001100111",
+    "00001101000",
+    "00001101100",
+    "00000110111",
+    "00000101000",
+    "00000010111",
+    "00000011000",
+    "000011001010",
+    "000011001011",
+    "000011001100",
+    "000011001101",
+    "000001101000",
+    "000001101001",
+    "000001101010",
+    "000001101011",
+    "000011010010",
+    "000011010011",
+    "000011010100",
+    "000011010101",
+    "000011010110",
+    "000011010111",
+    "000001101100",
+    "000001101101",
+    "000011011010",
+    "000011011011",
+    "000001010100",
+    "000001010101",
+    "000001010110",
+    "000001010111",
+    "000001100100",
+    "000001100101",
+    "000001010010",
+    "000001010011",
+    "000000100100",
+    "000000110111",
+    "000000111000",
+    "000000100111",
+    "000000101000",
+    "000001011000",
+    "000001011001",
+    "000000101011",
+    "000000101100",
+    "000001011010",
+    "000001100110",
+    "000001100111",
+];
+
+/// 64, 128, …, 1728
+const WHITE_MAKEUP: [&str; 27] = [
+    "11011",
+    "10010",
+    "010111",
+    "0110111",
+    "00110110",
+    "00110111",
+    "01100100",
+    "01100101",
+    "01101000",
+    "01100111",
+    "011001100",
+    "011001101",
+    "011010010",
+    "011010011",
+    "011010100",
+    "011010101",
+    "011010110",
+    "011010111",
+    "011011000",
+    "011011001",
+    "011011010",
+    "011011011",
+    "010011000",
+    "010011001",
+    "010011010",
+    "011000",
+    "010011011",
+];
+
+const BLACK_MAKEUP: [&str; 27] = [
+    "0000001111",
+    "000011001000",
+    "000011001001",
+    "000001011011",
+    "000000110011",
+    "000000110100",
+    "000000110101",
+    "0000001101100",
+    "0000001101101",
+    "0000001001010",
+    "0000001001011",
+    "0000001001100",
+    "0000001001101",
+    "0000001110010",
+    "0000001110011",
+    "0000001110100",
+    "0000001110101",
+    "0000001110110",
+    "0000001110111",
+    "0000001010010",
+    "0000001010011",
+    "0000001010100",
+    "0000001010101",
+    "0000001011010",
+    "0000001011011",
+    "0000001100100",
+    "0000001100101",
+];
+
+/// 1792, 1856, …, 2560，黑白通用
+const EXTENDED_MAKEUP: [&str; 13] = [
+    "00000001000",
+    "00000001100",
+    "00000001101",
+    "000000010010",
+    "000000010011",
+    "000000010100",
+    "000000010101",
+    "000000010110",
+    "000000010111",
+    "000000011100",
+    "000000011101",
+    "000000011110",
+    "000000011111",
+];
+
+const PASS: &str = "0001";
+const HORIZONTAL: &str = "001";
+/// 垂直模式，下标为 a1 - b1 + 3
+const VERTICAL: [&str; 7] = ["0000010", "000010", "010", "1", "011", "000011", "0000011"];
+const EOFB: &str = "000000000001000000000001";
+
+/// 按 CCITT Group 4（T.6，即 PDF 中 `/K -1`）编码黑白图片；灰度值小于 128 的像素视为黑。
+/// 输出对应 `/CCITTFaxDecode` 的默认参数：0 为黑、不按字节对齐行、以 EOFB 结束。
+pub fn encode_g4(image: &GrayImage) -> Vec<u8> {
+    let width = image.width() as usize;
+    let mut writer = BitWriter::default();
+    // 第一行的参考行为一整行白
+    let mut reference = vec![false; width];
+    let mut coding = vec![false; width];
+    for row in image.rows() {
+        for (pel, pixel) in coding.iter_mut().zip(row) {
+            *pel = pixel[0] < 128;
+        }
+        encode_line(&mut writer, &reference, &coding);
+        std::mem::swap(&mut reference, &mut coding);
+    }
+    writer.put(EOFB);
+    writer.finish()
+}
+
+fn encode_line(writer: &mut BitWriter, reference: &[bool], coding: &[bool]) {
+    let width = coding.len();
+    // a0 从行首之前的虚拟白像素开始
+    let mut a0: isize = -1;
+    let mut color = false;
+    while a0 < width as isize {
+        let a1 = next_color(coding, a0, !color);
+        let b1 = reference_b1(reference, a0, color);
+        let b2 = next_change(reference, b1);
+        if b2 < a1 {
+            writer.put(PASS);
+            a0 = b2 as isize;
+        } else if a1.abs_diff(b1) <= 3 {
+            writer.put(VERTICAL[(a1 as isize - b1 as isize + 3) as usize]);
+            a0 = a1 as isize;
+            color = !color;
+        } else {
+            let a2 = next_color(coding, a1 as isize, color);
+            writer.put(HORIZONTAL);
+            put_run(writer, a1 - a0.max(0) as usize, color);
+            put_run(writer, a2 - a1, !color);
+            a0 = a2 as isize;
+        }
+    }
+}
+
+/// `after` 之后第一个颜色为 `color` 的像素位置，没有时为行宽。
+fn next_color(line: &[bool], after: isize, color: bool) -> usize {
+    let start = (after + 1).max(0) as usize;
+    line.iter()
+        .skip(start)
+        .position(|&pel| pel == color)
+        .map_or(line.len(), |offset| start + offset)
+}
+
+/// 参考行上 a0 之后第一个颜色与 a0 相反的变化点。
+fn reference_b1(reference: &[bool], a0: isize, color: bool) -> usize {
+    let start = (a0 + 1).max(0) as usize;
+    (start..reference.len())
+        .find(|&i| {
+            let previous = i > 0 && reference[i - 1];
+            reference[i] != previous && reference[i] != color
+        })
+        .unwrap_or(reference.len())
+}
+
+/// `from` 之后的下一个变化点。
+fn next_change(line: &[bool], from: usize) -> usize {
+    if from >= line.len() {
+        return line.len();
+    }
+    next_color(line, from as isize, !line[from])
+}
+
+fn put_run(writer: &mut BitWriter, mut run: usize, black: bool) {
+    let (terminating, makeup) = if black {
+        (&BLACK_TERMINATING, &BLACK_MAKEUP)
+    } else {
+        (&WHITE_TERMINATING, &WHITE_MAKEUP)
+    };
+    while run >= 2560 {
+        writer.put(EXTENDED_MAKEUP[12]);
+        run -= 2560;
+    }
+    if run >= 64 {
+        let length = run / 64 * 64;
+        if length <= 1728 {
+            writer.put(makeup[length / 64 - 1]);
+        } else {
+            writer.put(EXTENDED_MAKEUP[(length - 1792) / 64]);
+        }
+        run -= length;
+    }
+    writer.put(terminating[run]);
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    current: u8,
+    filled: u8,
+}
+
+impl BitWriter {
+    fn put(&mut self, code: &str) {
+        for bit in code.bytes() {
+            self.current = (self.current << 1) | (bit == b'1') as u8;
+            self.filled += 1;
+            if self.filled == 8 {
+                self.bytes.push(self.current);
+                self.current = 0;
+                self.filled = 0;
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.filled > 0 {
+            self.bytes.push(self.current << (8 - self.filled));
+        }
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fax::{decoder, Color};
+    use image::Luma;
+
+    const BLACK: u8 = 0;
+    const WHITE: u8 = 255;
+
+    /// 用 fax crate 解码并逐像素比较
+    fn assert_round_trip(image: &GrayImage) {
+        let encoded = encode_g4(image);
+        let width = image.width() as u16;
+        let mut decoded: Vec<Vec<bool>> = Vec::new();
+        decoder::decode_g4(
+            encoded.into_iter(),
+            width,
+            Some(image.height() as u16),
+            |transitions| {
+                decoded.push(
+                    decoder::pels(transitions, width)
+                        .map(|color| color == Color::Black)
+                        .collect(),
+                );
+            },
+        )
+        .expect("G4 数据无法解码");
+        assert_eq!(decoded.len(), image.height() as usize, "行数不符");
+        for (y, row) in decoded.iter().enumerate() {
+            for (x, &black) in row.iter().enumerate() {
+                let expected = image.get_pixel(x as u32, y as u32)[0] < 128;
+                assert_eq!(black, expected, "第 {y} 行第 {x} 列像素不符");
+            }
+        }
+    }
+
+    /// 按 (颜色, 长度) 依次铺满一行，剩余部分为白
+    fn image_from_runs(width: u32, rows: &[&[(u8, u32)]]) -> GrayImage {
+        let mut image = GrayImage::from_pixel(width, rows.len() as u32, Luma([WHITE]));
+        for (y, runs) in rows.iter().enumerate() {
+            let mut x = 0;
+            for &(value, length) in runs.iter() {
+                for _ in 0..length {
+                    image.put_pixel(x, y as u32, Luma([value]));
+                    x += 1;
+                }
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn code_tables_are_prefix_free() {
+        for (terminating, makeup) in [
+            (&WHITE_TERMINATING, &WHITE_MAKEUP),
+            (&BLACK_TERMINATING, &BLACK_MAKEUP),
+        ] {
+            let codes: Vec<&str> = terminating
+                .iter()
+                .chain(makeup.iter())
+                .chain(EXTENDED_MAKEUP.iter())
+                .copied()
+                .collect();
+            for (i, a) in codes.iter().enumerate() {
+                for (j, b) in codes.iter().enumerate() {
+                    assert!(i == j || !b.starts_with(a), "码 {a} 是 {b} 的前缀");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn uniform_rows() {
+        for width in [1, 7, 13, 64, 1729] {
+            assert_round_trip(&GrayImage::from_pixel(width, 3, Luma([WHITE])));
+            assert_round_trip(&GrayImage::from_pixel(width, 3, Luma([BLACK])));
+        }
+    }
+
+    #[test]
+    fn run_length_boundaries() {
+        let image = image_from_runs(
+            6000,
+            &[
+                // 以黑开头：第一段白游程长度为 0
+                &[(BLACK, 63), (WHITE, 64), (BLACK, 1728)],
+                &[(WHITE, 63), (BLACK, 64), (WHITE, 1728), (BLACK, 1792)],
+                &[(BLACK, 2560), (WHITE, 2561), (BLACK, 1)],
+                &[(WHITE, 1), (BLACK, 5120), (WHITE, 65)],
+                &[(BLACK, 6000)],
+                &[],
+            ],
+        );
+        assert_round_trip(&image);
+    }
+
+    #[test]
+    fn runs_in_horizontal_mode() {
+        // 参考行全白，每段游程都走水平模式
+        for run in [
+            0, 1, 63, 64, 65, 127, 128, 1727, 1728, 1729, 1791, 1792, 2559, 2560, 2561, 4000,
+        ] {
+            let image = image_from_runs(8100, &[&[(WHITE, run), (BLACK, run)], &[(BLACK, run)]]);
+            assert_round_trip(&image);
+        }
+    }
+
+    #[test]
+    fn widths_not_multiple_of_eight() {
+        for width in [3, 9, 17, 100, 1001] {
+            let mut image = GrayImage::new(width, 11);
+            for (x, y, pixel) in image.enumerate_pixels_mut() {
+                *pixel = Luma([if (x * 7 + y * 3) % 5 < 2 { BLACK } else { WHITE }]);
+            }
+            assert_round_trip(&image);
+        }
+    }
+
+    #[test]
+    fn pseudo_random_content() {
+        // 固定种子的线性同余序列，覆盖通过、垂直与水平模式的各种组合
+        let mut state: u32 = 12345;
+        let mut image = GrayImage::new(333, 64);
+        for y in 0..64 {
+            let mut value = WHITE;
+            for x in 0..333 {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                // 保持一定的行间相关，避免全是水平模式
+                let flip = if y > 0 && image.get_pixel(x, y - 1)[0] != value {
+                    (state >> 16) % 3 == 0
+                } else {
+                    (state >> 16) % 11 == 0
+                };
+                if flip {
+                    value = if value == WHITE { BLACK } else { WHITE };
+                }
+                image.put_pixel(x, y, Luma([value]));
+            }
+        }
+        assert_round_trip(&image);
+    }
+}
@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::{append, incremental, jobs::JOBS_DIR_NAME, password, preview::PREVIEW_PREFIX, stamp};
+
+/// 崩溃残留的中间文件超过该时长才会被清理，避免误删正在进行的合并
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+/// 失败任务的续做目录保留更久，给用户留出重试的时间
+const JOB_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// 用过的自定义工作目录，每行一个，最近用过的在前
+const WORK_DIRS_FILE: &str = "work-dirs.txt";
+const MAX_REMEMBERED_DIRS: usize = 8;
+const TEMP_PREFIXES: &[&str] = &[
+    "mc-image-",
+    "mc-office-",
+    "mc-html-",
+    "mc-text-",
+    "mc-fit-",
+    "mc-nup-",
+    "mc-sized-",
+    append::TEMP_PREFIX,
+    stamp::TEMP_PREFIX,
+    password::TEMP_PREFIX,
+    incremental::TEMP_PREFIX,
+    PREVIEW_PREFIX,
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CleanupReport {
+    pub removed_entries: usize,
+    pub freed_bytes: u64,
+}
+
+/// 清理工作目录中超过 `max_age` 的 `mc-*` 中间文件以及过期的续做任务目录。
+pub fn sweep_temp_dir(work_dir: &Path, max_age: Duration) -> CleanupReport {
+    let mut report = CleanupReport::default();
+    let Ok(entries) = fs::read_dir(work_dir) else {
+        return report;
+    };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if TEMP_PREFIXES.iter().any(|prefix| name.starts_with(prefix)) {
+            remove_if_stale(&entry.path(), max_age, &mut report);
+        }
+    }
+
+    if let Ok(jobs) = fs::read_dir(work_dir.join(JOBS_DIR_NAME)) {
+        for job in jobs.filter_map(|entry| entry.ok()) {
+            remove_if_stale(&job.path(), max_age.max(JOB_MAX_AGE), &mut report);
+        }
+    }
+
+    report
+}
+
+/// 启动时清理系统临时目录以及记录过的自定义工作目录（含其中的续做任务目录）。
+pub fn sweep_on_startup(state_dir: Option<&Path>) {
+    sweep_temp_dir(&std::env::temp_dir(), DEFAULT_MAX_AGE);
+    for dir in state_dir.map(remembered_work_dirs).unwrap_or_default() {
+        sweep_temp_dir(&dir, DEFAULT_MAX_AGE);
+    }
+}
+
+/// 记下本次合并使用的自定义工作目录，崩溃后下次启动也能找到其中的残留。
+pub fn remember_work_dir(state_dir: &Path, work_dir: &Path) -> std::io::Result<()> {
+    let mut dirs = remembered_work_dirs(state_dir);
+    dirs.retain(|dir| dir != work_dir);
+    dirs.insert(0, work_dir.to_path_buf());
+    dirs.truncate(MAX_REMEMBERED_DIRS);
+    let content: String = dirs
+        .iter()
+        .map(|dir| format!("{}\n", dir.to_string_lossy()))
+        .collect();
+    fs::create_dir_all(state_dir)?;
+    fs::write(state_dir.join(WORK_DIRS_FILE), content)
+}
+
+/// 记录过且仍然存在的工作目录，读出后重新规范化，已删除的目录直接忽略。
+fn remembered_work_dirs(state_dir: &Path) -> Vec<PathBuf> {
+    let Ok(content) = fs::read_to_string(state_dir.join(WORK_DIRS_FILE)) else {
+        return Vec::new();
+    };
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| Path::new(line).canonicalize().ok())
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+fn remove_if_stale(path: &Path, max_age: Duration, report: &mut CleanupReport) {
+    let Ok(meta) = fs::symlink_metadata(path) else {
+        return;
+    };
+    let age = meta
+        .modified()
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .unwrap_or_default();
+    if age < max_age {
+        return;
+    }
+
+    let size = if meta.is_dir() { dir_size(path) } else { meta.len() };
+    let removed = if meta.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    if removed.is_ok() {
+        report.removed_entries += 1;
+        report.freed_bytes += size;
+    }
+}
+
+fn dir_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|meta| meta.is_file())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remembered_work_dirs_are_recent_first_and_skip_missing() {
+        let state = tempfile::tempdir().unwrap();
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        let first_path = first.path().canonicalize().unwrap();
+        let second_path = second.path().canonicalize().unwrap();
+
+        remember_work_dir(state.path(), &first_path).unwrap();
+        remember_work_dir(state.path(), &second_path).unwrap();
+        remember_work_dir(state.path(), &first_path).unwrap();
+        assert_eq!(
+            remembered_work_dirs(state.path()),
+            vec![first_path.clone(), second_path],
+            "最近用过的目录应排在最前且不重复"
+        );
+
+        drop(second);
+        assert_eq!(
+            remembered_work_dirs(state.path()),
+            vec![first_path],
+            "已删除的目录应被忽略"
+        );
+    }
+}
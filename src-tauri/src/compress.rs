@@ -0,0 +1,382 @@
+use image::{
+    codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, GenericImageView, GrayImage, ImageBuffer,
+    ImageFormat, RgbImage,
+};
+use imageproc::contrast::{otsu_level, threshold};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{ccitt, qr_guard};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct DownsampleOptions {
+    /// 只处理有效分辨率高于该阈值的图片
+    pub threshold_dpi: f64,
+    /// 降采样后的目标分辨率
+    pub target_dpi: f64,
+    pub jpeg_quality: u8,
+    /// 把彩色、灰度图片二值化为黑白并以 CCITT G4 编码，适合只有文字的小票
+    pub binarize: bool,
+}
+
+impl Default for DownsampleOptions {
+    fn default() -> Self {
+        Self {
+            threshold_dpi: 225.0,
+            target_dpi: 150.0,
+            jpeg_quality: 80,
+            binarize: false,
+        }
+    }
+}
+
+impl DownsampleOptions {
+    /// 由 1–100 的画质档位换算：档位越低，目标 DPI 与 JPEG 质量越低；100 约为 200 DPI、质量 95。
+    pub fn from_quality(quality: u8) -> Self {
+        let quality = quality.clamp(1, 100);
+        let target_dpi = 72.0 + (200.0 - 72.0) * quality as f64 / 100.0;
+        Self {
+            threshold_dpi: target_dpi * 1.5,
+            target_dpi,
+            jpeg_quality: quality.clamp(20, 95),
+            binarize: false,
+        }
+    }
+}
+
+/// 自适应压缩时依次尝试的档位（目标 DPI, JPEG 质量），从轻到重。
+const ADAPTIVE_LEVELS: &[(f64, u8)] = &[(150.0, 75), (120.0, 65), (96.0, 55), (72.0, 40)];
+
+/// 生成比 `base` 更激进的压缩档位序列，用于逐级逼近输出大小上限。
+pub fn adaptive_levels(base: Option<&DownsampleOptions>) -> Vec<DownsampleOptions> {
+    ADAPTIVE_LEVELS
+        .iter()
+        .filter(|(dpi, quality)| match base {
+            Some(base) => *dpi < base.target_dpi || *quality < base.jpeg_quality,
+            None => true,
+        })
+        .map(|&(target_dpi, jpeg_quality)| DownsampleOptions {
+            threshold_dpi: target_dpi,
+            target_dpi,
+            jpeg_quality,
+            binarize: base.is_some_and(|base| base.binarize),
+        })
+        .collect()
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DownsampleStats {
+    /// 实际被替换的图片数量
+    pub replaced: usize,
+    /// 含二维码、因此少压或未压缩的图片数量
+    pub qr_protected: usize,
+}
+
+/// 找出页面上分辨率过高的图片 XObject，按目标 DPI 重新采样并以 JPEG 写回。
+/// 无法解码或重新编码后反而更大的图片保持原样；含二维码的图片不会缩到无法扫描。
+pub fn downsample_images(doc: &mut Document, opts: &DownsampleOptions) -> DownsampleStats {
+    let targets = collect_page_images(doc);
+    let mut stats = DownsampleStats::default();
+
+    for (image_id, (page_w_pt, page_h_pt)) in targets {
+        let Some(Object::Stream(stream)) = doc.objects.get_mut(&image_id) else {
+            continue;
+        };
+        if let Some((width, height, encoded)) =
+            resample_stream(stream, page_w_pt, page_h_pt, opts, &mut stats.qr_protected)
+        {
+            stream.dict.set("Width", width as i64);
+            stream.dict.set("Height", height as i64);
+            stream.dict.set("BitsPerComponent", 8i64);
+            stream.dict.set("Filter", Object::Name(b"DCTDecode".to_vec()));
+            stream.dict.remove(b"DecodeParms");
+            stream.set_content(encoded);
+            stream.allows_compression = false;
+            stats.replaced += 1;
+        }
+    }
+
+    stats
+}
+
+/// 把黑白图片改用 CCITT G4 编码，只在结果更小时替换。
+/// 原本就是 1 位或只含纯黑纯白像素的 Flate 图片无损转换；`binarize` 时其余图片先按 Otsu 阈值二值化。
+pub fn encode_bilevel_images(doc: &mut Document, binarize: bool) -> usize {
+    let targets: Vec<ObjectId> = collect_page_images(doc).into_keys().collect();
+    let mut replaced = 0;
+
+    for image_id in targets {
+        let Some(Object::Stream(stream)) = doc.objects.get_mut(&image_id) else {
+            continue;
+        };
+        // 带透明度或自定义解码数组的图片换编码后可能显示不同
+        if [b"SMask".as_slice(), b"Mask", b"Decode"]
+            .iter()
+            .any(|key| stream.dict.has(key))
+        {
+            continue;
+        }
+        let Some(bilevel) = decode_bilevel_source(stream, binarize) else {
+            continue;
+        };
+        let encoded = ccitt::encode_g4(&bilevel);
+        if encoded.len() >= stream.content.len() {
+            continue;
+        }
+        let mut parms = Dictionary::new();
+        parms.set("K", -1i64);
+        parms.set("Columns", bilevel.width() as i64);
+        parms.set("Rows", bilevel.height() as i64);
+        stream
+            .dict
+            .set("Filter", Object::Name(b"CCITTFaxDecode".to_vec()));
+        stream.dict.set("DecodeParms", parms);
+        stream.dict.set("BitsPerComponent", 1i64);
+        stream
+            .dict
+            .set("ColorSpace", Object::Name(b"DeviceGray".to_vec()));
+        stream.set_content(encoded);
+        stream.allows_compression = false;
+        replaced += 1;
+    }
+
+    replaced
+}
+
+/// 可改用 G4 编码的图片，解码为只含 0/255 的灰度图；其余返回 `None`。
+fn decode_bilevel_source(stream: &Stream, binarize: bool) -> Option<GrayImage> {
+    let dict = &stream.dict;
+    if dict.get(b"ImageMask").ok().and_then(|obj| obj.as_bool().ok()) == Some(true) {
+        return None;
+    }
+    let filter = dict.get(b"Filter").ok()?.as_name().ok()?;
+    let bits = dict.get(b"BitsPerComponent").ok()?.as_i64().ok()?;
+    if bits == 1 {
+        let color_space = dict.get(b"ColorSpace").ok().and_then(|obj| obj.as_name().ok());
+        if filter != b"FlateDecode"
+            || dict.has(b"DecodeParms")
+            || color_space != Some(b"DeviceGray".as_slice())
+        {
+            return None;
+        }
+        let width = dict.get(b"Width").ok()?.as_i64().ok()? as u32;
+        let height = dict.get(b"Height").ok()?.as_i64().ok()? as u32;
+        let raw = stream.decompressed_content().ok()?;
+        let row_bytes = (width as usize).div_ceil(8);
+        if raw.len() < row_bytes * height as usize {
+            return None;
+        }
+        return Some(GrayImage::from_fn(width, height, |x, y| {
+            let byte = raw[y as usize * row_bytes + x as usize / 8];
+            let bit = (byte >> (7 - x % 8)) & 1;
+            image::Luma([if bit == 1 { 255 } else { 0 }])
+        }));
+    }
+    // 不二值化时只检查无损的 Flate 图片，JPEG 的压缩噪点使其几乎不可能是纯黑白
+    if !binarize && filter != b"FlateDecode" {
+        return None;
+    }
+    let gray = decode_image_stream(stream)?.to_luma8();
+    if gray.pixels().all(|pixel| pixel[0] == 0 || pixel[0] == 255) {
+        Some(gray)
+    } else if binarize {
+        let level = otsu_level(&gray);
+        Some(threshold(&gray, level))
+    } else {
+        None
+    }
+}
+
+/// 图片对象 → 引用它的最小页面尺寸（pt），用于估算有效 DPI。
+fn collect_page_images(doc: &Document) -> BTreeMap<ObjectId, (f64, f64)> {
+    let mut images: BTreeMap<ObjectId, (f64, f64)> = BTreeMap::new();
+
+    for page_id in doc.get_pages().into_values() {
+        let Some([x0, y0, x1, y1]) = page_media_box(doc, page_id) else {
+            continue;
+        };
+        let page_size = ((x1 - x0).abs(), (y1 - y0).abs());
+
+        let (direct, inherited) = doc.get_page_resources(page_id);
+        let mut resource_dicts: Vec<&lopdf::Dictionary> = direct.into_iter().collect();
+        resource_dicts.extend(inherited.iter().filter_map(|id| doc.get_dictionary(*id).ok()));
+
+        for resources in resource_dicts {
+            let Some(xobjects) = resources
+                .get(b"XObject")
+                .ok()
+                .and_then(|obj| resolve(doc, obj))
+                .and_then(|obj| obj.as_dict().ok())
+            else {
+                continue;
+            };
+
+            for (_, value) in xobjects.iter() {
+                let Ok(image_id) = value.as_reference() else {
+                    continue;
+                };
+                let is_image = doc
+                    .get_object(image_id)
+                    .ok()
+                    .and_then(|obj| obj.as_stream().ok())
+                    .and_then(|stream| stream.dict.get(b"Subtype").ok())
+                    .and_then(|subtype| subtype.as_name().ok())
+                    == Some(b"Image".as_slice());
+                if !is_image {
+                    continue;
+                }
+                images
+                    .entry(image_id)
+                    .and_modify(|size| {
+                        size.0 = size.0.min(page_size.0);
+                        size.1 = size.1.min(page_size.1);
+                    })
+                    .or_insert(page_size);
+            }
+        }
+    }
+
+    images
+}
+
+pub(crate) fn page_media_box(doc: &Document, page_id: ObjectId) -> Option<[f64; 4]> {
+    page_box(doc, page_id, b"MediaBox")
+}
+
+/// 读取页面上（或从父节点继承）的矩形属性，如 MediaBox、CropBox。
+pub(crate) fn page_box(doc: &Document, page_id: ObjectId, key: &[u8]) -> Option<[f64; 4]> {
+    let mut current = doc.get_dictionary(page_id).ok()?;
+    loop {
+        if let Some(array) = current
+            .get(key)
+            .ok()
+            .and_then(|obj| resolve(doc, obj))
+            .and_then(|obj| obj.as_array().ok())
+        {
+            if array.len() == 4 {
+                let values: Vec<f64> = array.iter().filter_map(number_value).collect();
+                if values.len() == 4 {
+                    return Some([values[0], values[1], values[2], values[3]]);
+                }
+            }
+        }
+        let parent = current.get(b"Parent").ok()?.as_reference().ok()?;
+        current = doc.get_dictionary(parent).ok()?;
+    }
+}
+
+pub(crate) fn resolve<'a>(doc: &'a Document, obj: &'a Object) -> Option<&'a Object> {
+    match obj {
+        Object::Reference(id) => doc.get_object(*id).ok(),
+        other => Some(other),
+    }
+}
+
+pub(crate) fn number_value(obj: &Object) -> Option<f64> {
+    match obj {
+        Object::Integer(value) => Some(*value as f64),
+        Object::Real(value) => Some(*value as f64),
+        _ => None,
+    }
+}
+
+fn resample_stream(
+    stream: &Stream,
+    page_w_pt: f64,
+    page_h_pt: f64,
+    opts: &DownsampleOptions,
+    qr_protected: &mut usize,
+) -> Option<(u32, u32, Vec<u8>)> {
+    let image = decode_image_stream(stream)?;
+    let (width, height) = image.dimensions();
+    let page_long_in = page_w_pt.max(page_h_pt) / 72.0;
+    if page_long_in <= 0.0 {
+        return None;
+    }
+
+    let effective_dpi = width.max(height) as f64 / page_long_in;
+    if effective_dpi <= opts.threshold_dpi || opts.target_dpi <= 0.0 {
+        return None;
+    }
+
+    let mut scale = opts.target_dpi / effective_dpi;
+    if let Some(min_scale) = qr_guard::min_scale_for_qr(&image).filter(|min| scale < *min) {
+        *qr_protected += 1;
+        if min_scale >= 1.0 {
+            return None;
+        }
+        scale = min_scale;
+    }
+    let new_w = ((width as f64 * scale).round() as u32).max(1);
+    let new_h = ((height as f64 * scale).round() as u32).max(1);
+    let resized = image.resize_exact(new_w, new_h, FilterType::Triangle);
+
+    let encoded = encode_jpeg(&resized, opts.jpeg_quality)?;
+    if encoded.len() >= stream.content.len() {
+        return None;
+    }
+    Some((new_w, new_h, encoded))
+}
+
+pub(crate) fn encode_jpeg(image: &DynamicImage, quality: u8) -> Option<Vec<u8>> {
+    let (width, height) = image.dimensions();
+    let mut encoded = Vec::new();
+    JpegEncoder::new_with_quality(&mut encoded, quality.clamp(1, 100))
+        .encode(image.as_bytes(), width, height, image.color())
+        .ok()?;
+    Some(encoded)
+}
+
+/// 仅支持最常见的 8 位 DCT / Flate 编码 RGB、灰度图片，其余情况（CMYK、索引色、
+/// 带 Predictor 的 Flate、ImageMask 等）直接跳过，以免破坏原始内容。
+pub(crate) fn decode_image_stream(stream: &Stream) -> Option<DynamicImage> {
+    let dict = &stream.dict;
+    if dict.get(b"ImageMask").ok().and_then(|obj| obj.as_bool().ok()) == Some(true) {
+        return None;
+    }
+    if dict
+        .get(b"BitsPerComponent")
+        .ok()
+        .and_then(|obj| obj.as_i64().ok())
+        != Some(8)
+    {
+        return None;
+    }
+    let width = dict.get(b"Width").ok()?.as_i64().ok()? as u32;
+    let height = dict.get(b"Height").ok()?.as_i64().ok()? as u32;
+    let color_space = dict.get(b"ColorSpace").ok().and_then(|obj| obj.as_name().ok());
+    let filter = dict.get(b"Filter").ok()?.as_name().ok()?;
+
+    match filter {
+        b"DCTDecode" => {
+            if color_space == Some(b"DeviceCMYK".as_slice()) {
+                return None;
+            }
+            let image = image::load_from_memory_with_format(&stream.content, ImageFormat::Jpeg).ok()?;
+            match image {
+                DynamicImage::ImageLuma8(_) | DynamicImage::ImageRgb8(_) => Some(image),
+                _ => None,
+            }
+        }
+        b"FlateDecode" => {
+            if dict.has(b"DecodeParms") {
+                return None;
+            }
+            let raw = stream.decompressed_content().ok()?;
+            match color_space {
+                Some(b"DeviceRGB") => {
+                    let rgb: RgbImage = ImageBuffer::from_raw(width, height, raw)?;
+                    Some(DynamicImage::ImageRgb8(rgb))
+                }
+                Some(b"DeviceGray") => {
+                    let gray: GrayImage = ImageBuffer::from_raw(width, height, raw)?;
+                    Some(DynamicImage::ImageLuma8(gray))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
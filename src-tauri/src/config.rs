@@ -0,0 +1,210 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::{Duration, SystemTime},
+};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    compress::DownsampleOptions,
+    hook::PostMergeHook,
+    page_fit::PageSize,
+    portable::{self, AppDir},
+    webhook::WebhookConfig,
+    MergeError, MergeRequest, SortMode,
+};
+
+pub const CONFIG_FILE: &str = "config.toml";
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 管理员可预先放在应用配置目录下的 `config.toml`，修改后自动重新加载。
+/// 所有字段都可省略，省略时保持程序原有默认行为。
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct AppConfig {
+    /// 前端打开文件夹时的默认排序
+    pub default_sort: Option<SortMode>,
+    /// 请求未指定纸张时使用
+    pub page_size: Option<PageSize>,
+    /// 未填写输出文件名时的模板，支持 `{date}`、`{time}`、`{folder}`
+    pub output_template: Option<String>,
+    /// 请求未指定压缩参数时，按该 DPI 降采样
+    pub dpi: Option<f64>,
+    /// 允许扫描与合并的根目录，为空表示不限制
+    pub allowlist: Vec<PathBuf>,
+    /// 命名的合并方案，`MergeRequest.profile` 按名称引用，如 `[profiles."报销提交"]`
+    pub profiles: BTreeMap<String, MergeProfile>,
+    /// 合并成功后执行的外部命令，如 `[post_merge_hook] command = "upload.bat"`
+    pub post_merge_hook: Option<PostMergeHook>,
+    /// 合并完成后 POST 结果摘要的地址，未配置时不联网
+    pub webhook: Option<WebhookConfig>,
+    /// 重复发票库文件，可放在部门共享目录下供所有人共用
+    pub duplicate_db: Option<PathBuf>,
+    /// 解码、解析等并行处理的线程数上限，配置较弱的电脑可调小以免卡顿
+    pub max_threads: Option<usize>,
+    /// 单个文件大小上限（MB），请求未指定时使用；0 表示不限制
+    pub max_file_mb: Option<f64>,
+    /// 单张图片的解码时限（秒），请求未指定时使用
+    pub decode_timeout_secs: Option<u64>,
+}
+
+/// 一组预设的合并选项。请求中未填写的选项取方案中的值，方案中为真的开关会被打开。
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct MergeProfile {
+    pub page_size: Option<PageSize>,
+    pub downsample: Option<DownsampleOptions>,
+    pub max_output_mb: Option<f64>,
+    pub output_template: Option<String>,
+    pub crop_to_content: bool,
+    pub skip_blank_images: bool,
+    pub auto_enhance: bool,
+    pub remove_shadows: bool,
+    pub correct_perspective: bool,
+    pub denoise: bool,
+    pub failure_placeholders: bool,
+    pub failure_appendix: bool,
+    pub summary_page: bool,
+}
+
+impl MergeProfile {
+    pub fn apply(&self, req: &mut MergeRequest, folder: &Path) {
+        req.page_size = req.page_size.or(self.page_size);
+        req.downsample = req.downsample.take().or_else(|| self.downsample.clone());
+        req.max_output_mb = req.max_output_mb.or(self.max_output_mb);
+        if req.output_file_name.is_none() {
+            req.output_file_name = render_template(self.output_template.as_deref(), folder);
+        }
+        req.crop_to_content |= self.crop_to_content;
+        req.skip_blank_images |= self.skip_blank_images;
+        req.auto_enhance |= self.auto_enhance;
+        req.remove_shadows |= self.remove_shadows;
+        req.correct_perspective |= self.correct_perspective;
+        req.denoise |= self.denoise;
+        req.failure_placeholders |= self.failure_placeholders;
+        req.failure_appendix |= self.failure_appendix;
+        req.summary_page |= self.summary_page;
+    }
+}
+
+#[derive(Default)]
+pub struct ConfigState(RwLock<AppConfig>);
+
+impl AppConfig {
+    pub fn downsample(&self) -> Option<DownsampleOptions> {
+        self.dpi.filter(|dpi| *dpi > 0.0).map(|dpi| DownsampleOptions {
+            threshold_dpi: dpi * 1.5,
+            target_dpi: dpi,
+            ..Default::default()
+        })
+    }
+
+    /// 按模板生成输出文件名（不含扩展名），模板为空时返回 `None`。
+    pub fn output_name(&self, folder: &Path) -> Option<String> {
+        render_template(self.output_template.as_deref(), folder)
+    }
+
+    /// 把请求引用的方案套用到请求上；方案不存在时报错，避免静默按默认设置合并。
+    pub fn apply_profile(&self, req: &mut MergeRequest, folder: &Path) -> Result<(), MergeError> {
+        let Some(name) = req.profile.clone() else {
+            return Ok(());
+        };
+        let profile = self.profiles.get(&name).ok_or(MergeError::UnknownProfile(name))?;
+        profile.apply(req, folder);
+        Ok(())
+    }
+
+    /// 配置了白名单时，文件夹必须位于其中某个目录之下。
+    pub fn ensure_allowed(&self, folder: &Path) -> Result<(), MergeError> {
+        if self.allowlist.is_empty() {
+            return Ok(());
+        }
+        let folder = folder.canonicalize().map_err(|_| MergeError::InvalidFolder)?;
+        let allowed = self
+            .allowlist
+            .iter()
+            .filter_map(|root| root.canonicalize().ok())
+            .any(|root| folder.starts_with(root));
+        if allowed {
+            Ok(())
+        } else {
+            Err(MergeError::FolderNotAllowed(
+                folder.to_string_lossy().into_owned(),
+            ))
+        }
+    }
+}
+
+/// 输出文件名模板，支持 `{date}`、`{time}`、`{folder}`。
+fn render_template(template: Option<&str>, folder: &Path) -> Option<String> {
+    let template = template?.trim();
+    if template.is_empty() {
+        return None;
+    }
+    let now = Local::now();
+    let folder_name = folder
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Some(
+        template
+            .replace("{date}", &now.format("%Y%m%d").to_string())
+            .replace("{time}", &now.format("%H%M").to_string())
+            .replace("{folder}", &folder_name),
+    )
+}
+
+/// 当前生效的配置；以插件形式嵌入、未托管配置状态时返回默认值。
+pub fn current(app: &AppHandle) -> AppConfig {
+    app.try_state::<ConfigState>()
+        .and_then(|state| state.0.read().ok().map(|config| config.clone()))
+        .unwrap_or_default()
+}
+
+pub fn config_path(app: &AppHandle) -> Option<PathBuf> {
+    portable::app_dir(app, AppDir::Config).map(|dir| dir.join(CONFIG_FILE))
+}
+
+/// 立即加载一次，然后在后台轮询修改时间；变化后重新加载并发出 `config-changed`。
+/// 解析失败时保留上一份配置，并以 `config-error` 事件提示。
+pub fn watch(app: AppHandle) {
+    let Some(path) = config_path(&app) else {
+        return;
+    };
+    std::thread::spawn(move || {
+        let mut last_modified: Option<SystemTime> = None;
+        let mut loaded_once = false;
+        loop {
+            let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok();
+            if modified != last_modified || !loaded_once {
+                last_modified = modified;
+                loaded_once = true;
+                match load(&path) {
+                    Ok(config) => {
+                        if let Ok(mut current) = app.state::<ConfigState>().0.write() {
+                            *current = config.clone();
+                        }
+                        let _ = app.emit_all("config-changed", config);
+                    }
+                    Err(message) => {
+                        let _ = app.emit_all("config-error", message);
+                    }
+                }
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+fn load(path: &Path) -> Result<AppConfig, String> {
+    match fs::read_to_string(path) {
+        Ok(text) => toml::from_str(&text).map_err(|err| format!("{}: {err}", path.display())),
+        // 文件不存在（或被删除）时回到默认配置
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(AppConfig::default()),
+        Err(err) => Err(format!("{}: {err}", path.display())),
+    }
+}
@@ -0,0 +1,23 @@
+use std::path::{Path, PathBuf};
+
+use crate::{plan::pdf_page_count, sniff, InvoiceFile, MergeError};
+
+/// 校验封面或插入用的模板 PDF（报销单、审批单等）：必须存在、能解析且至少有一页。
+/// 模板可以放在任意位置，不要求位于源文件夹内。
+pub fn resolve(path: &str) -> Result<PathBuf, MergeError> {
+    let cover = Path::new(path)
+        .canonicalize()
+        .map_err(|_| MergeError::Pdf(format!("模板文件不存在: {path}")))?;
+    if !cover.is_file() || sniff::sniff_extension(&cover) != Some("pdf") {
+        return Err(MergeError::Pdf(format!("模板不是 PDF 文件: {path}")));
+    }
+    if pdf_page_count(&cover)? == 0 {
+        return Err(MergeError::Pdf(format!("模板没有任何页面: {path}")));
+    }
+    Ok(cover)
+}
+
+/// 模板或追加目标本身也在源文件夹中时，从待合并的文件里去掉，避免重复出现。
+pub fn exclude(files: &mut Vec<InvoiceFile>, path: &Path) {
+    files.retain(|file| Path::new(&file.path).canonicalize().ok().as_deref() != Some(path));
+}
@@ -0,0 +1,132 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::{
+    backtrace::Backtrace,
+    fs,
+    io::Write,
+    panic,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+
+use crate::MergeError;
+
+const REPORT_PREFIX: &str = "crash-";
+
+static REPORT_DIR: OnceLock<PathBuf> = OnceLock::new();
+static LAST_OPERATION: Mutex<Option<String>> = Mutex::new(None);
+
+/// 本地保存的崩溃报告，用户反馈问题时可选择附上。
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CrashReport {
+    pub file_name: String,
+    pub created_ts: i64,
+    pub content: String,
+}
+
+/// 安装 panic 钩子：先写报告，再交给默认钩子输出到终端。
+/// 只能捕获 Rust panic；PDFium 等原生库的段错误不会经过这里。
+pub fn install(dir: PathBuf) {
+    let _ = REPORT_DIR.set(dir);
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|msg| msg.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        let location = info
+            .location()
+            .map(|loc| format!("{}:{}", loc.file(), loc.line()))
+            .unwrap_or_default();
+        let _ = write_report(&message, &location);
+        default_hook(info);
+    }));
+}
+
+/// 记录当前正在进行的操作，写入随后可能发生的崩溃报告。不要传入完整路径。
+pub fn set_operation(operation: impl Into<String>) {
+    if let Ok(mut last) = LAST_OPERATION.lock() {
+        *last = Some(operation.into());
+    }
+}
+
+fn write_report(message: &str, location: &str) -> std::io::Result<()> {
+    let Some(dir) = REPORT_DIR.get() else {
+        return Ok(());
+    };
+    fs::create_dir_all(dir)?;
+
+    let now = Local::now();
+    // panic 可能发生在持有锁的线程里，拿不到就不写
+    let operation = LAST_OPERATION
+        .try_lock()
+        .ok()
+        .and_then(|last| last.clone())
+        .unwrap_or_else(|| "未知".to_string());
+
+    let report = format!(
+        "版本: {}\n时间: {}\n系统: {} {}\n线程: {}\n最后操作: {}\n位置: {}\n信息: {}\n\n调用栈:\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        now.format("%Y-%m-%d %H:%M:%S"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        std::thread::current().name().unwrap_or("unnamed"),
+        operation,
+        location,
+        message,
+        Backtrace::force_capture(),
+    );
+
+    let path = dir.join(format!("{REPORT_PREFIX}{}.txt", now.format("%Y%m%d-%H%M%S-%3f")));
+    fs::File::create(path)?.write_all(sanitize(&report).as_bytes())
+}
+
+/// 把用户主目录替换为 `~`，避免报告中出现用户名。
+fn sanitize(text: &str) -> String {
+    let home = std::env::var("USERPROFILE")
+        .or_else(|_| std::env::var("HOME"))
+        .ok()
+        .filter(|home| home.len() > 1);
+    match home {
+        Some(home) => text.replace(&home, "~").replace(&home.replace('\\', "/"), "~"),
+        None => text.to_string(),
+    }
+}
+
+pub fn list_reports(dir: &Path) -> Result<Vec<CrashReport>, MergeError> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(Vec::new());
+    };
+    let mut reports = Vec::new();
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        if !file_name.starts_with(REPORT_PREFIX) {
+            continue;
+        }
+        let created_ts = entry
+            .metadata()
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|since| since.as_secs() as i64)
+            .unwrap_or_default();
+        reports.push(CrashReport {
+            content: fs::read_to_string(entry.path())?,
+            file_name,
+            created_ts,
+        });
+    }
+    reports.sort_by(|a, b| b.created_ts.cmp(&a.created_ts));
+    Ok(reports)
+}
+
+/// 只删除报告目录下以 `crash-` 开头的文件，拒绝带路径的文件名。
+pub fn delete_report(dir: &Path, file_name: &str) -> Result<(), MergeError> {
+    if !file_name.starts_with(REPORT_PREFIX) || file_name.contains(['/', '\\']) || file_name.contains("..") {
+        return Err(MergeError::Unsupported(file_name.to_string()));
+    }
+    fs::remove_file(dir.join(file_name))?;
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use image::{DynamicImage, GrayImage};
+use lopdf::{Document, Object};
+use std::path::Path;
+
+use crate::{
+    page_fit::{page_rotation, page_visible_box},
+    raster, MergeError,
+};
+
+/// 检测内容边界只需要粗略的位图
+const DETECT_DPI: f64 = 50.0;
+/// 灰度低于该值视为墨迹（扫描件的纸张底色通常在 220 以上）
+const INK_THRESHOLD: u8 = 180;
+/// 一行/一列至少有这么多墨迹像素才算有内容，过滤零星噪点
+const MIN_INK_PIXELS: u32 = 2;
+pub const DEFAULT_CROP_PADDING_MM: f64 = 5.0;
+
+/// 按每页实际内容的外接矩形（外扩 `padding_mm`）收紧 CropBox。
+/// `path` 须与 `doc` 为同一份文件，用于渲染检测；空白页保持原样。
+pub fn crop_pages_to_content(doc: &mut Document, path: &Path, padding_mm: f64) -> Result<usize, MergeError> {
+    let renders = raster::rasterize_pdf(path, DETECT_DPI)?;
+    let padding = padding_mm.max(0.0) * 72.0 / 25.4;
+    let mut cropped = 0;
+
+    for (page_id, image) in doc.get_pages().into_values().zip(renders) {
+        let Some([bx0, by0, bx1, by1]) = page_visible_box(doc, page_id) else {
+            continue;
+        };
+        let Some(bounds) = content_bounds(&image) else {
+            continue;
+        };
+        let (left, bottom) = (bx0.min(bx1), by0.min(by1));
+        let (width, height) = ((bx1 - bx0).abs(), (by1 - by0).abs());
+
+        let [x0, y0, x1, y1] = unrotate(bounds, page_rotation(doc, page_id));
+        let mut crop = [
+            left + x0 * width - padding,
+            bottom + y0 * height - padding,
+            left + x1 * width + padding,
+            bottom + y1 * height + padding,
+        ];
+        // 不超出原有的可见区域
+        crop[0] = crop[0].max(left);
+        crop[1] = crop[1].max(bottom);
+        crop[2] = crop[2].min(left + width);
+        crop[3] = crop[3].min(bottom + height);
+        if crop[2] <= crop[0] || crop[3] <= crop[1] {
+            continue;
+        }
+
+        doc.get_object_mut(page_id)
+            .and_then(|obj| obj.as_dict_mut())
+            .map_err(|err| MergeError::Pdf(err.to_string()))?
+            .set(
+                "CropBox",
+                crop.iter().map(|v| Object::Real(*v as _)).collect::<Vec<_>>(),
+            );
+        cropped += 1;
+    }
+    Ok(cropped)
+}
+
+/// 渲染图（已应用 /Rotate，原点在左上）中内容的归一化边界 (u0, v0, u1, v1)。
+fn content_bounds(image: &DynamicImage) -> Option<[f64; 4]> {
+    let gray: GrayImage = image.to_luma8();
+    let (width, height) = gray.dimensions();
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let mut columns = vec![0u32; width as usize];
+    let mut rows = vec![0u32; height as usize];
+    for (x, y, pixel) in gray.enumerate_pixels() {
+        if pixel.0[0] < INK_THRESHOLD {
+            columns[x as usize] += 1;
+            rows[y as usize] += 1;
+        }
+    }
+    let first = |counts: &[u32]| counts.iter().position(|count| *count >= MIN_INK_PIXELS);
+    let last = |counts: &[u32]| counts.iter().rposition(|count| *count >= MIN_INK_PIXELS);
+    let (u0, u1) = (first(&columns)?, last(&columns)?);
+    let (v0, v1) = (first(&rows)?, last(&rows)?);
+    Some([
+        u0 as f64 / width as f64,
+        v0 as f64 / height as f64,
+        (u1 + 1) as f64 / width as f64,
+        (v1 + 1) as f64 / height as f64,
+    ])
+}
+
+/// 把显示坐标（已旋转、y 向下）的归一化矩形换算回页面坐标（未旋转、y 向上）。
+fn unrotate([u0, v0, u1, v1]: [f64; 4], rotation: i64) -> [f64; 4] {
+    let map = |u: f64, v: f64| match rotation {
+        90 => (v, u),
+        180 => (1.0 - u, v),
+        270 => (1.0 - v, 1.0 - u),
+        _ => (u, 1.0 - v),
+    };
+    let (ax, ay) = map(u0, v0);
+    let (bx, by) = map(u1, v1);
+    [ax.min(bx), ay.min(by), ax.max(bx), ay.max(by)]
+}
@@ -0,0 +1,34 @@
+use std::{sync::mpsc, thread, time::Duration};
+
+use crate::MergeError;
+
+/// 未设置时单张图片的解码时限（秒）
+pub const DEFAULT_DECODE_TIMEOUT_SECS: u64 = 60;
+
+/// 在单独的线程中执行解码，超过时限即放弃等待并返回 `DecodeTimeout`。
+/// 损坏的 HEIC、GIF 可能让 libheif 或 image 陷入死循环，这样至多拖慢一个文件而不会卡住整个合并。
+/// 超时的线程无法被强行终止，只能留在后台直到自行结束，其结果会被丢弃。
+pub fn run<T, F>(limit: Duration, decode: F) -> Result<T, MergeError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, MergeError> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    thread::Builder::new().name("decode".into()).spawn(move || {
+        let _ = sender.send(decode());
+    })?;
+    match receiver.recv_timeout(limit) {
+        Ok(result) => result,
+        Err(mpsc::RecvTimeoutError::Timeout) => Err(MergeError::DecodeTimeout(limit.as_secs())),
+        // 解码线程 panic，没有发回结果
+        Err(mpsc::RecvTimeoutError::Disconnected) => Err(MergeError::Image("解码过程异常中止".into())),
+    }
+}
+
+/// 请求中的秒数，未设置或为 0 时取默认值
+pub fn limit(secs: Option<u64>) -> Duration {
+    Duration::from_secs(
+        secs.filter(|secs| *secs > 0)
+            .unwrap_or(DEFAULT_DECODE_TIMEOUT_SECS),
+    )
+}
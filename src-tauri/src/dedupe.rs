@@ -0,0 +1,152 @@
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+
+/// 只对这些类型的字典对象去重；页面等结构性对象即使内容相同也必须保持独立
+const SHAREABLE_DICT_TYPES: &[&[u8]] = &[b"Font", b"FontDescriptor", b"ExtGState"];
+const MAX_ROUNDS: usize = 4;
+
+/// 对内容完全相同的流（字体文件、图片、内容流）和字体类字典做哈希去重，
+/// 把引用统一改写到同一个对象上。同一开票方的多张发票通常嵌入同一份字体，
+/// 去重后体积可显著下降。返回被移除的对象数量。
+pub fn deduplicate_resources(doc: &mut Document) -> usize {
+    let mut removed = 0usize;
+    // 流去重后，引用它们的字体字典才会变得相同，因此迭代直到不再变化
+    for _ in 0..MAX_ROUNDS {
+        let remap = find_duplicates(doc);
+        if remap.is_empty() {
+            break;
+        }
+        for id in remap.keys() {
+            doc.objects.remove(id);
+        }
+        for object in doc.objects.values_mut() {
+            rewrite_references(object, &remap);
+        }
+        for (_, value) in doc.trailer.iter_mut() {
+            rewrite_references(value, &remap);
+        }
+        removed += remap.len();
+    }
+    removed
+}
+
+fn find_duplicates(doc: &Document) -> BTreeMap<ObjectId, ObjectId> {
+    let mut canonical: HashMap<Vec<u8>, ObjectId> = HashMap::new();
+    let mut remap = BTreeMap::new();
+
+    for (id, object) in &doc.objects {
+        let shareable = match object {
+            Object::Stream(_) => true,
+            Object::Dictionary(dict) => dict
+                .get(b"Type")
+                .and_then(Object::as_name)
+                .map(|name| SHAREABLE_DICT_TYPES.contains(&name))
+                .unwrap_or(false),
+            _ => false,
+        };
+        if !shareable {
+            continue;
+        }
+
+        let mut hasher = Sha256::new();
+        hash_object(object, &mut hasher);
+        let digest = hasher.finalize().to_vec();
+        match canonical.get(&digest) {
+            Some(existing) => {
+                remap.insert(*id, *existing);
+            }
+            None => {
+                canonical.insert(digest, *id);
+            }
+        }
+    }
+    remap
+}
+
+fn hash_object(object: &Object, hasher: &mut Sha256) {
+    match object {
+        Object::Null => hasher.update(b"n"),
+        Object::Boolean(value) => hasher.update(if *value { b"t" } else { b"f" }),
+        Object::Integer(value) => {
+            hasher.update(b"i");
+            hasher.update(value.to_le_bytes());
+        }
+        Object::Real(value) => {
+            hasher.update(b"r");
+            hasher.update((*value as f64).to_bits().to_le_bytes());
+        }
+        Object::Name(name) => {
+            hasher.update(b"/");
+            hash_bytes(name, hasher);
+        }
+        Object::String(bytes, _) => {
+            hasher.update(b"s");
+            hash_bytes(bytes, hasher);
+        }
+        Object::Array(items) => {
+            hasher.update(b"[");
+            hasher.update((items.len() as u64).to_le_bytes());
+            for item in items {
+                hash_object(item, hasher);
+            }
+        }
+        Object::Dictionary(dict) => hash_dictionary(dict, hasher),
+        Object::Stream(stream) => {
+            hasher.update(b"S");
+            hash_dictionary(&stream.dict, hasher);
+            hash_bytes(&stream.content, hasher);
+        }
+        Object::Reference((number, generation)) => {
+            hasher.update(b"R");
+            hasher.update(number.to_le_bytes());
+            hasher.update(generation.to_le_bytes());
+        }
+    }
+}
+
+/// 字典键按字典序参与哈希，忽略 Length（可能是间接引用，且由内容决定）。
+fn hash_dictionary(dict: &Dictionary, hasher: &mut Sha256) {
+    let mut entries: Vec<(&Vec<u8>, &Object)> = dict
+        .iter()
+        .filter(|(key, _)| key.as_slice() != b"Length")
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    hasher.update(b"<");
+    hasher.update((entries.len() as u64).to_le_bytes());
+    for (key, value) in entries {
+        hash_bytes(key, hasher);
+        hash_object(value, hasher);
+    }
+}
+
+fn hash_bytes(bytes: &[u8], hasher: &mut Sha256) {
+    hasher.update((bytes.len() as u64).to_le_bytes());
+    hasher.update(bytes);
+}
+
+pub(crate) fn rewrite_references(object: &mut Object, remap: &BTreeMap<ObjectId, ObjectId>) {
+    match object {
+        Object::Reference(id) => {
+            if let Some(target) = remap.get(id) {
+                *id = *target;
+            }
+        }
+        Object::Array(items) => {
+            for item in items {
+                rewrite_references(item, remap);
+            }
+        }
+        Object::Dictionary(dict) => {
+            for (_, value) in dict.iter_mut() {
+                rewrite_references(value, remap);
+            }
+        }
+        Object::Stream(stream) => {
+            for (_, value) in stream.dict.iter_mut() {
+                rewrite_references(value, remap);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,295 @@
+use serde::{Deserialize, Serialize};
+use std::{path::Path, sync::Mutex};
+
+use crate::{MergeError, SortMode};
+
+/// 自定义 URL 协议名，形如 `invoicemerge://merge?folder=...&sort=name`。
+pub const SCHEME: &str = "invoicemerge";
+
+/// 经过校验的深度链接，前端据此扫描文件夹并直接开始合并。
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeepLink {
+    pub folder: String,
+    pub sort_mode: SortMode,
+    pub descending: bool,
+    pub output_file_name: Option<String>,
+}
+
+/// 启动参数中携带的深度链接；解析失败时保留错误信息交给前端提示。
+#[derive(Default)]
+pub struct PendingLink(pub Mutex<Option<Result<DeepLink, String>>>);
+
+/// 操作系统以 `"<exe>" "invoicemerge://..."` 启动本程序，从参数中找出该链接。
+pub fn link_from_args<I>(args: I) -> Option<String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let prefix = format!("{SCHEME}:");
+    args.into_iter().find(|arg| {
+        arg.get(..prefix.len())
+            .is_some_and(|head| head.eq_ignore_ascii_case(&prefix))
+    })
+}
+
+/// 解析并校验链接：只接受 `merge` 动作，文件夹必须是本机上已存在的绝对路径，未知参数直接拒绝。
+pub fn parse(url: &str) -> Result<DeepLink, MergeError> {
+    let invalid = |reason: &str| MergeError::InvalidLink(reason.to_string());
+
+    let rest = url
+        .get(..SCHEME.len() + 1)
+        .filter(|head| head.eq_ignore_ascii_case(&format!("{SCHEME}:")))
+        .map(|head| &url[head.len()..])
+        .ok_or_else(|| invalid("协议名不正确"))?;
+    let rest = rest.trim_start_matches('/');
+    let (action, query) = rest.split_once('?').unwrap_or((rest, ""));
+    if !action.trim_end_matches('/').eq_ignore_ascii_case("merge") {
+        return Err(invalid("仅支持 merge 动作"));
+    }
+
+    let mut folder = None;
+    let mut sort_mode = SortMode::FileNameAsc;
+    let mut descending = false;
+    let mut output_file_name = None;
+
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode(value).ok_or_else(|| invalid("参数编码无效"))?;
+        match key {
+            "folder" => folder = Some(value),
+            "sort" => {
+                sort_mode = match value.as_str() {
+                    "name" => SortMode::FileNameAsc,
+                    "modified" => SortMode::ModifiedAsc,
+                    "invoice_number" => SortMode::InvoiceNumberAsc,
+                    _ => return Err(invalid("sort 仅支持 name、modified、invoice_number")),
+                }
+            }
+            "order" => {
+                descending = match value.as_str() {
+                    "asc" => false,
+                    "desc" => true,
+                    _ => return Err(invalid("order 仅支持 asc、desc")),
+                }
+            }
+            "output" => {
+                if value.contains(['/', '\\']) {
+                    return Err(MergeError::InvalidOutputName);
+                }
+                output_file_name = Some(value).filter(|name| !name.trim().is_empty());
+            }
+            other => return Err(invalid(&format!("未知参数 {other}"))),
+        }
+    }
+
+    let folder = folder.ok_or_else(|| invalid("缺少 folder 参数"))?;
+    // 先于任何文件系统访问拒绝网络路径：对 `\\host\share` 调用 is_dir 就会向对方发起 SMB 连接
+    if !is_local_folder(&folder) {
+        return Err(invalid("仅支持本机文件夹"));
+    }
+    if !Path::new(&folder).is_dir() {
+        return Err(MergeError::InvalidFolder);
+    }
+
+    Ok(DeepLink {
+        folder,
+        sort_mode,
+        descending,
+        output_file_name,
+    })
+}
+
+/// 只接受本机磁盘上的绝对路径：拒绝 UNC（含 `\\?\`、`\\.\` 设备路径）、相对路径和网络映射盘。
+fn is_local_folder(folder: &str) -> bool {
+    if folder.starts_with(['\\', '/']) && folder[1..].starts_with(['\\', '/']) {
+        return false;
+    }
+    let path = Path::new(folder);
+    path.is_absolute() && !is_remote_drive(path)
+}
+
+#[cfg(windows)]
+fn is_remote_drive(path: &Path) -> bool {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDriveTypeW;
+    const DRIVE_REMOTE: u32 = 4;
+
+    let Some(std::path::Component::Prefix(prefix)) = path.components().next() else {
+        return true;
+    };
+    let root: Vec<u16> = prefix
+        .as_os_str()
+        .encode_wide()
+        .chain("\\".encode_utf16())
+        .chain([0])
+        .collect();
+    // SAFETY: root 是以 0 结尾的宽字符串，调用期间有效
+    unsafe { GetDriveTypeW(root.as_ptr()) == DRIVE_REMOTE }
+}
+
+#[cfg(not(windows))]
+fn is_remote_drive(_path: &Path) -> bool {
+    false
+}
+
+/// 解码 `%XX` 与 `+`，结果必须是合法 UTF-8。
+fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'%' => {
+                let hex = value.get(index + 1..index + 3)?;
+                decoded.push(u8::from_str_radix(hex, 16).ok()?);
+                index += 3;
+            }
+            b'+' => {
+                decoded.push(b' ');
+                index += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// 在 HKCU 下注册 URL 协议，指向当前可执行文件；重复调用只会覆盖原有值。
+#[cfg(windows)]
+pub fn register_scheme() -> Result<(), MergeError> {
+    let exe = std::env::current_exe()?;
+    let key = format!(r"HKCU\Software\Classes\{SCHEME}");
+    let command_line = format!("\"{}\" \"%1\"", exe.display());
+    crate::shell_menu::run_reg(&["add", &key, "/ve", "/d", "URL:Invoice Merge", "/f"])?;
+    crate::shell_menu::run_reg(&["add", &key, "/v", "URL Protocol", "/d", "", "/f"])?;
+    crate::shell_menu::run_reg(&[
+        "add",
+        &format!(r"{key}\shell\open\command"),
+        "/ve",
+        "/d",
+        &command_line,
+        "/f",
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 把路径中除字母数字外的字节都编码，模拟浏览器生成的链接
+    fn encode(value: &str) -> String {
+        value
+            .bytes()
+            .map(|byte| {
+                if byte.is_ascii_alphanumeric() {
+                    (byte as char).to_string()
+                } else {
+                    format!("%{byte:02X}")
+                }
+            })
+            .collect()
+    }
+
+    fn folder() -> (tempfile::TempDir, String) {
+        let dir = tempfile::tempdir().unwrap();
+        let encoded = encode(&dir.path().to_string_lossy());
+        (dir, encoded)
+    }
+
+    #[test]
+    fn parses_valid_links() {
+        let (dir, folder) = folder();
+        let link = parse(&format!("invoicemerge://merge?folder={folder}")).unwrap();
+        assert_eq!(link.folder, dir.path().to_string_lossy());
+        assert_eq!(link.sort_mode, SortMode::FileNameAsc);
+        assert!(!link.descending);
+        assert_eq!(link.output_file_name, None);
+
+        let link = parse(&format!(
+            "InvoiceMerge:merge/?sort=invoice_number&order=desc&output=3%E6%9C%88+%E6%8A%A5%E9%94%80&folder={folder}"
+        ))
+        .unwrap();
+        assert_eq!(link.sort_mode, SortMode::InvoiceNumberAsc);
+        assert!(link.descending);
+        assert_eq!(link.output_file_name.as_deref(), Some("3月 报销"));
+
+        let link = parse(&format!(
+            "invoicemerge:///merge?folder={folder}&output=++&sort=modified&&"
+        ))
+        .unwrap();
+        assert_eq!(link.sort_mode, SortMode::ModifiedAsc);
+        assert_eq!(link.output_file_name, None);
+    }
+
+    #[test]
+    fn rejects_malformed_links() {
+        let (_dir, folder) = folder();
+        let cases = [
+            format!("http://merge?folder={folder}"),
+            format!("invoicemerg://merge?folder={folder}"),
+            format!("invoicemerge://delete?folder={folder}"),
+            format!("invoicemerge://merge/extra?folder={folder}"),
+            "invoicemerge://merge".to_string(),
+            "invoicemerge://merge?sort=name".to_string(),
+            format!("invoicemerge://merge?folder={folder}&sort=size"),
+            format!("invoicemerge://merge?folder={folder}&order=up"),
+            format!("invoicemerge://merge?folder={folder}&unknown=1"),
+            format!("invoicemerge://merge?folder={folder}&folder2=x"),
+            format!("invoicemerge://merge?folder={folder}&output=%4"),
+            format!("invoicemerge://merge?folder={folder}&output=%ZZ"),
+            format!("invoicemerge://merge?folder={folder}&output=%FF%FE"),
+            "invoicemerge:".to_string(),
+            String::new(),
+            // 网络路径与相对路径
+            format!("invoicemerge://merge?folder={}", encode(r"\\attacker\share")),
+            format!("invoicemerge://merge?folder={}", encode("//attacker/share")),
+            format!(
+                "invoicemerge://merge?folder={}",
+                encode(r"\\?\UNC\attacker\share")
+            ),
+            format!("invoicemerge://merge?folder={}", encode("invoices")),
+        ];
+        for url in cases {
+            assert!(
+                matches!(parse(&url), Err(MergeError::InvalidLink(_))),
+                "{url:?} 应被拒绝"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_paths_in_output_and_missing_folders() {
+        let (_dir, folder) = folder();
+        for output in ["a%2Fb", "..%5Cup", "%2Fabs"] {
+            assert!(matches!(
+                parse(&format!("invoicemerge://merge?folder={folder}&output={output}")),
+                Err(MergeError::InvalidOutputName)
+            ));
+        }
+        let missing = encode(
+            &std::env::temp_dir()
+                .join("mc-no-such-folder-7f3a")
+                .to_string_lossy(),
+        );
+        assert!(matches!(
+            parse(&format!("invoicemerge://merge?folder={missing}")),
+            Err(MergeError::InvalidFolder)
+        ));
+    }
+
+    #[test]
+    fn finds_link_in_arguments() {
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            link_from_args(args(&["app.exe", "INVOICEMERGE://merge?folder=x"])).as_deref(),
+            Some("INVOICEMERGE://merge?folder=x")
+        );
+        assert_eq!(
+            link_from_args(args(&["app.exe", "--hidden", "C:\\invoices"])),
+            None
+        );
+        assert_eq!(link_from_args(args(&["app.exe", "invoice"])), None);
+    }
+}
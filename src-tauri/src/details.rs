@@ -0,0 +1,62 @@
+use lopdf::Document;
+use serde::{Deserialize, Serialize};
+use std::{path::Path, thread};
+
+use crate::{open_heic, pipeline_ext, InvoiceFile, IMAGE_EXTENSIONS};
+
+/// 列表徽标所需的附加信息，只在扫描时显式请求才计算。
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FileDetails {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub page_count: Option<u32>,
+    pub encrypted: bool,
+}
+
+/// 用至多 `workers` 个线程分块并行读取各文件的尺寸、页数与加密状态。
+pub fn enrich_files(files: &mut [InvoiceFile], workers: usize) {
+    let chunk_size = files.len().div_ceil(workers).max(1);
+    thread::scope(|scope| {
+        for chunk in files.chunks_mut(chunk_size) {
+            scope.spawn(move || {
+                for file in chunk {
+                    file.details = Some(file_details(file));
+                }
+            });
+        }
+    });
+}
+
+fn file_details(file: &InvoiceFile) -> FileDetails {
+    let path = Path::new(&file.path);
+    let mut details = FileDetails::default();
+    match pipeline_ext(&file.ext, path).as_deref() {
+        Some("pdf") => {
+            if let Ok(doc) = Document::load(path) {
+                details.page_count = Some(doc.get_pages().len() as u32);
+                details.encrypted = doc.is_encrypted();
+            }
+        }
+        Some("heic") => {
+            if let Ok(handle) = open_heic(path) {
+                details.width = Some(handle.width());
+                details.height = Some(handle.height());
+                details.page_count = Some(1);
+            }
+        }
+        Some(ext) if IMAGE_EXTENSIONS.contains(&ext) => {
+            // 只读取文件头，不解码像素
+            if let Ok((width, height)) = image::io::Reader::open(path)
+                .and_then(|reader| reader.with_guessed_format())
+                .map_err(image::ImageError::IoError)
+                .and_then(|reader| reader.into_dimensions())
+            {
+                details.width = Some(width);
+                details.height = Some(height);
+                details.page_count = Some(1);
+            }
+        }
+        _ => {}
+    }
+    details
+}
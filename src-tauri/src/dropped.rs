@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+use crate::{attach_invoice_info, invoice_file_at, pipeline_ext, walk_folder, InvoiceFile};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RejectedPath {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DroppedPaths {
+    pub accepted: Vec<InvoiceFile>,
+    pub rejected: Vec<RejectedPath>,
+}
+
+/// 校验从资源管理器/访达拖入的路径：拖入的文件夹展开为其中的文件，
+/// 按内容识别类型、确认可读，并与当前列表（`existing` 为已有文件路径）及彼此去重。
+/// 指定 `folder` 时，不在该文件夹内的文件会被拒绝（合并时同样会拒绝）。
+pub fn validate_dropped_paths(paths: &[String], existing: &[String], folder: Option<&Path>) -> DroppedPaths {
+    let mut result = DroppedPaths::default();
+    let folder = folder.and_then(|folder| folder.canonicalize().ok());
+    let mut seen: HashSet<PathBuf> = existing
+        .iter()
+        .filter_map(|path| Path::new(path).canonicalize().ok())
+        .collect();
+
+    let mut candidates = Vec::new();
+    for path in paths {
+        let path = PathBuf::from(path);
+        if path.is_dir() {
+            let _ = walk_folder(&path, Some(&[] as &[String]), |file| {
+                candidates.push(PathBuf::from(file.path))
+            });
+        } else {
+            candidates.push(path);
+        }
+    }
+
+    for path in candidates {
+        let display = path.to_string_lossy().into_owned();
+        let mut reject = |reason: &str| {
+            result.rejected.push(RejectedPath {
+                path: display.clone(),
+                reason: reason.to_string(),
+            })
+        };
+
+        let Ok(canon) = path.canonicalize() else {
+            reject("文件不存在");
+            continue;
+        };
+        if folder.as_ref().is_some_and(|folder| !canon.starts_with(folder)) {
+            reject("不在当前文件夹内");
+            continue;
+        }
+        if !seen.insert(canon.clone()) {
+            reject("已在列表中");
+            continue;
+        }
+        let Ok(meta) = fs::metadata(&canon) else {
+            reject("无法读取文件信息");
+            continue;
+        };
+        if !meta.is_file() || File::open(&canon).is_err() {
+            reject("文件不可读");
+            continue;
+        }
+
+        let mut file = invoice_file_at(&path, &meta);
+        if pipeline_ext(&file.ext, &canon).is_none() {
+            reject("不支持的文件类型");
+            continue;
+        }
+        attach_invoice_info(&mut file);
+        result.accepted.push(file);
+    }
+    result
+}
@@ -0,0 +1,213 @@
+use lopdf::{Document, Object};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path, path::PathBuf};
+use tempfile::TempPath;
+
+use crate::{
+    compress::resolve,
+    text_page::{render_text_document, TextLine},
+    MergeError,
+};
+
+pub const XML_EXTENSIONS: &[&str] = &["xml"];
+
+/// 来自发票结构化 XML 的权威数据（PDF 内嵌附件或独立 XML 文件）。
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct InvoiceInfo {
+    pub invoice_number: String,
+    pub issue_date: String,
+    pub total_amount: String,
+    pub seller_name: String,
+    pub buyer_name: String,
+}
+
+pub fn parse_invoice_info(xml_text: &str) -> Option<InvoiceInfo> {
+    let xml = roxmltree::Document::parse(xml_text.trim_start_matches('\u{feff}')).ok()?;
+    let root = xml.root_element();
+    let field = |name: &str| -> String {
+        root.descendants()
+            .find(|node| node.has_tag_name(name))
+            .and_then(|node| node.text())
+            .map(|text| text.trim().to_string())
+            .unwrap_or_default()
+    };
+
+    let info = InvoiceInfo {
+        invoice_number: field("InvoiceNumber"),
+        issue_date: field("IssueTime"),
+        total_amount: field("TotalTax-includedAmount"),
+        seller_name: field("SellerName"),
+        buyer_name: field("BuyerName"),
+    };
+    (!info.invoice_number.is_empty()).then_some(info)
+}
+
+pub fn read_invoice_xml(path: &Path) -> Option<InvoiceInfo> {
+    parse_invoice_info(&fs::read_to_string(path).ok()?)
+}
+
+/// 从电子发票 PDF 的附件（EmbeddedFile）中查找 .xml 并解析发票数据。
+pub fn extract_embedded_invoice(path: &Path) -> Option<InvoiceInfo> {
+    let bytes = fs::read(path).ok()?;
+    // 绝大多数 PDF 没有附件，先做字节级粗筛，避免扫描时解析整个文档
+    if !bytes
+        .windows(b"EmbeddedFile".len())
+        .any(|window| window == b"EmbeddedFile")
+    {
+        return None;
+    }
+    let doc = Document::load_mem(&bytes).ok()?;
+
+    for object in doc.objects.values() {
+        let Ok(filespec) = object.as_dict() else {
+            continue;
+        };
+        let Ok(embedded) = filespec.get(b"EF") else {
+            continue;
+        };
+        let name = filespec
+            .get(b"UF")
+            .or_else(|_| filespec.get(b"F"))
+            .ok()
+            .and_then(pdf_text)
+            .unwrap_or_default();
+        if !name.to_ascii_lowercase().ends_with(".xml") {
+            continue;
+        }
+
+        let Some(stream) = resolve(&doc, embedded)
+            .and_then(|obj| obj.as_dict().ok())
+            .and_then(|ef| ef.get(b"F").ok())
+            .and_then(|obj| resolve(&doc, obj))
+            .and_then(|obj| obj.as_stream().ok())
+        else {
+            continue;
+        };
+        let content = stream
+            .decompressed_content()
+            .unwrap_or_else(|_| stream.content.clone());
+        if let Some(info) = parse_invoice_info(&String::from_utf8_lossy(&content)) {
+            return Some(info);
+        }
+    }
+    None
+}
+
+/// PDF 文本字符串可能是 UTF-16BE（带 BOM）或单字节编码。
+pub(crate) fn pdf_text(obj: &Object) -> Option<String> {
+    let Object::String(bytes, _) = obj else {
+        return None;
+    };
+    if let Some(utf16) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = utf16
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        Some(String::from_utf16_lossy(&units))
+    } else {
+        Some(String::from_utf8_lossy(bytes).into_owned())
+    }
+}
+
+/// 将全电发票（数电票）XML 中的关键信息按发票版式的顺序排成一页 PDF。
+pub fn render_einvoice_xml(path: &Path, work_dir: &Path) -> Result<(PathBuf, TempPath), MergeError> {
+    let raw = fs::read_to_string(path)?;
+    let xml = roxmltree::Document::parse(raw.trim_start_matches('\u{feff}'))
+        .map_err(|err| MergeError::Convert(format!("XML 解析失败: {err}")))?;
+    let root = xml.root_element();
+    if !root.has_tag_name("EInvoice") {
+        return Err(MergeError::Convert("不是全电发票 XML".into()));
+    }
+
+    let field = |name: &str| -> String {
+        root.descendants()
+            .find(|node| node.has_tag_name(name))
+            .and_then(|node| node.text())
+            .map(|text| text.trim().to_string())
+            .unwrap_or_default()
+    };
+
+    let mut lines = vec![
+        TextLine::new("电子发票（全面数字化的电子发票）", 16.0),
+        TextLine::blank(),
+        TextLine::new(format!("发票号码：{}", field("InvoiceNumber")), 10.0),
+        TextLine::new(format!("开票日期：{}", field("IssueTime")), 10.0),
+        TextLine::blank(),
+        TextLine::new("购买方信息", 11.0),
+        TextLine::new(format!("    名称：{}", field("BuyerName")), 10.0),
+        TextLine::new(
+            format!("    统一社会信用代码/纳税人识别号：{}", field("BuyerIdNum")),
+            10.0,
+        ),
+        TextLine::new("销售方信息", 11.0),
+        TextLine::new(format!("    名称：{}", field("SellerName")), 10.0),
+        TextLine::new(
+            format!("    统一社会信用代码/纳税人识别号：{}", field("SellerIdNum")),
+            10.0,
+        ),
+        TextLine::blank(),
+        TextLine::new(
+            "项目名称 | 规格型号 | 单位 | 数量 | 单价 | 金额 | 税率 | 税额",
+            10.0,
+        ),
+    ];
+
+    for item in root
+        .descendants()
+        .filter(|node| node.has_tag_name("IssuItemInformation"))
+    {
+        let item_field = |name: &str| -> String {
+            item.children()
+                .find(|node| node.has_tag_name(name))
+                .and_then(|node| node.text())
+                .map(|text| text.trim().to_string())
+                .unwrap_or_default()
+        };
+        lines.push(TextLine::new(
+            format!(
+                "{} | {} | {} | {} | {} | {} | {} | {}",
+                item_field("ItemName"),
+                item_field("SpecMod"),
+                item_field("MeaUnits"),
+                item_field("Quantity"),
+                item_field("UnPrice"),
+                item_field("Amount"),
+                item_field("TaxRate"),
+                item_field("ComTaxAm"),
+            ),
+            9.0,
+        ));
+    }
+
+    lines.extend([
+        TextLine::blank(),
+        TextLine::new(
+            format!(
+                "合计：金额 ¥{}    税额 ¥{}",
+                field("TotalAmWithoutTax"),
+                field("TotalTaxAm")
+            ),
+            10.0,
+        ),
+        TextLine::new(
+            format!(
+                "价税合计（大写）：{}    （小写）¥{}",
+                field("TotalTax-includedAmountInChinese"),
+                field("TotalTax-includedAmount")
+            ),
+            10.0,
+        ),
+        TextLine::blank(),
+        TextLine::new(format!("开票人：{}", field("Drawer")), 10.0),
+        // 只写文件名：生成页会随报销材料外发，不能带出本机目录结构
+        TextLine::new(
+            format!(
+                "（由 XML 数据生成：{}）",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            ),
+            7.0,
+        ),
+    ]);
+
+    render_text_document("E-Invoice", &lines, work_dir)
+}
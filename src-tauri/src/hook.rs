@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+use std::{path::Path, process::Command, time::Duration};
+
+use crate::office::{hide_console_window, run_with_timeout};
+
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+/// 警告中保留的输出长度，避免脚本刷屏
+const MAX_CAPTURED_CHARS: usize = 2000;
+
+/// 合并成功后执行的外部命令（上传脚本、杀毒扫描等），输出文件路径作为最后一个参数传入。
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct PostMergeHook {
+    pub command: String,
+    pub args: Vec<String>,
+    /// 超时后结束进程，默认 60 秒
+    pub timeout_secs: Option<u64>,
+}
+
+impl PostMergeHook {
+    /// 运行钩子；成功返回 `Ok`，失败、超时或无法启动时返回带输出的错误说明。
+    pub fn run(&self, output: &Path) -> Result<(), String> {
+        let mut command = Command::new(&self.command);
+        command.args(&self.args).arg(output);
+        hide_console_window(&mut command);
+
+        let timeout = Duration::from_secs(self.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS));
+        let result = run_with_timeout(&mut command, timeout)
+            .map_err(|err| format!("无法启动 {}: {err}", self.command))?;
+        let output = format!("{}{}", result.stdout, result.stderr);
+        let output: String = output.trim().chars().take(MAX_CAPTURED_CHARS).collect();
+
+        match result.status {
+            Some(status) if status.success() => Ok(()),
+            Some(status) => Err(format!("{} 退出码 {:?}：{output}", self.command, status.code())),
+            None => Err(format!(
+                "{} 超过 {} 秒未结束，已终止：{output}",
+                self.command,
+                timeout.as_secs()
+            )),
+        }
+    }
+}
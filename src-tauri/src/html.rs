@@ -0,0 +1,94 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+use tempfile::TempPath;
+
+use crate::{
+    office::{display_path, file_url, find_executable, hide_console_window, run_with_timeout},
+    MergeError,
+};
+
+pub const HTML_EXTENSIONS: &[&str] = &["html", "htm", "mhtml", "mht"];
+
+/// 页面引用的外部资源加载不出来时，浏览器可能一直不退出
+const RENDER_TIMEOUT: Duration = Duration::from_secs(60);
+
+const KNOWN_BROWSER_LOCATIONS: &[&str] = &[
+    r"C:\Program Files (x86)\Microsoft\Edge\Application\msedge.exe",
+    r"C:\Program Files\Microsoft\Edge\Application\msedge.exe",
+    r"C:\Program Files\Google\Chrome\Application\chrome.exe",
+    r"C:\Program Files (x86)\Google\Chrome\Application\chrome.exe",
+    "/Applications/Microsoft Edge.app/Contents/MacOS/Microsoft Edge",
+    "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+    "/Applications/Chromium.app/Contents/MacOS/Chromium",
+];
+
+/// 查找可无头打印 PDF 的 Chromium 系浏览器（Windows 自带 Edge 即可满足）。
+pub fn find_headless_browser() -> Option<PathBuf> {
+    let names: &[&str] = if cfg!(windows) {
+        &["msedge.exe", "chrome.exe"]
+    } else {
+        &[
+            "microsoft-edge",
+            "google-chrome",
+            "google-chrome-stable",
+            "chromium",
+            "chromium-browser",
+        ]
+    };
+    find_executable(names, KNOWN_BROWSER_LOCATIONS)
+}
+
+/// 通过 `--headless --print-to-pdf` 将保存下来的 HTML/MHTML 发票渲染为临时 PDF。
+pub fn convert_html_to_pdf(path: &Path, work_dir: &Path) -> Result<(PathBuf, TempPath), MergeError> {
+    let browser = find_headless_browser()
+        .ok_or_else(|| MergeError::Convert("未检测到 Edge / Chrome，无法渲染 HTML".into()))?;
+    let profile_dir = tempfile::Builder::new().prefix("mc-html-").tempdir_in(work_dir)?;
+    let temp_file = tempfile::Builder::new()
+        .prefix("mc-html-")
+        .suffix(".pdf")
+        .tempfile_in(work_dir)?;
+    let temp_path = temp_file.into_temp_path();
+
+    let mut command = Command::new(&browser);
+    command
+        .arg("--headless=new")
+        .arg("--disable-gpu")
+        .arg("--no-first-run")
+        .arg("--no-pdf-header-footer")
+        .arg("--print-to-pdf-no-header")
+        // 邮件里保存的 HTML 常引用远程图片和跟踪脚本：禁止一切联网（域名解析失败、直连 IP
+        // 也走不通的代理），并关闭 JavaScript，只渲染本地内容
+        .arg("--host-resolver-rules=MAP * ~NOTFOUND")
+        .arg("--proxy-server=127.0.0.1:9")
+        .arg("--blink-settings=scriptEnabled=false")
+        .arg("--disable-background-networking")
+        .arg("--disable-extensions")
+        .arg(format!("--user-data-dir={}", display_path(profile_dir.path())))
+        .arg(format!("--print-to-pdf={}", display_path(&temp_path)))
+        .arg(file_url(path));
+    hide_console_window(&mut command);
+
+    let output = run_with_timeout(&mut command, RENDER_TIMEOUT)?;
+    let Some(status) = output.status else {
+        return Err(MergeError::Convert(format!(
+            "HTML 渲染超过 {} 秒未完成，已终止",
+            RENDER_TIMEOUT.as_secs()
+        )));
+    };
+    let rendered = fs::metadata(&temp_path)
+        .map(|meta| meta.len() > 0)
+        .unwrap_or(false);
+    if !status.success() || !rendered {
+        return Err(MergeError::Convert(format!(
+            "HTML 渲染失败: {}",
+            output.stderr.trim()
+        )));
+    }
+
+    let path_buf = temp_path.to_path_buf();
+    Ok((path_buf, temp_path))
+}
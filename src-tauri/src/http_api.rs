@@ -0,0 +1,275 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tauri::{AppHandle, Manager};
+
+use crate::{cancel, MergeError, MergeRequest, MergeResult};
+
+/// 请求体上限，合并请求携带完整文件列表时也足够。
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 本地自动化接口的运行状态；默认不启动，只监听 127.0.0.1。
+#[derive(Default)]
+pub struct HttpApiState {
+    server: Mutex<Option<Running>>,
+    last_result: Arc<Mutex<Option<MergeResult>>>,
+}
+
+struct Running {
+    addr: SocketAddr,
+    token: String,
+    stop: Arc<AtomicBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HttpApiInfo {
+    pub port: u16,
+    pub token: String,
+}
+
+#[derive(Serialize)]
+struct Status<'a> {
+    merging: bool,
+    last_result: Option<&'a MergeResult>,
+}
+
+#[derive(Deserialize)]
+struct ScanBody {
+    folder_path: String,
+}
+
+/// 启动接口；`token` 为空时随机生成。已在运行时直接返回当前地址与令牌。
+pub fn start(app: &AppHandle, port: u16, token: Option<String>) -> Result<HttpApiInfo, MergeError> {
+    let state = app.state::<HttpApiState>();
+    let mut server = state
+        .server
+        .lock()
+        .map_err(|_| MergeError::Unsupported("接口状态异常".into()))?;
+    if let Some(running) = server.as_ref() {
+        return Ok(HttpApiInfo {
+            port: running.addr.port(),
+            token: running.token.clone(),
+        });
+    }
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+    let addr = listener.local_addr()?;
+    let token = match token.filter(|token| !token.trim().is_empty()) {
+        Some(token) => token,
+        None => random_token()?,
+    };
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let context = Context {
+        app: app.clone(),
+        token: token.clone(),
+        last_result: state.last_result.clone(),
+    };
+    let stop_flag = stop.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            if stop_flag.load(Ordering::Relaxed) {
+                break;
+            }
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let context = context.clone();
+            std::thread::spawn(move || {
+                let _ = handle_connection(stream, &context);
+            });
+        }
+    });
+
+    *server = Some(Running {
+        addr,
+        token: token.clone(),
+        stop,
+    });
+    Ok(HttpApiInfo {
+        port: addr.port(),
+        token,
+    })
+}
+
+pub fn stop(app: &AppHandle) {
+    let state = app.state::<HttpApiState>();
+    let Some(running) = state.server.lock().ok().and_then(|mut server| server.take()) else {
+        return;
+    };
+    running.stop.store(true, Ordering::Relaxed);
+    // 连一次自己，让阻塞在 accept 上的线程看到停止标记
+    let _ = TcpStream::connect_timeout(&running.addr, Duration::from_millis(200));
+}
+
+#[derive(Clone)]
+struct Context {
+    app: AppHandle,
+    token: String,
+    last_result: Arc<Mutex<Option<MergeResult>>>,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+fn handle_connection(stream: TcpStream, context: &Context) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut writer = stream.try_clone()?;
+    let request = match read_request(stream) {
+        Ok(request) => request,
+        Err(err) => return respond(&mut writer, 400, &error_body(&err.to_string())),
+    };
+
+    let authorized = request
+        .authorization
+        .as_deref()
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| constant_time_eq(token.trim().as_bytes(), context.token.as_bytes()));
+    if !authorized {
+        return respond(&mut writer, 401, &error_body("缺少或错误的令牌"));
+    }
+
+    let (status, body) = route(&request, context);
+    respond(&mut writer, status, &body)
+}
+
+fn route(request: &Request, context: &Context) -> (u16, String) {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/status") => {
+            let last = context.last_result.lock().ok();
+            let status = Status {
+                merging: cancel::in_progress(),
+                last_result: last.as_ref().and_then(|last| last.as_ref()),
+            };
+            (200, serde_json::to_string(&status).unwrap_or_default())
+        }
+        ("POST", "/scan") => {
+            let body: ScanBody = match serde_json::from_slice(&request.body) {
+                Ok(body) => body,
+                Err(err) => return (400, error_body(&err.to_string())),
+            };
+            let folder = Path::new(&body.folder_path);
+            // 与界面的扫描命令一样受管理员允许列表约束
+            if let Err(err) = crate::config::current(&context.app).ensure_allowed(folder) {
+                return (403, error_body(&err.to_string()));
+            }
+            match crate::scan_folder(folder, &[]) {
+                Ok(files) => (200, serde_json::to_string(&files).unwrap_or_default()),
+                Err(err) => (422, error_body(&err.to_string())),
+            }
+        }
+        ("POST", "/merge") => {
+            let req: MergeRequest = match serde_json::from_slice(&request.body) {
+                Ok(req) => req,
+                Err(err) => return (400, error_body(&err.to_string())),
+            };
+            let Some(window) = context.app.get_window("main") else {
+                return (503, error_body("主窗口不可用"));
+            };
+            match crate::merge_invoices(&window, req, false) {
+                Ok(result) => {
+                    if let Ok(mut last) = context.last_result.lock() {
+                        *last = Some(result.clone());
+                    }
+                    (200, serde_json::to_string(&result).unwrap_or_default())
+                }
+                Err(err @ MergeError::Busy) => (409, error_body(&err.to_string())),
+                Err(err @ MergeError::FolderNotAllowed(_)) => (403, error_body(&err.to_string())),
+                Err(err) => (422, error_body(&err.to_string())),
+            }
+        }
+        _ => (404, error_body("未知接口")),
+    }
+}
+
+fn read_request(stream: TcpStream) -> Result<Request, MergeError> {
+    let invalid = || MergeError::Unsupported("无效的 HTTP 请求".into());
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().ok_or_else(invalid)?.to_string();
+    let path = parts.next().ok_or_else(invalid)?.to_string();
+
+    let mut content_length = 0usize;
+    let mut authorization = None;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            return Err(invalid());
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.parse().map_err(|_| invalid())?;
+        } else if name.eq_ignore_ascii_case("authorization") {
+            authorization = Some(value.to_string());
+        }
+    }
+    if content_length > MAX_BODY_BYTES {
+        return Err(MergeError::Unsupported("请求体过大".into()));
+    }
+
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(Request {
+        method,
+        path,
+        authorization,
+        body,
+    })
+}
+
+fn respond(writer: &mut TcpStream, status: u16, body: &str) -> std::io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        409 => "Conflict",
+        422 => "Unprocessable Entity",
+        _ => "Service Unavailable",
+    };
+    write!(
+        writer,
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    writer.flush()
+}
+
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+/// 256 位的令牌，直接取自系统随机数源
+fn random_token() -> Result<String, MergeError> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|err| MergeError::Unsupported(format!("无法生成接口令牌: {err}")))?;
+    Ok(bytes.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
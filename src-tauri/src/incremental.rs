@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use tempfile::TempPath;
+
+use crate::{
+    jobs::hex_prefix, page_map::PageRange, replicate::file_sha256, ConvertOptions, InvoiceFile, MergeError,
+    MergeRequest,
+};
+
+pub const TEMP_PREFIX: &str = "mc-incr-";
+const STATE_DIR: &str = "merge-state";
+
+/// 某个文件夹上一次完整合并的状态：按顺序记录每个文件的标识，以及输出文件的哈希。
+#[derive(Debug, Serialize, Deserialize)]
+struct FolderState {
+    output_path: String,
+    output_sha256: String,
+    settings: String,
+    files: Vec<String>,
+    #[serde(default)]
+    page_map: Vec<PageRange>,
+}
+
+/// 可复用的上次输出：它已包含前 `prefix_len` 个文件的全部页面。
+pub struct Reuse {
+    pub pdf: PathBuf,
+    pub temp: TempPath,
+    pub prefix_len: usize,
+    /// 上次输出的页码分布，复用后结果中的页码映射依然完整
+    pub page_map: Vec<PageRange>,
+}
+
+pub fn state_path(cache_dir: &Path, folder: &Path) -> PathBuf {
+    let digest = Sha256::digest(folder.to_string_lossy().as_bytes());
+    cache_dir
+        .join(STATE_DIR)
+        .join(format!("{}.json", hex_prefix(&digest)))
+}
+
+/// 文件标识包含内容变化（大小、修改时间）和影响转换结果的全部选项。
+pub fn file_key(file: &InvoiceFile, opts: &ConvertOptions) -> String {
+    format!(
+        "{}|{}|{}|{}|{:?}|{:?}",
+        file.id,
+        file.size,
+        file.modified_ts,
+        opts.cache_key(),
+        file.page_rotations,
+        file.page_ranges
+    )
+}
+
+/// 作用于整份输出、与单个文件无关的选项；变化后不能复用上次结果。
+pub fn settings_key(req: &MergeRequest) -> String {
+    let cover = req.cover_pdf.as_deref().map(|path| {
+        let meta = fs::metadata(path).ok();
+        (
+            path,
+            meta.as_ref().map(|meta| meta.len()),
+            meta.and_then(|meta| meta.modified().ok()),
+        )
+    });
+    format!(
+        "{:?}|{}|{}|{:?}|{:?}|{:?}|{:?}|{:?}|{}|{:?}|{:?}|{:?}",
+        req.downsample,
+        req.rasterize_xfa,
+        req.crop_to_content,
+        req.crop_padding_mm,
+        cover,
+        req.insertions,
+        req.attachment_stamp,
+        req.bates,
+        req.tagged_pdf,
+        req.page_ranges,
+        req.n_up,
+        req.source_footer
+    )
+}
+
+/// 新的文件列表以上次的列表为前缀、且上次的输出未被改动时，复制一份上次的输出作为合并基础，
+/// 只需再转换新增的文件。
+pub fn reusable(state_path: &Path, keys: &[String], settings: &str, work_dir: &Path) -> Option<Reuse> {
+    let state: FolderState = serde_json::from_str(&fs::read_to_string(state_path).ok()?).ok()?;
+    if state.settings != settings
+        || state.files.is_empty()
+        || state.page_map.is_empty()
+        || keys.len() <= state.files.len()
+    {
+        return None;
+    }
+    if !keys.starts_with(&state.files) {
+        return None;
+    }
+    let output = Path::new(&state.output_path);
+    if file_sha256(output).ok()? != state.output_sha256 {
+        return None;
+    }
+
+    // 本次输出可能覆盖上次的文件，先复制到工作目录
+    let temp = tempfile::Builder::new()
+        .prefix(TEMP_PREFIX)
+        .suffix(".pdf")
+        .tempfile_in(work_dir)
+        .ok()?
+        .into_temp_path();
+    fs::copy(output, &temp).ok()?;
+    Some(Reuse {
+        pdf: temp.to_path_buf(),
+        temp,
+        prefix_len: state.files.len(),
+        page_map: state.page_map,
+    })
+}
+
+pub fn save(
+    state_path: &Path,
+    output: &Path,
+    settings: &str,
+    keys: Vec<String>,
+    page_map: &[PageRange],
+) -> Result<(), MergeError> {
+    let state = FolderState {
+        output_path: output.to_string_lossy().into_owned(),
+        output_sha256: file_sha256(output)?,
+        settings: settings.to_string(),
+        files: keys,
+        page_map: page_map.to_vec(),
+    };
+    if let Some(parent) = state_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_string(&state).map_err(|err| MergeError::Io(err.into()))?;
+    fs::write(state_path, json)?;
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tempfile::TempPath;
+
+use crate::{
+    cover,
+    text_page::{self, TextLine},
+    InvoiceFile, MergeError,
+};
+
+/// 合并时在指定位置插入的页面：一份模板 PDF（如差旅审批单），或未指定 PDF 时的一页分隔页。
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InsertRule {
+    pub pdf: Option<String>,
+    /// 分隔页上的文字，`{n}` 替换为序号；默认为“第 {n} 组”或匹配的文字
+    pub label: Option<String>,
+    #[serde(flatten)]
+    pub at: InsertAt,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "at", rename_all = "snake_case")]
+pub enum InsertAt {
+    /// 插在文件名或销售方名称包含该文字的第一个文件之前
+    BeforeFirst { contains: String },
+    /// 每 `every` 个文件之后插入一次，最后一个文件之后不插
+    AfterEvery { every: usize },
+}
+
+/// 插入内容在合并前解析好，缺失或无效的模板在开始转换前就报错。
+pub enum Insertion {
+    Pdf(PathBuf),
+    Divider(String),
+}
+
+impl InsertRule {
+    pub fn resolve_pdf(&self) -> Result<Option<PathBuf>, MergeError> {
+        self.pdf
+            .as_deref()
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+            .map(cover::resolve)
+            .transpose()
+    }
+
+    fn label_for(&self, n: usize) -> String {
+        let default = match &self.at {
+            InsertAt::BeforeFirst { contains } => contains.clone(),
+            InsertAt::AfterEvery { .. } => "第 {n} 组".to_string(),
+        };
+        self.label
+            .clone()
+            .filter(|label| !label.trim().is_empty())
+            .unwrap_or(default)
+            .replace("{n}", &n.to_string())
+    }
+}
+
+/// 在第 `index` 个文件（按排序后的顺序）之前需要插入的内容。
+pub fn before(
+    rules: &[InsertRule],
+    templates: &[Option<PathBuf>],
+    files: &[InvoiceFile],
+    index: usize,
+) -> Vec<Insertion> {
+    rules
+        .iter()
+        .zip(templates)
+        .filter_map(|(rule, template)| {
+            let n = match &rule.at {
+                InsertAt::BeforeFirst { contains } => {
+                    let first = files.iter().position(|file| matches(file, contains))?;
+                    (first == index).then_some(1)?
+                }
+                InsertAt::AfterEvery { every } => {
+                    let every = (*every).max(1);
+                    (index > 0 && index % every == 0).then_some(index / every + 1)?
+                }
+            };
+            Some(match template {
+                Some(pdf) => Insertion::Pdf(pdf.clone()),
+                None => Insertion::Divider(rule.label_for(n)),
+            })
+        })
+        .collect()
+}
+
+fn matches(file: &InvoiceFile, contains: &str) -> bool {
+    let contains = contains.trim();
+    !contains.is_empty()
+        && (file.file_name.contains(contains)
+            || file
+                .invoice_info
+                .as_ref()
+                .is_some_and(|info| info.seller_name.contains(contains)))
+}
+
+pub fn divider_page(label: &str, work_dir: &Path) -> Result<(PathBuf, TempPath), MergeError> {
+    let lines = [TextLine::new(label, 24.0)];
+    text_page::render_text_document(label, &lines, work_dir)
+}
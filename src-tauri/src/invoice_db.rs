@@ -0,0 +1,152 @@
+use chrono::Local;
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{invoice_number_from_name, validate::FileValidation, InvoiceFile, MergeError};
+
+/// 默认数据库文件名，放在应用配置目录下；`config.toml` 的 `duplicate_db` 可指向网络共享路径。
+pub const DEFAULT_DB_FILE: &str = "merged-invoices.jsonl";
+
+/// 已合并过的发票记录，一行一条 JSON，便于多台电脑通过共享目录追加。
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MergedInvoice {
+    pub invoice_number: String,
+    pub file_name: String,
+    pub output_path: String,
+    pub merged_at: String,
+    pub user: String,
+    pub machine: String,
+}
+
+/// 跨机器共享的重复发票库。读写都通过旁边的 `.lock` 文件加锁，
+/// 共享盘上的文件锁由 SMB 服务器协调，避免两人同时追加时互相覆盖。
+pub struct InvoiceDb {
+    path: PathBuf,
+}
+
+impl InvoiceDb {
+    pub fn open(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// 查询这些发票号码此前是否合并过，返回号码到最早一条记录的映射。
+    pub fn lookup(&self, numbers: &[String]) -> Result<HashMap<String, MergedInvoice>, MergeError> {
+        let mut found = HashMap::new();
+        if numbers.is_empty() || !self.path.is_file() {
+            return Ok(found);
+        }
+        let lock = self.lock_file()?;
+        lock.lock_shared()?;
+        let file = fs::File::open(&self.path)?;
+        for line in BufReader::new(file).lines() {
+            let Ok(record) = serde_json::from_str::<MergedInvoice>(&line?) else {
+                continue;
+            };
+            if numbers.contains(&record.invoice_number) {
+                found.entry(record.invoice_number.clone()).or_insert(record);
+            }
+        }
+        lock.unlock()?;
+        Ok(found)
+    }
+
+    /// 追加本次合并的发票；没有发票号码的文件不记录。
+    pub fn record(&self, files: &[&InvoiceFile], output_path: &Path) -> Result<(), MergeError> {
+        let now = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let user = env_any(&["USERNAME", "USER"]);
+        let machine = env_any(&["COMPUTERNAME", "HOSTNAME"]);
+        let mut lines = String::new();
+        for file in files {
+            let Some(invoice_number) = invoice_key(file) else {
+                continue;
+            };
+            let record = MergedInvoice {
+                invoice_number,
+                file_name: file.file_name.clone(),
+                output_path: output_path.to_string_lossy().into_owned(),
+                merged_at: now.clone(),
+                user: user.clone(),
+                machine: machine.clone(),
+            };
+            if let Ok(line) = serde_json::to_string(&record) {
+                lines.push_str(&line);
+                lines.push('\n');
+            }
+        }
+        if lines.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let lock = self.lock_file()?;
+        lock.lock_exclusive()?;
+        let result = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| {
+                file.write_all(lines.as_bytes())?;
+                file.sync_all()
+            });
+        lock.unlock()?;
+        result?;
+        Ok(())
+    }
+
+    fn lock_file(&self) -> std::io::Result<fs::File> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(self.path.with_extension("lock"))
+    }
+}
+
+/// 标出此前已合并过的发票，提醒可能重复报销。
+pub fn annotate(
+    db: &InvoiceDb,
+    files: &[InvoiceFile],
+    results: &mut [FileValidation],
+) -> Result<(), MergeError> {
+    let keys: Vec<Option<String>> = files.iter().map(invoice_key).collect();
+    let numbers: Vec<String> = keys.iter().flatten().cloned().collect();
+    let found = db.lookup(&numbers)?;
+    for (key, result) in keys.iter().zip(results.iter_mut()) {
+        let Some(record) = key.as_ref().and_then(|key| found.get(key)) else {
+            continue;
+        };
+        result.warnings.push(format!(
+            "发票 {} 已于 {} 由 {} 合并过（{}）",
+            record.invoice_number, record.merged_at, record.user, record.file_name
+        ));
+        result.previously_merged = Some(record.clone());
+    }
+    Ok(())
+}
+
+/// 发票号码：优先取电子发票 XML 中的号码，否则取文件名中的长数字串。
+fn invoice_key(file: &InvoiceFile) -> Option<String> {
+    file.invoice_info
+        .as_ref()
+        .map(|info| info.invoice_number.trim().to_string())
+        .filter(|number| !number.is_empty())
+        .or_else(|| invoice_number_from_name(&file.file_name).map(|number| number.to_string()))
+}
+
+fn env_any(names: &[&str]) -> String {
+    names
+        .iter()
+        .find_map(|name| std::env::var(name).ok())
+        .unwrap_or_default()
+}
@@ -0,0 +1,66 @@
+use sha2::{Digest, Sha256};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{InvoiceFile, MergeRequest};
+
+pub(crate) const JOBS_DIR_NAME: &str = "invoice-merge-jobs";
+
+/// 单次合并任务的中间结果目录。任务 ID 由文件夹和输入文件（路径/大小/修改时间）决定，
+/// 因此合并失败后用相同输入重试时，已转换好的文件可直接复用。
+pub struct JobStore {
+    dir: PathBuf,
+}
+
+impl JobStore {
+    pub fn open(req: &MergeRequest, work_dir: &Path) -> io::Result<Self> {
+        let mut keys: Vec<String> = req.files.iter().map(file_key).collect();
+        keys.sort();
+
+        let mut hasher = Sha256::new();
+        hasher.update(req.folder_path.as_bytes());
+        for key in &keys {
+            hasher.update(key.as_bytes());
+        }
+        let job_id = hex_prefix(&hasher.finalize());
+
+        let dir = work_dir.join(JOBS_DIR_NAME).join(job_id);
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// 返回该文件在上一次（失败的）任务中已转换好的 PDF。
+    pub fn lookup(&self, file: &InvoiceFile, variant: &str) -> Option<PathBuf> {
+        let path = self.entry_path(file, variant);
+        path.is_file().then_some(path)
+    }
+
+    /// 将刚转换好的临时 PDF 复制进任务目录，先写临时名再改名，避免中途崩溃留下半个文件。
+    pub fn store(&self, file: &InvoiceFile, variant: &str, converted: &Path) -> io::Result<PathBuf> {
+        let target = self.entry_path(file, variant);
+        let partial = target.with_extension("partial");
+        fs::copy(converted, &partial)?;
+        fs::rename(&partial, &target)?;
+        Ok(target)
+    }
+
+    /// 任务成功完成后清理中间结果。
+    pub fn finish(self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+
+    fn entry_path(&self, file: &InvoiceFile, variant: &str) -> PathBuf {
+        let digest = Sha256::digest(format!("{}|{variant}", file_key(file)).as_bytes());
+        self.dir.join(format!("{}.pdf", hex_prefix(&digest)))
+    }
+}
+
+fn file_key(file: &InvoiceFile) -> String {
+    format!("{}|{}|{}", file.path, file.size, file.modified_ts)
+}
+
+pub(crate) fn hex_prefix(digest: &[u8]) -> String {
+    digest.iter().take(12).map(|byte| format!("{byte:02x}")).collect()
+}
@@ -0,0 +1,134 @@
+use lopdf::{dictionary, Document, Object, Stream};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use tempfile::TempPath;
+
+use crate::{
+    blank, load_preview_image, orientation, page_fit::save_temp_document, sniff, ConvertOptions, MergeError,
+};
+
+const MM_TO_PT: f64 = 72.0 / 25.4;
+/// 空白检测只需要小图
+const BLANK_CHECK_SIDE: u32 = 512;
+
+struct JpegInfo {
+    width: u32,
+    height: u32,
+    components: u8,
+}
+
+/// 不需要改动像素的 JPEG 直接把原始数据作为 DCTDecode 图片嵌入 PDF，
+/// 省去解码再重新压缩：体积不膨胀、画质不损失，转换也快得多。
+///
+/// 需要图像处理（增强、去阴影等）、旋转（EXIF 方向或版式要求）、CMYK 或无法识别的 JPEG
+/// 返回 `None`，由调用方按普通方式解码转换。
+pub fn convert(
+    path: &Path,
+    opts: &ConvertOptions,
+    work_dir: &Path,
+) -> Result<Option<(PathBuf, TempPath)>, MergeError> {
+    if opts.correct_perspective || opts.denoise || opts.remove_shadows || opts.enhance {
+        return Ok(None);
+    }
+    if sniff::sniff_extension(path) != Some("jpg") || !orientation::is_upright(path) {
+        return Ok(None);
+    }
+    let data = fs::read(path)?;
+    let Some(info) = probe(&data) else {
+        return Ok(None);
+    };
+    let color_space = match info.components {
+        1 => "DeviceGray",
+        3 => "DeviceRGB",
+        // Adobe 生成的 CMYK JPEG 常是反相存储，交给解码器处理
+        _ => return Ok(None),
+    };
+    if opts.page_layout.rotates(info.width, info.height) {
+        return Ok(None);
+    }
+    if opts.skip_blank && blank::is_near_blank(&load_preview_image(path, BLANK_CHECK_SIDE)?) {
+        return Err(MergeError::BlankImage);
+    }
+
+    let (page_w, page_h) = opts.page_layout.page_mm(opts.page_size, info.width, info.height);
+    let scale = (page_w / info.width as f64).min(page_h / info.height as f64);
+    let (display_w, display_h) = (
+        info.width as f64 * scale * MM_TO_PT,
+        info.height as f64 * scale * MM_TO_PT,
+    );
+    let (page_w, page_h) = (page_w * MM_TO_PT, page_h * MM_TO_PT);
+    let (x, y) = ((page_w - display_w) / 2.0, (page_h - display_h) / 2.0);
+
+    let mut doc = Document::with_version("1.5");
+    let image_id = doc.add_object(Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => info.width as i64,
+            "Height" => info.height as i64,
+            "ColorSpace" => color_space,
+            "BitsPerComponent" => 8,
+            "Filter" => "DCTDecode",
+        },
+        data,
+    ));
+    let content = format!("q {display_w:.3} 0 0 {display_h:.3} {x:.3} {y:.3} cm /Im0 Do Q");
+    let content_id = doc.add_object(Stream::new(dictionary! {}, content.into_bytes()));
+    let pages_id = doc.new_object_id();
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), Object::Real(page_w as _), Object::Real(page_h as _)],
+        "Resources" => dictionary! { "XObject" => dictionary! { "Im0" => image_id } },
+        "Contents" => content_id,
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![Object::Reference(page_id)],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    save_temp_document(&mut doc, "mc-image-", work_dir).map(Some)
+}
+
+/// 读取帧头（SOF）中的尺寸与颜色分量数，只接受 8 位精度。
+fn probe(data: &[u8]) -> Option<JpegInfo> {
+    let mut pos = 2;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            return None;
+        }
+        let marker = data[pos + 1];
+        // 填充字节
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let is_sof = matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+        if is_sof {
+            let segment = data.get(pos + 4..pos + 2 + length)?;
+            // 只接受基线与渐进式；无损、差分和算术编码的 JPEG 不是所有阅读器都支持
+            if !matches!(marker, 0xC0..=0xC2) || segment.len() < 6 || segment[0] != 8 {
+                return None;
+            }
+            return Some(JpegInfo {
+                height: u16::from_be_bytes([segment[1], segment[2]]) as u32,
+                width: u16::from_be_bytes([segment[3], segment[4]]) as u32,
+                components: segment[5],
+            })
+            .filter(|info| info.width > 0 && info.height > 0);
+        }
+        pos += 2 + length;
+    }
+    None
+}
@@ -0,0 +1,3049 @@
+//! 发票合并引擎与桌面应用。桌面程序的入口只调用 [`run`]；
+//! 其他 Tauri 应用依赖本 crate 后以 `.plugin(tauri_plugin_invoice_merge::init())` 接入，见 [`plugin`]。
+
+mod accessibility;
+mod append;
+mod bates;
+mod blank;
+mod cache;
+mod cancel;
+mod ccitt;
+mod cleanup;
+mod compress;
+mod config;
+mod cover;
+mod crash;
+mod crop;
+mod decode_guard;
+mod dedupe;
+mod deep_link;
+mod details;
+mod dropped;
+mod einvoice_xml;
+mod enhance;
+mod hook;
+mod html;
+mod http_api;
+mod incremental;
+mod insert;
+mod invoice_db;
+mod jobs;
+mod jpeg_passthrough;
+mod mmap_pdf;
+mod notify;
+mod nup;
+mod office;
+mod order_file;
+mod orientation;
+mod outline;
+mod page_fit;
+mod page_map;
+mod page_range;
+mod parallel;
+mod password;
+mod pdf_writer;
+mod perspective;
+mod phash;
+mod plan;
+pub mod plugin;
+mod policy;
+mod portable;
+mod preview;
+mod qr_guard;
+mod raster;
+mod replicate;
+mod shell_menu;
+mod sniff;
+mod split;
+mod stamp;
+mod stdio_rpc;
+mod summary;
+mod text_page;
+mod thumbnail;
+mod tiff_stream;
+mod tray;
+mod update;
+mod validate;
+mod webhook;
+
+use chrono::{DateTime, Local};
+use image::{DynamicImage, GenericImageView, ImageBuffer, Rgb, RgbImage, RgbaImage};
+use libheif_rs::{ColorSpace, HeifContext, ImageHandle, RgbChroma};
+use lopdf::{Document, Object, ObjectId};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::{Duration, Instant, UNIX_EPOCH},
+};
+use tauri::{Manager, Window};
+use tempfile::TempPath;
+use thiserror::Error;
+
+use accessibility::PageTag;
+use bates::BatesNumbering;
+use cache::{ConversionCache, DEFAULT_CACHE_LIMIT_MB};
+use cleanup::CleanupReport;
+use compress::DownsampleOptions;
+use config::{AppConfig, ConfigState};
+use crash::CrashReport;
+use deep_link::{DeepLink, PendingLink};
+use details::FileDetails;
+use dropped::DroppedPaths;
+use einvoice_xml::{InvoiceInfo, XML_EXTENSIONS};
+use html::HTML_EXTENSIONS;
+use http_api::{HttpApiInfo, HttpApiState};
+use insert::{InsertRule, Insertion};
+use invoice_db::InvoiceDb;
+use jobs::JobStore;
+use nup::NUpLayout;
+use office::OFFICE_EXTENSIONS;
+use order_file::OrderImport;
+use page_fit::{PageLayout, PageSize};
+use page_map::{PageMapBuilder, PageRange, PageSourceKind};
+use page_range::PageRanges;
+use plan::MergePlan;
+use policy::Policy;
+use portable::AppDir;
+use shell_menu::LaunchFolder;
+use split::SplitOptions;
+use stamp::{AttachmentStamp, SourceFooter};
+use text_page::TextLine;
+use tray::TrayState;
+
+pub use plugin::init;
+use update::UpdateInfo;
+use validate::FileValidation;
+
+const VALID_EXTENSIONS: &[&str] = &[
+    "pdf", "jpg", "jpeg", "png", "bmp", "gif", "tiff", "webp", "heic", "doc", "docx", "xls", "xlsx", "html",
+    "htm", "mhtml", "mht", "xml",
+];
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "bmp", "gif", "tiff", "webp", "heic"];
+const IMAGE_RENDER_DPI: f64 = 150.0;
+/// 常见邮箱附件上限，超过后提示用户开启压缩
+const DEFAULT_OVERSIZE_WARNING_MB: f64 = 25.0;
+/// 估算图片转 PDF 后体积的放大系数（解码后以较低压缩率重新嵌入）
+const CONVERTED_SIZE_FACTOR: u64 = 4;
+/// 流式扫描每批推送的文件数
+const SCAN_BATCH_SIZE: usize = 200;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct InvoiceFile {
+    /// 由文件系统标识（inode / 创建时间）派生的稳定 ID，重命名后保持不变
+    #[serde(default)]
+    pub id: String,
+    pub path: String,
+    pub file_name: String,
+    pub ext: String,
+    pub modified_ts: i64,
+    pub size: u64,
+    /// 从内嵌/独立的发票 XML 中解析出的发票数据
+    pub invoice_info: Option<InvoiceInfo>,
+    /// 单独指定该文件的纸张（如火车票用 A5），优先于请求中的全局设置
+    #[serde(default)]
+    pub page_size: Option<PageSize>,
+    /// 仅对 PDF 生效：页序号（从 0 开始）→ 额外旋转角度（90 的倍数），用于纠正个别倒置的页
+    #[serde(default)]
+    pub page_rotations: BTreeMap<u32, i64>,
+    /// 仅对 PDF 生效：只合并这些页，如 `"1"`、`"1-3,5"`，优先于请求中的全局设置
+    #[serde(default)]
+    pub page_ranges: Option<String>,
+    /// 扫描时请求了 `enrich` 才会填充
+    #[serde(default)]
+    pub details: Option<FileDetails>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScanPage {
+    pub files: Vec<InvoiceFile>,
+    /// 文件夹中可合并文件的总数
+    pub total: usize,
+    pub offset: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SortMode {
+    FileNameAsc,
+    ModifiedAsc,
+    /// 按文件名中的发票号码（如 `dzfp_24312000000123456789_….pdf`）数值升序
+    InvoiceNumberAsc,
+    Custom,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MergeRequest {
+    pub folder_path: String,
+    /// 提供 `file_ids` 时可只携带需要单独设置（纸张、旋转）的文件
+    #[serde(default)]
+    pub files: Vec<InvoiceFile>,
+    /// 扫描返回的文件 ID，按此顺序重新读取文件夹中的最新信息，优先于 `files`
+    #[serde(default)]
+    pub file_ids: Vec<String>,
+    pub sort_mode: SortMode,
+    /// 图片等生成页的纸张，默认 A4
+    pub page_size: Option<PageSize>,
+    /// 图片等生成页的方向，以及是否把页面裁到图片大小
+    #[serde(default)]
+    pub page_layout: PageLayout,
+    /// 把相邻的图片每 2、4 或 8 张排在一页上，适合打印小票；PDF 等其他文件仍各自成页。
+    /// 拼版的图片不加盖附件编号
+    #[serde(default)]
+    pub n_up: Option<NUpLayout>,
+    /// 按内容裁掉 PDF 页面多余的空白边距（扫描件常见）
+    #[serde(default)]
+    pub crop_to_content: bool,
+    /// 裁剪后保留的边距（毫米），默认 5 mm
+    pub crop_padding_mm: Option<f64>,
+    /// 对任意排序方式（包括自定义顺序）整体倒序
+    #[serde(default)]
+    pub descending: bool,
+    pub output_file_name: Option<String>,
+    pub downsample: Option<DownsampleOptions>,
+    /// 画质档位（1–100），未指定 `downsample` 时换算为降采样参数，便于界面用一个滑块控制
+    pub compression_quality: Option<u8>,
+    /// 输出文件大小上限（MB），超出时逐级降低图片质量重新合并
+    pub max_output_mb: Option<f64>,
+    /// 输出超过该大小（MB）时发出警告，默认 25 MB
+    pub oversize_warning_mb: Option<f64>,
+    /// 将输出文件的修改时间设为最新源文件的修改时间
+    #[serde(default)]
+    pub match_source_mtime: bool,
+    /// 转换缓存目录的大小上限（MB），默认 512 MB
+    pub cache_limit_mb: Option<u64>,
+    /// 中间文件的工作目录，未设置时使用系统临时目录
+    pub temp_dir: Option<String>,
+    /// 输出目录，未设置时写入源文件夹
+    pub output_dir: Option<String>,
+    /// 源文件夹不可写（只读共享、光盘等）时改写到该目录
+    pub fallback_output_dir: Option<String>,
+    /// 将 XFA 表单栅格化为图片页，避免合并后显示空白
+    #[serde(default)]
+    pub rasterize_xfa: bool,
+    /// 跳过几乎纯色的图片（误拍、全白/全黑帧）
+    #[serde(default)]
+    pub skip_blank_images: bool,
+    /// 对照片自动调整色阶/对比度，改善昏暗发灰的拍照小票
+    #[serde(default)]
+    pub auto_enhance: bool,
+    /// 去除拍照时灯光造成的阴影和明暗渐变
+    #[serde(default)]
+    pub remove_shadows: bool,
+    /// 识别照片中的纸张边缘并拉直为矩形
+    #[serde(default)]
+    pub correct_perspective: bool,
+    /// 对低光照片做中值去噪
+    #[serde(default)]
+    pub denoise: bool,
+    /// 在失败文件原本的位置插入一页说明，保持顺序与完整性可见
+    #[serde(default)]
+    pub failure_placeholders: bool,
+    /// 有文件失败时，在合并结果末尾追加一页失败清单
+    #[serde(default)]
+    pub failure_appendix: bool,
+    /// 在开头插入合并摘要页：合并时间、文件夹、文件数及各文件在输出中的页码范围。追加模式下不插入
+    #[serde(default)]
+    pub summary_page: bool,
+    /// 输出文件已存在时的处理方式；未指定时返回 `needs_confirmation` 交由用户决定。
+    /// 拆分输出的各份同样遵循：除非指定覆盖，已存在的分卷会自动改名
+    #[serde(alias = "overwrite_policy")]
+    pub on_conflict: Option<ConflictAction>,
+    /// 引用 `config.toml` 中的命名合并方案
+    pub profile: Option<String>,
+    /// 保存成功后再复制一份到该目录（如网络归档共享）并校验
+    pub secondary_output_dir: Option<String>,
+    /// 把本次的文件追加到这份已有的合并 PDF 之后并写回原文件，不再重新生成整份文件
+    pub append_to: Option<String>,
+    /// 固定放在最前面的 PDF（公司报销单、已签字的审批单），不参与排序
+    pub cover_pdf: Option<String>,
+    /// 按规则插入模板页或分隔页，如在第一张交通票据前插入审批单、每 10 个文件后插入分隔页
+    #[serde(default)]
+    pub insertions: Vec<InsertRule>,
+    /// 在每份源文件首页加盖附件编号
+    pub attachment_stamp: Option<AttachmentStamp>,
+    /// 在输出的每一页加盖 Bates 编号
+    pub bates: Option<BatesNumbering>,
+    /// 在输出的每一页底部注明来源文件名和页码；追加模式下原有的页面不盖
+    pub source_footer: Option<SourceFooter>,
+    /// 按大小或文件数把输出拆成 `名称_part1.pdf`、`名称_part2.pdf`……；预览时不拆分
+    pub split: Option<SplitOptions>,
+    /// 为生成的页面（图片页、占位页等）加结构标签和替代文字，输出标记为带标签的 PDF
+    #[serde(default)]
+    pub tagged_pdf: bool,
+    /// 并行处理的线程数上限，未指定时取 `config.toml` 中的设置，再退回 CPU 核数
+    pub max_threads: Option<usize>,
+    /// 单个文件大小上限（MB），超出的文件记为失败；未指定时取配置，再退回 1024 MB，0 表示不限制
+    pub max_file_mb: Option<f64>,
+    /// 单张图片的解码时限（秒），超时的文件记为失败；未指定时取配置，再退回 60 秒
+    pub decode_timeout_secs: Option<u64>,
+    /// 每个源 PDF 只合并这些页（如 `"1"` 只取发票所在的首页），文件自身的设置优先
+    pub page_ranges: Option<String>,
+}
+
+impl MergeRequest {
+    /// 该文件生效的页码范围：文件自身的设置优先，留空表示全部页
+    fn page_ranges_for<'a>(&'a self, file: &'a InvoiceFile) -> Option<&'a str> {
+        file.page_ranges
+            .as_deref()
+            .or(self.page_ranges.as_deref())
+            .map(str::trim)
+            .filter(|spec| !spec.is_empty())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictAction {
+    Overwrite,
+    /// 自动改名为 `name (1).pdf` 等
+    Rename,
+    Cancel,
+    /// 直接返回错误，适合脚本调用
+    Fail,
+}
+
+/// 影响图片/文档转换结果的选项，同时参与转换缓存的键。
+#[derive(Debug, Clone, Default)]
+struct ConvertOptions {
+    skip_blank: bool,
+    correct_perspective: bool,
+    denoise: bool,
+    remove_shadows: bool,
+    enhance: bool,
+    page_size: PageSize,
+    page_layout: PageLayout,
+    /// 解码时限不影响转换结果，不参与缓存键
+    decode_timeout_secs: Option<u64>,
+}
+
+impl ConvertOptions {
+    fn from_request(req: &MergeRequest) -> Self {
+        Self {
+            skip_blank: req.skip_blank_images,
+            correct_perspective: req.correct_perspective,
+            denoise: req.denoise,
+            remove_shadows: req.remove_shadows,
+            enhance: req.auto_enhance,
+            page_size: req.page_size.unwrap_or_default(),
+            page_layout: req.page_layout,
+            decode_timeout_secs: req.decode_timeout_secs,
+        }
+    }
+
+    /// 套用文件自身的纸张设置
+    fn for_file(&self, file: &InvoiceFile) -> Self {
+        Self {
+            page_size: file.page_size.unwrap_or(self.page_size),
+            ..self.clone()
+        }
+    }
+
+    fn cache_key(&self) -> String {
+        format!(
+            "{:?}",
+            Self {
+                decode_timeout_secs: None,
+                ..self.clone()
+            }
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MergeResult {
+    pub success: bool,
+    /// 拆分输出时为第一份
+    pub output_path: String,
+    /// 全部输出文件，未拆分时只有 `output_path` 一项
+    pub output_paths: Vec<String>,
+    /// 仅含文件名，保留给旧版前端；新代码请使用 `failures`
+    pub failed_files: Vec<String>,
+    pub failures: Vec<FailedFile>,
+    /// 成功并入输出的文件数
+    pub merged_files: usize,
+    pub page_count: usize,
+    pub message: Option<String>,
+    pub size_target_met: Option<bool>,
+    pub oversize: bool,
+    pub output_fallback_used: bool,
+    /// 被判定为空白而跳过的图片
+    pub skipped_files: Vec<String>,
+    pub timings: PhaseTimings,
+    /// 预览模式下确认后应移动到的最终路径；此时 `output_path` 指向预览文件
+    pub target_path: Option<String>,
+    /// 输出文件已存在且请求未指定处理方式时，返回冲突的路径，未做任何合并
+    pub needs_confirmation: Option<String>,
+    /// 输出文件（或拆分后的某一份）原本已存在时实际采取的处理：覆盖或改名
+    pub conflict_resolution: Option<ConflictAction>,
+    /// 已校验的第二份副本路径
+    pub secondary_output_path: Option<String>,
+    /// 复制第二份副本失败的原因；主输出不受影响
+    pub secondary_output_error: Option<String>,
+    /// 启用 Bates 编号时，最后一页的编号
+    pub bates_last: Option<String>,
+    /// 输出页码与来源文件的对应关系，按页码顺序排列
+    pub page_map: Vec<PageRange>,
+    /// 用户中途取消，未生成输出
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FailedFile {
+    pub file_name: String,
+    pub path: String,
+    pub stage: FailureStage,
+    pub kind: FailureKind,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureStage {
+    /// 路径检查阶段（文件不存在、越界等）
+    Scan,
+    Convert,
+}
+
+/// 失败类别，前端据此给出针对性的处理建议。
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    Missing,
+    OutsideFolder,
+    /// 超过单个文件大小上限
+    TooLarge,
+    Io,
+    Decode,
+    /// 解码超时，文件可能已损坏
+    Timeout,
+    /// 加密 PDF 未提供密码或密码错误
+    Encrypted,
+    Pdf,
+    Convert,
+    Unsupported,
+}
+
+impl FailureKind {
+    fn of(err: &MergeError) -> Self {
+        match err {
+            MergeError::Io(io) if io.kind() == std::io::ErrorKind::NotFound => Self::Missing,
+            MergeError::Io(_) => Self::Io,
+            MergeError::Image(_) => Self::Decode,
+            MergeError::Pdf(_) => Self::Pdf,
+            MergeError::Unsupported(_) => Self::Unsupported,
+            MergeError::FileTooLarge { .. } => Self::TooLarge,
+            MergeError::DecodeTimeout(_) => Self::Timeout,
+            MergeError::PasswordRequired | MergeError::WrongPassword => Self::Encrypted,
+            _ => Self::Convert,
+        }
+    }
+}
+
+fn failure_of(stage: FailureStage, err: MergeError) -> (FailureStage, FailureKind, String) {
+    (stage, FailureKind::of(&err), err.to_string())
+}
+
+/// 各阶段耗时（毫秒），用于定位大批量合并的瓶颈。
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct PhaseTimings {
+    /// 校验目录、排序、准备临时与输出目录
+    pub scan_ms: u64,
+    pub convert_ms: u64,
+    pub merge_ms: u64,
+    /// 写出 PDF 及修改时间等收尾
+    pub write_ms: u64,
+    pub total_ms: u64,
+}
+
+#[derive(Debug, Error)]
+pub enum MergeError {
+    #[error("未找到任何可合并的文件")]
+    NoFiles,
+    #[error("指定的文件夹无效")]
+    InvalidFolder,
+    #[error("读取文件失败: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("图片解码失败: {0}")]
+    Image(String),
+    #[error("PDF 处理失败: {0}")]
+    Pdf(String),
+    #[error("文档转换失败: {0}")]
+    Convert(String),
+    #[error("图片几乎为纯色，已跳过")]
+    BlankImage,
+    #[error("不支持的文件类型: {0}")]
+    Unsupported(String),
+    #[error("输出文件名不能包含路径分隔符")]
+    InvalidOutputName,
+    #[error("预览文件不存在或已失效")]
+    PreviewNotFound,
+    #[error("文件夹不在允许的范围内: {0}")]
+    FolderNotAllowed(String),
+    #[error("该功能已被管理员禁用: {0}")]
+    PolicyForbidden(String),
+    #[error("未找到合并方案: {0}")]
+    UnknownProfile(String),
+    #[error("链接无效: {0}")]
+    InvalidLink(String),
+    #[error("检查更新失败: {0}")]
+    Update(String),
+    #[error("更新未配置：此版本未设置发布地址与签名公钥")]
+    UpdateNotConfigured,
+    #[error("输出目录不可写: {0}")]
+    OutputNotWritable(String),
+    #[error("输出目录不存在或不是文件夹: {0}")]
+    InvalidOutputDir(String),
+    #[error("输出文件已存在: {0}")]
+    OutputExists(String),
+    #[error("磁盘空间不足：{location} 需要约 {needed_mb} MB，可用 {available_mb} MB")]
+    InsufficientSpace {
+        location: String,
+        needed_mb: u64,
+        available_mb: u64,
+    },
+    #[error("文件过大：{size_mb} MB，超过单个文件上限 {limit_mb} MB")]
+    FileTooLarge { size_mb: u64, limit_mb: u64 },
+    #[error("图片解码超过 {0} 秒仍未完成，文件可能已损坏")]
+    DecodeTimeout(u64),
+    #[error("已取消合并")]
+    Cancelled,
+    #[error("已有合并任务在进行")]
+    Busy,
+    #[error("PDF 已加密，未提供密码")]
+    PasswordRequired,
+    #[error("PDF 密码错误")]
+    WrongPassword,
+    #[error("页码范围无效: {0}")]
+    InvalidPageRange(String),
+    #[error("页码范围 {spec} 超出文档页数（共 {page_count} 页）")]
+    PageRangeOutOfBounds { spec: String, page_count: u32 },
+    #[error("拼版设置无效: {0}")]
+    InvalidNUp(String),
+}
+
+/// `extra_extensions` 为用户在设置中追加的扩展名（如 `jfif`），这类文件按内容识别后再转换。
+/// `enrich` 为真时并行读取图片尺寸、PDF 页数与加密状态，填入 `details`。
+#[tauri::command]
+fn scan_folder_cmd(
+    app: tauri::AppHandle,
+    folder_path: String,
+    extra_extensions: Option<Vec<String>>,
+    enrich: Option<bool>,
+) -> Result<Vec<InvoiceFile>, String> {
+    let config = config::current(&app);
+    config
+        .ensure_allowed(Path::new(&folder_path))
+        .map_err(|err| err.to_string())?;
+    let extra = normalize_extensions(extra_extensions);
+    let mut files = scan_folder(Path::new(&folder_path), &extra).map_err(|err| err.to_string())?;
+    if enrich.unwrap_or(false) {
+        details::enrich_files(&mut files, parallel::workers(config.max_threads));
+    }
+    Ok(files)
+}
+
+/// 超大文件夹的流式扫描：边读边以 `scan-batch` 事件分批推送（按目录顺序，未排序），
+/// 结束时发送 `scan-complete`。返回文件总数。
+#[tauri::command]
+async fn scan_folder_stream_cmd(
+    window: Window,
+    folder_path: String,
+    extra_extensions: Option<Vec<String>>,
+) -> Result<usize, String> {
+    config::current(&window.app_handle())
+        .ensure_allowed(Path::new(&folder_path))
+        .map_err(|err| err.to_string())?;
+    let extra = normalize_extensions(extra_extensions);
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut batch = Vec::with_capacity(SCAN_BATCH_SIZE);
+        let mut total = 0usize;
+        walk_folder(Path::new(&folder_path), Some(&extra), |mut file| {
+            attach_invoice_info(&mut file);
+            batch.push(file);
+            total += 1;
+            if batch.len() >= SCAN_BATCH_SIZE {
+                let _ = window.emit("scan-batch", std::mem::take(&mut batch));
+            }
+        })?;
+        if !batch.is_empty() {
+            let _ = window.emit("scan-batch", batch);
+        }
+        let _ = window.emit("scan-complete", total);
+        Ok::<_, MergeError>(total)
+    })
+    .await
+    .map_err(|err| err.to_string())?
+    .map_err(|err| err.to_string())
+}
+
+/// 分页扫描：按文件名排序后只返回 `[offset, offset + limit)` 区间，便于前端虚拟列表按需加载。
+#[tauri::command]
+async fn scan_folder_page_cmd(
+    app: tauri::AppHandle,
+    folder_path: String,
+    extra_extensions: Option<Vec<String>>,
+    offset: usize,
+    limit: usize,
+) -> Result<ScanPage, String> {
+    config::current(&app)
+        .ensure_allowed(Path::new(&folder_path))
+        .map_err(|err| err.to_string())?;
+    let extra = normalize_extensions(extra_extensions);
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut files = Vec::new();
+        walk_folder(Path::new(&folder_path), Some(&extra), |file| files.push(file))?;
+        files.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+        let total = files.len();
+        let mut files: Vec<InvoiceFile> = files.into_iter().skip(offset).take(limit).collect();
+        files.iter_mut().for_each(attach_invoice_info);
+        Ok::<_, MergeError>(ScanPage { files, total, offset })
+    })
+    .await
+    .map_err(|err| err.to_string())?
+    .map_err(|err| err.to_string())
+}
+
+/// 校验拖放到窗口上的路径，返回可直接加入列表的文件与被拒绝的原因。
+#[tauri::command]
+async fn validate_dropped_paths_cmd(
+    paths: Vec<String>,
+    existing: Vec<String>,
+    folder_path: Option<String>,
+) -> Result<DroppedPaths, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        dropped::validate_dropped_paths(&paths, &existing, folder_path.as_deref().map(Path::new))
+    })
+    .await
+    .map_err(|err| err.to_string())
+}
+
+/// 把自定义顺序导出到文件夹中的顺序文件，返回其路径。
+#[tauri::command]
+fn export_order_cmd(folder_path: String, files: Vec<InvoiceFile>) -> Result<String, String> {
+    order_file::export(Path::new(&folder_path), &files).map_err(|err| err.to_string())
+}
+
+/// 按名称列表重排文件；`text` 为空时读取文件夹中此前导出的顺序文件。
+#[tauri::command]
+fn import_order_cmd(
+    folder_path: String,
+    files: Vec<InvoiceFile>,
+    text: Option<String>,
+) -> Result<OrderImport, String> {
+    let folder = Path::new(&folder_path);
+    let text = match text.filter(|text| !text.trim().is_empty()) {
+        Some(text) => text,
+        None => order_file::read_sidecar(folder).map_err(|err| err.to_string())?,
+    };
+    Ok(order_file::apply(folder, files, &text))
+}
+
+fn normalize_extensions(extensions: Option<Vec<String>>) -> Vec<String> {
+    extensions
+        .unwrap_or_default()
+        .iter()
+        .map(|ext| ext.trim().trim_start_matches('.').to_ascii_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .collect()
+}
+
+#[tauri::command]
+async fn merge_invoices_cmd(window: Window, req: MergeRequest) -> Result<MergeResult, String> {
+    let handle = window.clone();
+    let recorded = req.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || merge_invoices(&handle, req, false))
+        .await
+        .map_err(|err| err.to_string())?;
+    let result = cancelled_result(&window, result)?;
+    if result.cancelled {
+        return Ok(result);
+    }
+    tray::remember(&window.app_handle(), &recorded, &result);
+    notify::merge_complete(&window, &result);
+    send_webhook(&window, &result);
+    Ok(result)
+}
+
+/// 合并到工作目录中的预览文件，由 `commit_merge_cmd` 决定保留或丢弃。
+#[tauri::command]
+async fn preview_merge_cmd(window: Window, req: MergeRequest) -> Result<MergeResult, String> {
+    let handle = window.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || merge_invoices(&handle, req, true))
+        .await
+        .map_err(|err| err.to_string())?;
+    cancelled_result(&window, result)
+}
+
+/// 请求取消正在进行的合并，合并循环在处理下一个文件前停止。
+#[tauri::command]
+fn cancel_merge_cmd() {
+    cancel::request();
+}
+
+/// 答复合并过程中的 `password-required` 事件；`password` 为空表示跳过该文件。
+#[tauri::command]
+fn provide_pdf_password_cmd(password: Option<String>) -> bool {
+    password::provide(password)
+}
+
+/// 文件列表的缩略图（base64 编码的 PNG），PDF 取第一页。
+/// 只读取 `folder_path`（须在允许列表内）之下的文件，路径先规范化，`..` 与符号链接都逃不出去。
+#[tauri::command]
+async fn get_thumbnail_cmd(
+    app: tauri::AppHandle,
+    folder_path: String,
+    path: String,
+    max_px: u32,
+) -> Result<String, String> {
+    let config = config::current(&app);
+    tauri::async_runtime::spawn_blocking(move || {
+        let folder = Path::new(&folder_path)
+            .canonicalize()
+            .map_err(|_| MergeError::InvalidFolder)?;
+        config.ensure_allowed(&folder)?;
+        let canon = Path::new(&path).canonicalize()?;
+        if !canon.starts_with(&folder) {
+            return Err(MergeError::FolderNotAllowed(canon.to_string_lossy().into_owned()));
+        }
+        thumbnail::render(&canon, max_px)
+    })
+    .await
+    .map_err(|err| err.to_string())?
+    .map_err(|err| err.to_string())
+}
+
+/// 取消不算失败：发出 `merge-cancelled` 并返回标记为已取消的结果。
+fn cancelled_result(window: &Window, result: Result<MergeResult, MergeError>) -> Result<MergeResult, String> {
+    match result {
+        Err(MergeError::Cancelled) => {
+            emit_event(window, "merge-cancelled", ());
+            Ok(MergeResult {
+                message: Some(MergeError::Cancelled.to_string()),
+                cancelled: true,
+                ..Default::default()
+            })
+        }
+        other => other.map_err(|err| err.to_string()),
+    }
+}
+
+/// 确认或放弃 `preview_merge_cmd` 生成的预览。只接受登记过的预览，目标路径取登记时解析的结果；
+/// 目标在预览期间被占用时与合并前一样按 `on_conflict` 处理（未指定时沿用合并请求中的设置），
+/// 需要用户选择时预览保持待确认，返回 `needs_confirmation`。
+#[tauri::command]
+async fn commit_merge_cmd(
+    window: Window,
+    preview_path: String,
+    keep: bool,
+    on_conflict: Option<ConflictAction>,
+) -> Result<MergeResult, String> {
+    let handle = window.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        commit_preview(&handle, Path::new(&preview_path), keep, on_conflict)
+    })
+    .await
+    .map_err(|err| err.to_string())?
+    .map_err(|err| err.to_string())?;
+    send_webhook(&window, &result);
+    Ok(result)
+}
+
+fn commit_preview(
+    window: &Window,
+    preview_path: &Path,
+    keep: bool,
+    on_conflict: Option<ConflictAction>,
+) -> Result<MergeResult, MergeError> {
+    let pending = preview::take(preview_path)?;
+    if !keep {
+        preview::discard(preview_path)?;
+        return Ok(MergeResult {
+            message: Some("已放弃预览".to_string()),
+            ..Default::default()
+        });
+    }
+    let on_conflict = on_conflict.or(pending.req.on_conflict);
+    if on_conflict == Some(ConflictAction::Overwrite) {
+        policy::get().ensure_allowed(policy::OVERWRITE)?;
+    }
+    let (target, resolution) = match resolve_conflict(pending.target.clone(), on_conflict) {
+        Ok(ConflictOutcome::Proceed(target, resolution)) => (target, resolution),
+        Ok(ConflictOutcome::Stop(result)) => {
+            preview::record(preview_path.to_path_buf(), pending);
+            return Ok(result);
+        }
+        Err(err) => {
+            preview::record(preview_path.to_path_buf(), pending);
+            return Err(err);
+        }
+    };
+    if let Err(err) = preview::move_into_place(preview_path, &target) {
+        preview::record(preview_path.to_path_buf(), pending);
+        return Err(err);
+    }
+
+    let target = target.to_string_lossy().into_owned();
+    let mut result = pending.result;
+    result.output_path = target.clone();
+    result.output_paths = vec![target];
+    result.target_path = None;
+    result.conflict_resolution = resolution.or(result.conflict_resolution);
+    finish_output(window, &pending.req, &mut result);
+    Ok(result)
+}
+
+#[tauri::command]
+async fn validate_files_cmd(
+    app: tauri::AppHandle,
+    files: Vec<InvoiceFile>,
+) -> Result<Vec<FileValidation>, String> {
+    let db = invoice_db(&app);
+    let size_limit = validate::size_limit_bytes(config::current(&app).max_file_mb);
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut results = validate::validate_files(&files, size_limit);
+        // 共享库暂时不可达时仍返回其余检查结果
+        if let Some(db) = db {
+            let _ = invoice_db::annotate(&db, &files, &mut results);
+        }
+        results
+    })
+    .await
+    .map_err(|err| err.to_string())
+}
+
+fn invoice_db(app: &tauri::AppHandle) -> Option<InvoiceDb> {
+    config::current(app)
+        .duplicate_db
+        .or_else(|| portable::app_dir(app, AppDir::Config).map(|dir| dir.join(invoice_db::DEFAULT_DB_FILE)))
+        .map(InvoiceDb::open)
+}
+
+#[tauri::command]
+async fn plan_merge_cmd(app: tauri::AppHandle, req: MergeRequest) -> Result<MergePlan, String> {
+    let config = config::current(&app);
+    tauri::async_runtime::spawn_blocking(move || plan::plan_merge(&config, req))
+        .await
+        .map_err(|err| err.to_string())?
+        .map_err(|err| err.to_string())
+}
+
+/// 取走启动参数中的 `--folder`，之后再次调用返回空。
+#[tauri::command]
+fn launch_folder_cmd(state: tauri::State<'_, LaunchFolder>) -> Option<String> {
+    state
+        .0
+        .lock()
+        .ok()
+        .and_then(|mut folder| folder.take())
+        .map(|folder| folder.to_string_lossy().into_owned())
+}
+
+/// 取走启动时收到的 `invoicemerge://` 链接；链接无效或文件夹不在允许范围内时返回错误说明。
+/// 前端须经用户确认后才扫描、合并。
+#[tauri::command]
+fn launch_link_cmd(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, PendingLink>,
+) -> Result<Option<DeepLink>, String> {
+    let link = state.0.lock().ok().and_then(|mut link| link.take()).transpose()?;
+    if let Some(link) = &link {
+        config::current(&app)
+            .ensure_allowed(Path::new(&link.folder))
+            .map_err(|err| err.to_string())?;
+    }
+    Ok(link)
+}
+
+/// 托盘模式下关闭窗口只隐藏到托盘，监视等后台任务继续运行。
+#[tauri::command]
+fn set_tray_mode_cmd(state: tauri::State<'_, TrayState>, enabled: bool) {
+    state.set_hide_on_close(enabled);
+}
+
+#[tauri::command]
+async fn check_update_cmd(app: tauri::AppHandle) -> Result<Option<UpdateInfo>, String> {
+    update::check(app).await.map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+async fn install_update_cmd(app: tauri::AppHandle) -> Result<bool, String> {
+    update::install(app).await.map_err(|err| err.to_string())
+}
+
+fn crash_dir(app: &tauri::AppHandle) -> Option<PathBuf> {
+    portable::app_dir(app, AppDir::Log).map(|dir| dir.join("crash"))
+}
+
+/// 启动本地自动化接口（仅 127.0.0.1），提供 `GET /status`、`POST /scan`、`POST /merge`，
+/// 请求需带 `Authorization: Bearer <token>`。`port` 为 0 时由系统分配。
+#[tauri::command]
+fn start_http_api_cmd(
+    app: tauri::AppHandle,
+    port: u16,
+    token: Option<String>,
+) -> Result<HttpApiInfo, String> {
+    policy::get()
+        .ensure_allowed(policy::HTTP_API)
+        .map_err(|err| err.to_string())?;
+    http_api::start(&app, port, token).map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+fn stop_http_api_cmd(app: tauri::AppHandle) {
+    http_api::stop(&app);
+}
+
+/// 管理员下发的锁定设置，前端据此禁用对应选项。
+#[tauri::command]
+fn get_policy_cmd() -> Policy {
+    policy::get().clone()
+}
+
+#[tauri::command]
+fn get_config_cmd(app: tauri::AppHandle) -> AppConfig {
+    config::current(&app)
+}
+
+/// 列出本地保存的崩溃报告（路径已脱敏），供用户反馈问题时附上。
+#[tauri::command]
+fn crash_reports_cmd(app: tauri::AppHandle) -> Result<Vec<CrashReport>, String> {
+    match crash_dir(&app) {
+        Some(dir) => crash::list_reports(&dir).map_err(|err| err.to_string()),
+        None => Ok(Vec::new()),
+    }
+}
+
+#[tauri::command]
+fn delete_crash_report_cmd(app: tauri::AppHandle, file_name: String) -> Result<(), String> {
+    let dir = crash_dir(&app).ok_or_else(|| MergeError::InvalidFolder.to_string())?;
+    crash::delete_report(&dir, &file_name).map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+fn context_menu_status_cmd() -> bool {
+    shell_menu::is_registered()
+}
+
+/// 在设置中开启/关闭资源管理器的文件夹右键菜单。
+#[tauri::command]
+fn set_context_menu_cmd(enabled: bool) -> Result<(), String> {
+    if enabled {
+        policy::get()
+            .ensure_allowed(policy::CONTEXT_MENU)
+            .map_err(|err| err.to_string())?;
+    }
+    shell_menu::set_registered(enabled).map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+async fn clean_temp_cmd(
+    temp_dir: Option<String>,
+    max_age_hours: Option<u64>,
+) -> Result<CleanupReport, String> {
+    let work_dir = resolve_work_dir(temp_dir.as_deref()).map_err(|err| err.to_string())?;
+    let max_age = max_age_hours
+        .map(|hours| Duration::from_secs(hours * 60 * 60))
+        .unwrap_or(cleanup::DEFAULT_MAX_AGE);
+    tauri::async_runtime::spawn_blocking(move || cleanup::sweep_temp_dir(&work_dir, max_age))
+        .await
+        .map_err(|err| err.to_string())
+}
+
+fn scan_folder(path: &Path, extra_extensions: &[String]) -> Result<Vec<InvoiceFile>, MergeError> {
+    let mut results = Vec::new();
+    walk_folder(path, Some(extra_extensions), |file| results.push(file))?;
+    results.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    results.iter_mut().for_each(attach_invoice_info);
+    Ok(results)
+}
+
+/// 解析 PDF 内嵌或独立的发票 XML；开销较大，分页扫描时只对当前页调用。
+fn attach_invoice_info(file: &mut InvoiceFile) {
+    let path = Path::new(&file.path);
+    file.invoice_info = match file.ext.as_str() {
+        "pdf" => einvoice_xml::extract_embedded_invoice(path),
+        "xml" => einvoice_xml::read_invoice_xml(path),
+        _ => None,
+    };
+}
+
+/// 按目录顺序逐个回调可合并的文件，不排序。`extra_extensions` 为 `None` 时不按扩展名过滤。
+fn walk_folder(
+    path: &Path,
+    extra_extensions: Option<&[String]>,
+    mut on_file: impl FnMut(InvoiceFile),
+) -> Result<(), MergeError> {
+    if !path.exists() || !path.is_dir() {
+        return Err(MergeError::InvalidFolder);
+    }
+
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        if !meta.is_file() {
+            continue;
+        }
+
+        let ext = entry
+            .path()
+            .extension()
+            .and_then(|s| s.to_str())
+            .unwrap_or("")
+            .to_ascii_lowercase();
+
+        if let Some(extra) = extra_extensions {
+            if !VALID_EXTENSIONS.contains(&ext.as_str()) && !extra.contains(&ext) {
+                continue;
+            }
+        }
+
+        on_file(invoice_file_at(&entry.path(), &meta));
+    }
+
+    Ok(())
+}
+
+fn invoice_file_at(path: &Path, meta: &fs::Metadata) -> InvoiceFile {
+    let ext = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+
+    let modified_ts = meta
+        .modified()
+        .ok()
+        .and_then(|mtime| mtime.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_else(|| {
+            let now: DateTime<Local> = Local::now();
+            now.timestamp()
+        });
+
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    InvoiceFile {
+        id: file_id(meta, path),
+        path: path.to_string_lossy().into_owned(),
+        file_name,
+        ext,
+        modified_ts,
+        size: meta.len(),
+        invoice_info: None,
+        page_size: None,
+        page_rotations: BTreeMap::new(),
+        page_ranges: None,
+        details: None,
+    }
+}
+
+/// 文件的稳定 ID：Unix 取设备号与 inode，Windows 取卷序列号与文件索引，重命名或修改内容后都不变。
+fn file_id(meta: &fs::Metadata, path: &Path) -> String {
+    jobs::hex_prefix(&Sha256::digest(identity_key(meta, path).as_bytes()))
+}
+
+#[cfg(unix)]
+fn identity_key(meta: &fs::Metadata, _path: &Path) -> String {
+    use std::os::unix::fs::MetadataExt;
+    format!("{}:{}", meta.dev(), meta.ino())
+}
+
+#[cfg(windows)]
+fn identity_key(meta: &fs::Metadata, path: &Path) -> String {
+    use std::os::windows::{fs::MetadataExt, io::AsRawHandle};
+    use windows_sys::Win32::Storage::FileSystem::{GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION};
+
+    let by_handle = fs::File::open(path).ok().and_then(|file| {
+        // SAFETY: 全零是该纯数据结构的合法值；句柄在 `file` 存活期间有效
+        let mut info: BY_HANDLE_FILE_INFORMATION = unsafe { std::mem::zeroed() };
+        let ok = unsafe { GetFileInformationByHandle(file.as_raw_handle() as _, &mut info) } != 0;
+        ok.then(|| {
+            let index = (u64::from(info.nFileIndexHigh) << 32) | u64::from(info.nFileIndexLow);
+            format!("{}:{index}", info.dwVolumeSerialNumber)
+        })
+    });
+    // 打不开时退回创建时间；同一批解压或复制出的文件创建时间可能相同，再加上大小和路径区分
+    by_handle.unwrap_or_else(|| {
+        format!(
+            "{}:{}:{}",
+            meta.creation_time(),
+            meta.file_size(),
+            path.to_string_lossy()
+        )
+    })
+}
+
+#[cfg(not(any(unix, windows)))]
+fn identity_key(_meta: &fs::Metadata, path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+/// 按 `file_ids` 重新读取文件夹，得到与 ID 对应的最新文件信息；`files` 中同 ID 的单独设置会保留。
+/// 找不到的 ID 以空路径占位，合并时记为“文件不存在”。
+fn resolve_file_ids(req: &mut MergeRequest) -> Result<(), MergeError> {
+    if req.file_ids.is_empty() {
+        return Ok(());
+    }
+    let mut current: HashMap<String, InvoiceFile> = HashMap::new();
+    walk_folder(Path::new(&req.folder_path), None, |file| {
+        current.insert(file.id.clone(), file);
+    })?;
+
+    req.files = req
+        .file_ids
+        .iter()
+        .map(|id| {
+            let overrides = req.files.iter().find(|file| &file.id == id);
+            match current.get(id) {
+                Some(file) => InvoiceFile {
+                    page_size: overrides.and_then(|file| file.page_size),
+                    page_rotations: overrides
+                        .map(|file| file.page_rotations.clone())
+                        .unwrap_or_default(),
+                    page_ranges: overrides.and_then(|file| file.page_ranges.clone()),
+                    ..file.clone()
+                },
+                None => InvoiceFile {
+                    id: id.clone(),
+                    file_name: id.clone(),
+                    ..Default::default()
+                },
+            }
+        })
+        .collect();
+    Ok(())
+}
+
+fn sort_files(files: &mut [InvoiceFile], sort_mode: SortMode, descending: bool) {
+    match sort_mode {
+        SortMode::FileNameAsc => {
+            files.sort_by(|a, b| a.file_name.to_lowercase().cmp(&b.file_name.to_lowercase()))
+        }
+        SortMode::ModifiedAsc => files.sort_by_key(|f| f.modified_ts),
+        SortMode::InvoiceNumberAsc => files.sort_by_cached_key(|f| {
+            // 没有号码的文件排在最后，并按文件名保持稳定顺序
+            let number = invoice_number_from_name(&f.file_name);
+            (number.is_none(), number, f.file_name.to_lowercase())
+        }),
+        SortMode::Custom => {}
+    }
+    if descending {
+        files.reverse();
+    }
+}
+
+/// 取文件名中最长的一段连续数字（至少 8 位，即传统发票号码长度）作为发票号码。
+fn invoice_number_from_name(file_name: &str) -> Option<u128> {
+    file_name
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|run| run.len() >= 8)
+        .max_by_key(|run| run.len())
+        .and_then(|run| run.parse().ok())
+}
+
+fn merge_invoices(window: &Window, mut req: MergeRequest, preview: bool) -> Result<MergeResult, MergeError> {
+    let _merging = cancel::begin()?;
+    cancel::reset();
+    let started = Instant::now();
+    // 清掉上一次合并遗留的字体提示
+    text_page::take_font_fallback();
+    let mut timings = PhaseTimings::default();
+    let folder_path = PathBuf::from(&req.folder_path);
+    if !folder_path.exists() || !folder_path.is_dir() {
+        return Err(MergeError::InvalidFolder);
+    }
+    let folder_real = folder_path.canonicalize()?;
+    let config = config::current(&window.app_handle());
+    config.ensure_allowed(&folder_real)?;
+    config.apply_profile(&mut req, &folder_real)?;
+    let policy = policy::get();
+    policy.check_request(&req)?;
+    if req.page_size.is_none() {
+        req.page_size = config.page_size;
+    }
+    if req.downsample.is_none() {
+        req.downsample = req
+            .compression_quality
+            .map(DownsampleOptions::from_quality)
+            .or_else(|| config.downsample());
+    }
+    let workers = parallel::workers(req.max_threads.or(config.max_threads));
+    let size_limit = validate::size_limit_bytes(req.max_file_mb.or(config.max_file_mb));
+    req.decode_timeout_secs = req.decode_timeout_secs.or(config.decode_timeout_secs);
+
+    resolve_file_ids(&mut req)?;
+    let append_base = req
+        .append_to
+        .as_deref()
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(append::resolve_base)
+        .transpose()?;
+    if let Some(base) = &append_base {
+        // 已有的合并文件通常就在源文件夹里，不能再把它当作新文件合并进去
+        cover::exclude(&mut req.files, base);
+    }
+    let cover_pdf = match req
+        .cover_pdf
+        .as_deref()
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+    {
+        Some(_) if append_base.is_some() => {
+            emit_warning(
+                window,
+                "cover",
+                "追加模式下保留原文件的封面，已忽略指定的封面".to_string(),
+            );
+            None
+        }
+        Some(path) => Some(cover::resolve(path)?),
+        None => None,
+    };
+    if let Some(cover) = &cover_pdf {
+        cover::exclude(&mut req.files, cover);
+    }
+    if let Some(nup) = &req.n_up {
+        nup.validate()?;
+    }
+    let insert_templates = req
+        .insertions
+        .iter()
+        .map(InsertRule::resolve_pdf)
+        .collect::<Result<Vec<_>, _>>()?;
+    for template in insert_templates.iter().flatten() {
+        cover::exclude(&mut req.files, template);
+    }
+    sort_files(&mut req.files, req.sort_mode, req.descending);
+
+    let total_files = req.files.len();
+    if total_files == 0 {
+        return Err(MergeError::NoFiles);
+    }
+
+    let mut pdf_inputs = Vec::new();
+    let mut temp_paths: Vec<TempPath> = Vec::new();
+    let mut failed = Vec::new();
+    let mut skipped = Vec::new();
+    let convert_opts = ConvertOptions::from_request(&req);
+    let mut nup_batch = nup::Batch::default();
+    // 任务目录不可用时仍可合并，只是失败后无法续做
+    let work_dir = resolve_work_dir(req.temp_dir.as_deref())?;
+    if req.temp_dir.as_deref().is_some_and(|dir| !dir.trim().is_empty()) {
+        if let Some(state_dir) = portable::app_dir(&window.app_handle(), AppDir::Cache) {
+            let _ = cleanup::remember_work_dir(&state_dir, &work_dir);
+        }
+    }
+    let chosen_output_dir = req
+        .output_dir
+        .as_deref()
+        .map(str::trim)
+        .filter(|dir| !dir.is_empty());
+    let (output_dir, output_fallback_used) = match (&policy.forced_output_dir, chosen_output_dir) {
+        (Some(dir), _) => (resolve_forced_output_dir(dir)?, false),
+        (None, Some(dir)) => (resolve_chosen_output_dir(Path::new(dir))?, false),
+        (None, None) => resolve_output_dir(&folder_real, req.fallback_output_dir.as_deref())?,
+    };
+    ensure_free_space(&req.files, &work_dir, &output_dir)?;
+    let output_name = match req
+        .output_file_name
+        .clone()
+        .or_else(|| config.output_name(&folder_real))
+        .as_deref()
+    {
+        Some(name) => sanitize_output_name(name)?,
+        None => None,
+    }
+    .map(|name| {
+        if name.to_ascii_lowercase().ends_with(".pdf") {
+            name
+        } else {
+            format!("{name}.pdf")
+        }
+    })
+    .unwrap_or_else(|| {
+        let now = Local::now();
+        format!("merged_invoices_{}.pdf", now.format("%Y%m%d_%H%M"))
+    });
+
+    if let Some(base) = append_base
+        .as_ref()
+        .filter(|_| policy.forced_output_dir.is_some())
+    {
+        if !base.starts_with(&output_dir) {
+            return Err(MergeError::PolicyForbidden("追加到指定输出目录以外的文件".into()));
+        }
+    }
+    let mut target_path = append_base
+        .clone()
+        .unwrap_or_else(|| output_dir.join(output_name));
+    let mut conflict_resolution = None;
+    if append_base.is_none() {
+        match resolve_conflict(target_path, req.on_conflict)? {
+            ConflictOutcome::Proceed(path, resolution) => {
+                target_path = path;
+                conflict_resolution = resolution;
+            }
+            ConflictOutcome::Stop(result) => return Ok(result),
+        }
+    }
+    let job = JobStore::open(&req, &work_dir).ok();
+    let cache = portable::app_dir(&window.app_handle(), AppDir::Cache).and_then(|dir| {
+        ConversionCache::open(
+            dir.join("conversions"),
+            req.cache_limit_mb.unwrap_or(DEFAULT_CACHE_LIMIT_MB),
+        )
+        .ok()
+    });
+
+    // 上次完整合并过的文件原样保留在上次的输出里，只转换新增的文件
+    let file_keys: Vec<String> = req
+        .files
+        .iter()
+        .map(|file| incremental::file_key(file, &convert_opts.for_file(file)))
+        .collect();
+    let settings_key = incremental::settings_key(&req);
+    let state_path = portable::app_dir(&window.app_handle(), AppDir::Cache)
+        .map(|dir| incremental::state_path(&dir, &folder_real));
+    let mut reused_prefix = 0;
+    let mut page_map = PageMapBuilder::default();
+    // 键为 `pdf_inputs` 的下标；仅在请求带标签输出时填写
+    let mut page_tags: HashMap<usize, PageTag> = HashMap::new();
+    // 键为 `pdf_inputs` 中的下标，只合并其中选中的页
+    let mut page_selections: HashMap<usize, PageRanges> = HashMap::new();
+    if let Some(base) = &append_base {
+        let (path_buf, temp_path) = append::copy_base(base, &work_dir)?;
+        pdf_inputs.push(path_buf);
+        temp_paths.push(temp_path);
+        page_map.push(
+            0..1,
+            PageSourceKind::Existing,
+            &path_file_name(base),
+            &base.to_string_lossy(),
+        );
+    } else if let Some(reuse) = state_path
+        .as_deref()
+        .filter(|_| req.max_output_mb.is_none() && !req.summary_page && req.source_footer.is_none())
+        .and_then(|path| incremental::reusable(path, &file_keys, &settings_key, &work_dir))
+    {
+        reused_prefix = reuse.prefix_len;
+        page_map.push_reused(pdf_inputs.len(), &reuse.page_map);
+        pdf_inputs.push(reuse.pdf);
+        temp_paths.push(reuse.temp);
+    } else if let Some(cover) = &cover_pdf {
+        // 上次的输出已经以封面开头，只有重新生成时才放入
+        page_map.push(
+            pdf_inputs.len()..pdf_inputs.len() + 1,
+            PageSourceKind::Cover,
+            &path_file_name(cover),
+            &cover.to_string_lossy(),
+        );
+        pdf_inputs.push(cover.clone());
+    }
+
+    let total_bytes: u64 = req.files.iter().map(|f| f.size).sum();
+    let mut done_bytes = 0u64;
+    // 复用的上次输出里，前面的文件已按顺序编好号
+    let mut stamped = reused_prefix;
+    let numbered_pages = if req.bates.is_some() && (append_base.is_some() || reused_prefix > 0) {
+        plan::pdf_page_count(&pdf_inputs[0])? as usize
+    } else {
+        0
+    };
+    timings.scan_ms = elapsed_ms(started);
+    let convert_started = Instant::now();
+
+    for (index, file) in req.files.iter().enumerate() {
+        cancel::check()?;
+        crash::set_operation(format!(
+            "转换第 {}/{} 个文件（.{}）",
+            index + 1,
+            total_files,
+            file.ext
+        ));
+        emit_progress(
+            window,
+            index,
+            total_files,
+            (done_bytes, total_bytes),
+            ProgressPhase::Scan,
+        );
+        done_bytes += file.size;
+        if index < reused_prefix {
+            continue;
+        }
+        let insertions = insert::before(&req.insertions, &insert_templates, &req.files, index);
+        if let Some(nup) = &req.n_up {
+            // 拼版页只放相邻的图片，遇到其他文件或插入页时先把已攒的图片输出
+            let is_image = pipeline_ext(&file.ext, Path::new(&file.path))
+                .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.as_str()));
+            if nup_batch.len() >= nup.per_page || !is_image || !insertions.is_empty() {
+                flush_nup(
+                    &mut nup_batch,
+                    &req,
+                    &work_dir,
+                    &mut pdf_inputs,
+                    &mut temp_paths,
+                    &mut page_map,
+                    &mut page_tags,
+                )?;
+            }
+        }
+        for insertion in insertions {
+            let start = pdf_inputs.len();
+            let (name, path) = match insertion {
+                Insertion::Pdf(path) => {
+                    let source = (path_file_name(&path), path.to_string_lossy().into_owned());
+                    pdf_inputs.push(path);
+                    source
+                }
+                Insertion::Divider(label) => {
+                    match insert::divider_page(&label, &work_dir) {
+                        Ok((path_buf, temp_path)) => {
+                            if req.tagged_pdf {
+                                page_tags.insert(pdf_inputs.len(), PageTag::Text { text: label.clone() });
+                            }
+                            pdf_inputs.push(path_buf);
+                            temp_paths.push(temp_path);
+                        }
+                        Err(err) => emit_warning(window, "insert", format!("生成分隔页失败: {err}")),
+                    }
+                    (label, String::new())
+                }
+            };
+            page_map.push(start..pdf_inputs.len(), PageSourceKind::Insert, &name, &path);
+        }
+        let first_input = pdf_inputs.len();
+        let failure: Option<(FailureStage, FailureKind, String)> = 'convert: {
+            let candidate = PathBuf::from(&file.path);
+            if !candidate.exists() {
+                break 'convert Some((FailureStage::Scan, FailureKind::Missing, "文件不存在".to_string()));
+            }
+
+            let canon = match candidate.canonicalize() {
+                Ok(c) => c,
+                Err(err) => break 'convert Some(failure_of(FailureStage::Scan, err.into())),
+            };
+
+            if !canon.starts_with(&folder_real) {
+                break 'convert Some((
+                    FailureStage::Scan,
+                    FailureKind::OutsideFolder,
+                    "文件不在所选文件夹内".to_string(),
+                ));
+            }
+            let size = fs::metadata(&canon).map_or(file.size, |meta| meta.len());
+            if let Err(err) = validate::check_size(size, size_limit) {
+                break 'convert Some(failure_of(FailureStage::Scan, err));
+            }
+
+            let Some(ext) = pipeline_ext(&file.ext, &canon) else {
+                break 'convert Some((
+                    FailureStage::Convert,
+                    FailureKind::Unsupported,
+                    MergeError::Unsupported(file.ext.clone()).to_string(),
+                ));
+            };
+            if is_mislabeled(&file.ext, &ext) {
+                emit_warning(
+                    window,
+                    "mislabeled",
+                    format!(
+                        "{} 实际为 {} 文件，已按实际格式处理",
+                        file.file_name,
+                        ext.to_uppercase()
+                    ),
+                );
+            }
+            let is_xfa = ext == "pdf" && validate::pdf_has_xfa(&canon);
+            if is_xfa && !req.rasterize_xfa {
+                emit_warning(
+                    window,
+                    "xfa",
+                    format!("{} 为 XFA 表单，合并后可能显示空白", file.file_name),
+                );
+            }
+
+            let file_opts = convert_opts.for_file(file);
+            let variant = file_opts.cache_key();
+            if is_xfa && req.rasterize_xfa {
+                match rasterize_to_pdfs(&canon, &file_opts, &work_dir) {
+                    Ok(pages) => {
+                        for (path_buf, temp_path) in pages {
+                            pdf_inputs.push(path_buf);
+                            temp_paths.push(temp_path);
+                        }
+                    }
+                    Err(err) => break 'convert Some(failure_of(FailureStage::Convert, err)),
+                }
+            } else if ext == "pdf" {
+                // 只有需要裁剪、单独指定了纸张或页面旋转的 PDF 才重新生成，其余保持原样
+                let crop_padding = req
+                    .crop_to_content
+                    .then(|| req.crop_padding_mm.unwrap_or(crop::DEFAULT_CROP_PADDING_MM));
+                // 空密码打不开的加密 PDF 先向用户索要密码，之后都使用解密后的副本
+                let source = match password::unlock(window, &canon, &file.file_name, &work_dir) {
+                    Ok(Some((path_buf, temp_path))) => {
+                        temp_paths.push(temp_path);
+                        path_buf
+                    }
+                    Ok(None) => canon,
+                    Err(err) => break 'convert Some(failure_of(FailureStage::Convert, err)),
+                };
+                if let Some(spec) = req.page_ranges_for(file) {
+                    // 先确认至少选中一页，否则记为该文件失败而不是在合并阶段中断
+                    let selected = PageRanges::parse(spec).and_then(|ranges| {
+                        ranges.select_some(plan::pdf_page_count(&source)?)?;
+                        Ok(ranges)
+                    });
+                    match selected {
+                        Ok(ranges) => {
+                            page_selections.insert(pdf_inputs.len(), ranges);
+                        }
+                        Err(err) => break 'convert Some(failure_of(FailureStage::Convert, err)),
+                    }
+                }
+                if crop_padding.is_none() && file.page_size.is_none() && file.page_rotations.is_empty() {
+                    pdf_inputs.push(source);
+                } else {
+                    let adjust = |crop_padding| {
+                        page_fit::adjust_pdf(
+                            &source,
+                            file.page_size,
+                            &file.page_rotations,
+                            crop_padding,
+                            &work_dir,
+                        )
+                    };
+                    let mut adjusted = adjust(crop_padding);
+                    if let (Err(err), Some(_)) = (&adjusted, crop_padding) {
+                        // 裁边失败（如缺少 Pdfium）不应连累整个文件，改为不裁边再试一次
+                        let crop_error = err.to_string();
+                        adjusted = adjust(None);
+                        if adjusted.is_ok() {
+                            emit_warning(
+                                window,
+                                "crop",
+                                format!("{} 自动裁边失败，已按原页面合并: {crop_error}", file.file_name),
+                            );
+                        }
+                    }
+                    match adjusted {
+                        Ok((path_buf, temp_path)) => {
+                            pdf_inputs.push(path_buf);
+                            temp_paths.push(temp_path);
+                        }
+                        Err(err) => break 'convert Some(failure_of(FailureStage::Convert, err)),
+                    }
+                }
+            } else if req.n_up.is_some() && IMAGE_EXTENSIONS.contains(&ext.as_str()) {
+                match prepare_image(&canon, &file_opts) {
+                    Ok(image) => nup_batch.push(
+                        nup::shrink_for_sheet(image, convert_opts.page_size),
+                        &file.file_name,
+                        &file.path,
+                    ),
+                    Err(MergeError::BlankImage) => {
+                        emit_warning(
+                            window,
+                            "blank",
+                            format!("{} 几乎为纯色图片，已跳过", file.file_name),
+                        );
+                        skipped.push(file.file_name.clone());
+                        continue;
+                    }
+                    Err(err) => break 'convert Some(failure_of(FailureStage::Convert, err)),
+                }
+            } else if let Some(cached) = cache.as_ref().and_then(|cache| cache.lookup(&canon, &variant)) {
+                pdf_inputs.push(cached);
+            } else if let Some(done) = job.as_ref().and_then(|job| job.lookup(file, &variant)) {
+                pdf_inputs.push(done);
+            } else {
+                match convert_to_pdf(&ext, &canon, &work_dir, &file_opts) {
+                    Ok((path_buf, temp_path)) => {
+                        // 缓存与任务目录各存一份：缓存可能因体积上限被清理，失败重试时仍能从任务目录续上
+                        let cached = cache
+                            .as_ref()
+                            .and_then(|cache| cache.store(&canon, &variant, &path_buf).ok());
+                        let stored = job
+                            .as_ref()
+                            .and_then(|job| job.store(file, &variant, &path_buf).ok())
+                            .or(cached);
+                        match stored {
+                            Some(stored) => pdf_inputs.push(stored),
+                            None => {
+                                pdf_inputs.push(path_buf);
+                                temp_paths.push(temp_path);
+                            }
+                        }
+                    }
+                    Err(MergeError::BlankImage) => {
+                        emit_warning(
+                            window,
+                            "blank",
+                            format!("{} 几乎为纯色图片，已跳过", file.file_name),
+                        );
+                        skipped.push(file.file_name.clone());
+                        continue;
+                    }
+                    Err(err) => break 'convert Some(failure_of(FailureStage::Convert, err)),
+                }
+            }
+            None
+        };
+
+        if let Some((stage, kind, reason)) = failure {
+            if req.failure_placeholders {
+                match failure_placeholder(&file.file_name, &reason, &work_dir) {
+                    Ok((path_buf, temp_path)) => {
+                        if req.tagged_pdf {
+                            let text = format!("文件 {} 处理失败，原因: {reason}", file.file_name);
+                            page_tags.insert(pdf_inputs.len(), PageTag::Text { text });
+                        }
+                        page_map.push(
+                            pdf_inputs.len()..pdf_inputs.len() + 1,
+                            PageSourceKind::Placeholder,
+                            &file.file_name,
+                            &file.path,
+                        );
+                        pdf_inputs.push(path_buf);
+                        temp_paths.push(temp_path);
+                    }
+                    Err(err) => emit_warning(window, "placeholder", format!("生成占位页失败: {err}")),
+                }
+            }
+            failed.push(FailedFile {
+                file_name: file.file_name.clone(),
+                path: file.path.clone(),
+                stage,
+                kind,
+                message: reason,
+            });
+            continue;
+        }
+        if let Some(options) = req
+            .attachment_stamp
+            .as_ref()
+            .filter(|_| pdf_inputs.len() > first_input)
+        {
+            let label = options.label(stamped);
+            stamped += 1;
+            match stamp::stamp_first_page(&pdf_inputs[first_input], &label, options.font_size, &work_dir) {
+                Ok((path_buf, temp_path)) => {
+                    pdf_inputs[first_input] = path_buf;
+                    temp_paths.push(temp_path);
+                }
+                Err(err) => emit_warning(
+                    window,
+                    "stamp",
+                    format!("{} 加盖“{label}”失败: {err}", file.file_name),
+                ),
+            }
+        }
+        if req.tagged_pdf
+            && pipeline_ext(&file.ext, Path::new(&file.path))
+                .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.as_str()))
+        {
+            for input in first_input..pdf_inputs.len() {
+                let alt = format!("发票图片：{}", file.file_name);
+                page_tags.insert(input, PageTag::Figure { alt });
+            }
+        }
+        page_map.push(
+            first_input..pdf_inputs.len(),
+            PageSourceKind::Source,
+            &file.file_name,
+            &file.path,
+        );
+        emit_file_converted(window, index, &file.file_name, &pdf_inputs[first_input..]);
+        emit_progress(
+            window,
+            index + 1,
+            total_files,
+            (done_bytes, total_bytes),
+            ProgressPhase::Convert,
+        );
+    }
+
+    flush_nup(
+        &mut nup_batch,
+        &req,
+        &work_dir,
+        &mut pdf_inputs,
+        &mut temp_paths,
+        &mut page_map,
+        &mut page_tags,
+    )?;
+    if pdf_inputs.is_empty() {
+        return Err(MergeError::NoFiles);
+    }
+    timings.convert_ms = elapsed_ms(convert_started);
+
+    if req.failure_appendix && !failed.is_empty() {
+        match failure_appendix(&failed, &work_dir) {
+            Ok((path_buf, temp_path)) => {
+                if req.tagged_pdf {
+                    let names: Vec<&str> = failed.iter().map(|failure| failure.file_name.as_str()).collect();
+                    let text = format!("以下 {} 个文件未能合并：{}", failed.len(), names.join("、"));
+                    page_tags.insert(pdf_inputs.len(), PageTag::Text { text });
+                }
+                page_map.push(
+                    pdf_inputs.len()..pdf_inputs.len() + 1,
+                    PageSourceKind::Appendix,
+                    "失败清单",
+                    "",
+                );
+                pdf_inputs.push(path_buf);
+                temp_paths.push(temp_path);
+            }
+            Err(err) => emit_warning(window, "appendix", format!("生成失败清单页失败: {err}")),
+        }
+    }
+
+    let output_path = if preview {
+        preview::reserve_preview_path(&work_dir)?
+    } else {
+        target_path.clone()
+    };
+    let merge_started = Instant::now();
+    crash::set_operation(format!("合并 {} 个 PDF", pdf_inputs.len()));
+    let extras = InputExtras {
+        page_tags: &page_tags,
+        page_selections: &page_selections,
+        page_map: &page_map,
+    };
+    // 有体积上限时可能要重写好几轮，先写到工作目录，全部完成后再放到输出位置，
+    // 中途取消既不会留下多余的输出，也不会覆盖已有文件
+    let size_limit = req
+        .max_output_mb
+        .filter(|mb| *mb > 0.0)
+        .map(|mb| (mb * 1024.0 * 1024.0) as u64);
+    let staged = match size_limit {
+        Some(_) => Some(
+            tempfile::Builder::new()
+                .prefix("mc-sized-")
+                .suffix(".pdf")
+                .tempfile_in(&work_dir)?
+                .into_temp_path(),
+        ),
+        None => None,
+    };
+    let write_path: &Path = staged.as_deref().unwrap_or(&output_path);
+    let (mut write_time, pages_per_input) = merge_pdf_files(
+        window,
+        &pdf_inputs,
+        write_path,
+        req.downsample.as_ref(),
+        &extras,
+        workers,
+    )?;
+    let mut page_count: usize = pages_per_input.iter().sum();
+    let mut page_map = page_map.build(&pages_per_input);
+
+    let mut size_target_met = None;
+    if let Some(limit_bytes) = size_limit {
+        let mut met = fs::metadata(write_path)?.len() <= limit_bytes;
+        for level in compress::adaptive_levels(req.downsample.as_ref()) {
+            if met {
+                break;
+            }
+            write_time += merge_pdf_files(window, &pdf_inputs, write_path, Some(&level), &extras, workers)?.0;
+            met = fs::metadata(write_path)?.len() <= limit_bytes;
+        }
+        size_target_met = Some(met);
+        cancel::check()?;
+        preview::move_into_place(write_path, &output_path)?;
+    }
+    if req.summary_page && append_base.is_none() {
+        let file_count = total_files - failed.len() - skipped.len();
+        match summary::prepend(
+            &output_path,
+            &req.folder_path,
+            file_count,
+            &mut page_map,
+            &work_dir,
+        ) {
+            Ok(pages) => page_count += pages,
+            Err(err) => emit_warning(window, "summary", format!("生成合并摘要页失败: {err}")),
+        }
+    }
+    // 页脚中的页码要计入开头的摘要页，因此在插入摘要之后再加盖
+    if let Some(footer) = &req.source_footer {
+        footer.apply(&output_path, &page_map)?;
+    }
+    let bates_last = match &req.bates {
+        Some(options) => bates::apply(&output_path, options, numbered_pages)?,
+        None => None,
+    };
+    if text_page::take_font_fallback() {
+        emit_warning(
+            window,
+            "font",
+            "未找到可用的中文字体，生成的说明页、页脚等中的中文可能无法正常显示".to_string(),
+        );
+    }
+    let mut output_paths = vec![output_path];
+    if let Some(options) = req.split.as_ref().filter(|_| !preview) {
+        let overwrite = req.on_conflict == Some(ConflictAction::Overwrite);
+        let split = split::split_output(&output_paths[0], &page_map, options, overwrite)?;
+        if split.existing {
+            conflict_resolution.get_or_insert(if overwrite {
+                ConflictAction::Overwrite
+            } else {
+                ConflictAction::Rename
+            });
+        }
+        if !split.oversized.is_empty() {
+            emit_warning(
+                window,
+                "split",
+                format!(
+                    "{} 单个文件已超过每份大小上限，单独成份",
+                    split.oversized.join("、")
+                ),
+            );
+        }
+        output_paths = split.paths;
+    }
+    let output_path = output_paths[0].clone();
+    let merge_time = merge_started.elapsed();
+    timings.merge_ms = merge_time.saturating_sub(write_time).as_millis() as u64;
+    let finish_started = Instant::now();
+    if req.match_source_mtime {
+        if let Some(latest) = req.files.iter().map(|f| f.modified_ts).max() {
+            let mtime = UNIX_EPOCH + Duration::from_secs(latest.max(0) as u64);
+            for path in &output_paths {
+                fs::File::options().write(true).open(path)?.set_modified(mtime)?;
+            }
+        }
+    }
+    emit_progress(
+        window,
+        total_files,
+        total_files,
+        (total_bytes, total_bytes),
+        ProgressPhase::Write,
+    );
+
+    // 拆分后按最大的一份判断
+    let mut output_size = 0;
+    for path in &output_paths {
+        output_size = output_size.max(fs::metadata(path)?.len());
+    }
+    let warning_mb = req
+        .oversize_warning_mb
+        .filter(|mb| *mb > 0.0)
+        .unwrap_or(DEFAULT_OVERSIZE_WARNING_MB);
+    let oversize = output_size as f64 > warning_mb * 1024.0 * 1024.0;
+    if oversize {
+        emit_warning(
+            window,
+            "oversize",
+            format!(
+                "输出文件 {:.1} MB，超过 {warning_mb} MB，建议开启图片降采样或设置大小上限",
+                output_size as f64 / 1024.0 / 1024.0
+            ),
+        );
+    }
+
+    if let Some(job) = job {
+        job.finish();
+    }
+    if let Some(cache) = &cache {
+        cache.prune();
+    }
+    timings.write_ms = (write_time + finish_started.elapsed()).as_millis() as u64;
+    timings.total_ms = elapsed_ms(started);
+
+    if !preview
+        && append_base.is_none()
+        && failed.is_empty()
+        && skipped.is_empty()
+        && req.max_output_mb.is_none()
+        && !req.summary_page
+        && output_paths.len() == 1
+    {
+        if let Some(path) = &state_path {
+            let _ = incremental::save(path, &output_path, &settings_key, file_keys, &page_map);
+        }
+    }
+
+    let mut notes = Vec::new();
+    if !failed.is_empty() {
+        notes.push(format!("{} 个文件处理失败", failed.len()));
+    }
+    if output_paths.len() > 1 {
+        notes.push(format!("已拆分为 {} 份", output_paths.len()));
+    }
+    if output_fallback_used {
+        notes.push("源文件夹不可写，已输出到备用目录".to_string());
+    }
+    if size_target_met == Some(false) {
+        notes.push(format!(
+            "已降至最低图片质量，输出仍超过 {} MB",
+            req.max_output_mb.unwrap_or_default()
+        ));
+    }
+    let message = if notes.is_empty() {
+        None
+    } else {
+        Some(notes.join("；"))
+    };
+    let merged_files = total_files - failed.len() - skipped.len();
+
+    let mut result = MergeResult {
+        success: failed.len() < total_files,
+        output_path: output_path.to_string_lossy().into_owned(),
+        output_paths: output_paths
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect(),
+        failed_files: failed.iter().map(|failure| failure.file_name.clone()).collect(),
+        failures: failed,
+        merged_files,
+        page_count,
+        message,
+        size_target_met,
+        oversize,
+        output_fallback_used,
+        skipped_files: skipped,
+        timings,
+        target_path: preview.then(|| target_path.to_string_lossy().into_owned()),
+        needs_confirmation: None,
+        conflict_resolution,
+        secondary_output_path: None,
+        secondary_output_error: None,
+        bates_last,
+        page_map,
+        cancelled: false,
+    };
+    if preview {
+        preview::record(
+            output_path,
+            preview::PendingPreview {
+                target: target_path,
+                req,
+                result: result.clone(),
+            },
+        );
+    } else {
+        finish_output(window, &req, &mut result);
+    }
+    Ok(result)
+}
+
+/// 输出落到最终位置后的收尾：第二份副本、重复发票库登记与合并后命令。
+/// 预览在确认前都不执行，避免把可能被丢弃的结果写进归档，确认后由 `commit_preview` 调用。
+fn finish_output(window: &Window, req: &MergeRequest, result: &mut MergeResult) {
+    let output_paths: Vec<PathBuf> = result.output_paths.iter().map(PathBuf::from).collect();
+    if let Some(dir) = req
+        .secondary_output_dir
+        .as_deref()
+        .map(str::trim)
+        .filter(|dir| !dir.is_empty())
+    {
+        for path in &output_paths {
+            match replicate::copy_verified(path, Path::new(dir)) {
+                Ok(copied) => {
+                    result
+                        .secondary_output_path
+                        .get_or_insert(copied.to_string_lossy().into_owned());
+                }
+                Err(err) => {
+                    emit_warning(window, "secondary_output", err.to_string());
+                    result.secondary_output_error = Some(err.to_string());
+                    break;
+                }
+            }
+        }
+    }
+
+    if let (Some(db), Some(output_path)) = (invoice_db(&window.app_handle()), output_paths.first()) {
+        let merged: Vec<&InvoiceFile> = req
+            .files
+            .iter()
+            .filter(|file| !result.failures.iter().any(|failure| failure.path == file.path))
+            .filter(|file| !result.skipped_files.contains(&file.file_name))
+            .collect();
+        if let Err(err) = db.record(&merged, output_path) {
+            emit_warning(window, "duplicate_db", err.to_string());
+        }
+    }
+
+    let config = config::current(&window.app_handle());
+    if let Some(hook) = config
+        .post_merge_hook
+        .as_ref()
+        .filter(|hook| !hook.command.trim().is_empty() && !policy::get().forbids(policy::POST_MERGE_HOOK))
+    {
+        if let Some(message) = output_paths.iter().find_map(|path| hook.run(path).err()) {
+            emit_warning(window, "post_merge_hook", message);
+            let note = "合并后命令执行失败";
+            result.message = Some(match result.message.take() {
+                Some(message) => format!("{note}；{message}"),
+                None => note.to_string(),
+            });
+        }
+    }
+}
+
+enum ConflictOutcome {
+    /// 写到该路径；目标原本存在时附带采取的处理
+    Proceed(PathBuf, Option<ConflictAction>),
+    /// 不写输出，直接返回：等待用户选择或已取消
+    Stop(MergeResult),
+}
+
+/// 目标文件已存在时按 `on_conflict` 处理。合并开始前与确认预览时都经过这里。
+fn resolve_conflict(
+    target: PathBuf,
+    on_conflict: Option<ConflictAction>,
+) -> Result<ConflictOutcome, MergeError> {
+    if !target.exists() {
+        return Ok(ConflictOutcome::Proceed(target, None));
+    }
+    match on_conflict {
+        None => Ok(ConflictOutcome::Stop(MergeResult {
+            message: Some("输出文件已存在，请选择覆盖、重命名或取消".to_string()),
+            needs_confirmation: Some(target.to_string_lossy().into_owned()),
+            ..Default::default()
+        })),
+        Some(ConflictAction::Cancel) => Ok(ConflictOutcome::Stop(MergeResult {
+            message: Some("已取消合并".to_string()),
+            ..Default::default()
+        })),
+        Some(ConflictAction::Fail) => Err(MergeError::OutputExists(target.to_string_lossy().into_owned())),
+        Some(ConflictAction::Rename) => Ok(ConflictOutcome::Proceed(
+            unique_output_path(&target),
+            Some(ConflictAction::Rename),
+        )),
+        Some(ConflictAction::Overwrite) => {
+            Ok(ConflictOutcome::Proceed(target, Some(ConflictAction::Overwrite)))
+        }
+    }
+}
+
+fn path_file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// 在同目录下找一个不冲突的文件名：`name (1).pdf`、`name (2).pdf`……
+fn unique_output_path(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let parent = path.parent().unwrap_or_else(|| Path::new(""));
+    (1..)
+        .map(|n| parent.join(format!("{stem} ({n}).pdf")))
+        .find(|candidate| !candidate.exists())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// 清理用户输入的输出文件名：拒绝路径分隔符，替换 Windows 非法字符，
+/// 去掉结尾的点和空格，并避开 CON/NUL 等保留设备名。空名返回 `None`。
+fn sanitize_output_name(name: &str) -> Result<Option<String>, MergeError> {
+    if name.contains(['/', '\\']) {
+        return Err(MergeError::InvalidOutputName);
+    }
+
+    let replaced: String = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let cleaned = replaced.trim_end_matches(['.', ' ']);
+    if cleaned.is_empty() {
+        return Ok(None);
+    }
+
+    let stem = cleaned.split('.').next().unwrap_or("").to_ascii_uppercase();
+    let reserved = matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || ((stem.starts_with("COM") || stem.starts_with("LPT"))
+            && stem.len() == 4
+            && stem.as_bytes()[3].is_ascii_digit());
+    if reserved {
+        return Ok(Some(format!("_{cleaned}")));
+    }
+    Ok(Some(cleaned.to_string()))
+}
+
+#[derive(Clone, Copy)]
+enum ProgressPhase {
+    Scan,
+    Convert,
+    Merge,
+    Write,
+}
+
+/// `bytes` 为（已处理字节数, 总字节数），前端据此按体积而非文件个数计算进度条。
+fn emit_progress(window: &Window, current: usize, total: usize, bytes: (u64, u64), phase: ProgressPhase) {
+    #[derive(Serialize, Clone)]
+    struct Payload<'a> {
+        current: usize,
+        total: usize,
+        processed_bytes: u64,
+        total_bytes: u64,
+        phase: &'a str,
+    }
+
+    let phase_label = match phase {
+        ProgressPhase::Scan => "scan",
+        ProgressPhase::Convert => "convert",
+        ProgressPhase::Merge => "merge",
+        ProgressPhase::Write => "write",
+    };
+
+    emit_event(
+        window,
+        "merge-progress",
+        Payload {
+            current,
+            total,
+            processed_bytes: bytes.0,
+            total_bytes: bytes.1,
+            phase: phase_label,
+        },
+    );
+}
+
+/// 生成一页“文件 X 处理失败: 原因”的占位页。
+fn failure_placeholder(
+    file_name: &str,
+    reason: &str,
+    work_dir: &Path,
+) -> Result<(PathBuf, TempPath), MergeError> {
+    let lines = [
+        TextLine::new(format!("文件 {file_name} 处理失败"), 16.0),
+        TextLine::blank(),
+        TextLine::new(format!("原因: {reason}"), 11.0),
+    ];
+    text_page::render_text_document("处理失败", &lines, work_dir)
+}
+
+/// 汇总所有失败文件及原因，作为合并结果的最后一页。
+fn failure_appendix(failed: &[FailedFile], work_dir: &Path) -> Result<(PathBuf, TempPath), MergeError> {
+    let mut lines = vec![
+        TextLine::new(format!("以下 {} 个文件未能合并", failed.len()), 16.0),
+        TextLine::blank(),
+    ];
+    for (index, failure) in failed.iter().enumerate() {
+        lines.push(TextLine::new(
+            format!("{}. {}", index + 1, failure.file_name),
+            11.0,
+        ));
+        lines.push(TextLine::new(format!("    原因: {}", failure.message), 9.0));
+    }
+    text_page::render_text_document("失败清单", &lines, work_dir)
+}
+
+fn elapsed_ms(since: Instant) -> u64 {
+    since.elapsed().as_millis() as u64
+}
+
+/// 单个文件转换完成后通知前端中间 PDF 的位置，用于实时预览。
+fn emit_file_converted(window: &Window, index: usize, file_name: &str, pdf_paths: &[PathBuf]) {
+    #[derive(Serialize, Clone)]
+    struct Payload<'a> {
+        index: usize,
+        file_name: &'a str,
+        pdf_paths: Vec<String>,
+    }
+
+    emit_event(
+        window,
+        "merge-file-converted",
+        Payload {
+            index,
+            file_name,
+            pdf_paths: pdf_paths
+                .iter()
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect(),
+        },
+    );
+}
+
+fn emit_warning(window: &Window, kind: &str, message: String) {
+    #[derive(Serialize, Clone)]
+    struct Payload<'a> {
+        kind: &'a str,
+        message: String,
+    }
+
+    emit_event(window, "merge-warning", Payload { kind, message });
+}
+
+/// 合并成功且配置了 webhook 时在后台发送，失败只作为警告，不影响结果。
+fn send_webhook(window: &Window, result: &MergeResult) {
+    if !result.success || result.needs_confirmation.is_some() || result.target_path.is_some() {
+        return;
+    }
+    let Some(webhook) = config::current(&window.app_handle())
+        .webhook
+        .filter(|webhook| !webhook.url.trim().is_empty() && !policy::get().forbids(policy::WEBHOOK))
+    else {
+        return;
+    };
+    let window = window.clone();
+    let result = result.clone();
+    std::thread::spawn(move || {
+        if let Err(message) = webhook.send(&result) {
+            emit_warning(&window, "webhook", message);
+        }
+    });
+}
+
+/// 发给前端；stdio 模式下同时转发为 JSON-RPC 通知。
+fn emit_event<S: Serialize + Clone>(window: &Window, event: &str, payload: S) {
+    stdio_rpc::forward(event, &payload);
+    let _ = window.emit(event, payload);
+}
+
+/// 策略强制的输出目录：不存在时创建，不可写时直接报错，不再回退到其他目录。
+fn resolve_forced_output_dir(dir: &Path) -> Result<PathBuf, MergeError> {
+    fs::create_dir_all(dir).map_err(|_| MergeError::OutputNotWritable(dir.to_string_lossy().into_owned()))?;
+    let dir = dir.canonicalize()?;
+    if !is_writable_dir(&dir) {
+        return Err(MergeError::OutputNotWritable(dir.to_string_lossy().into_owned()));
+    }
+    Ok(dir)
+}
+
+/// 用户选择的输出目录：必须已存在且可写。选错目录时直接报错，不悄悄写回源文件夹。
+fn resolve_chosen_output_dir(dir: &Path) -> Result<PathBuf, MergeError> {
+    if !dir.is_dir() {
+        return Err(MergeError::InvalidOutputDir(dir.to_string_lossy().into_owned()));
+    }
+    let dir = dir.canonicalize()?;
+    if !is_writable_dir(&dir) {
+        return Err(MergeError::OutputNotWritable(dir.to_string_lossy().into_owned()));
+    }
+    Ok(dir)
+}
+
+/// 合并前先在源文件夹试写一个探测文件；不可写时改用备用目录（若提供），
+/// 否则返回 `OutputNotWritable`，让前端提示用户另选输出位置。
+fn resolve_output_dir(folder: &Path, fallback: Option<&str>) -> Result<(PathBuf, bool), MergeError> {
+    if is_writable_dir(folder) {
+        return Ok((folder.to_path_buf(), false));
+    }
+
+    let Some(fallback) = fallback.map(str::trim).filter(|dir| !dir.is_empty()) else {
+        return Err(MergeError::OutputNotWritable(
+            folder.to_string_lossy().into_owned(),
+        ));
+    };
+    let fallback = PathBuf::from(fallback);
+    if !fallback.is_dir() {
+        return Err(MergeError::InvalidFolder);
+    }
+    let fallback = fallback.canonicalize()?;
+    if !is_writable_dir(&fallback) {
+        return Err(MergeError::OutputNotWritable(
+            fallback.to_string_lossy().into_owned(),
+        ));
+    }
+    Ok((fallback, true))
+}
+
+fn is_writable_dir(dir: &Path) -> bool {
+    tempfile::Builder::new()
+        .prefix(".mc-write-test-")
+        .tempfile_in(dir)
+        .is_ok()
+}
+
+/// 开始合并前估算中间文件与输出文件所需空间，不足时直接报错，
+/// 而不是在写出最终 PDF 时才遇到难以理解的 IO 错误。
+fn ensure_free_space(files: &[InvoiceFile], work_dir: &Path, output_dir: &Path) -> Result<(), MergeError> {
+    let (pdf_bytes, converted_bytes) = files.iter().fold((0u64, 0u64), |(pdf, converted), file| {
+        if file.ext.eq_ignore_ascii_case("pdf") {
+            (pdf + file.size, converted)
+        } else {
+            (pdf, converted + file.size * CONVERTED_SIZE_FACTOR)
+        }
+    });
+    // 预留 10% 余量
+    let temp_needed = converted_bytes + converted_bytes / 10;
+    let output_needed = (pdf_bytes + converted_bytes) + (pdf_bytes + converted_bytes) / 10;
+
+    for (location, needed) in [(work_dir, temp_needed), (output_dir, output_needed)] {
+        let Ok(available) = fs2::available_space(location) else {
+            continue;
+        };
+        if available < needed {
+            return Err(MergeError::InsufficientSpace {
+                location: location.to_string_lossy().into_owned(),
+                needed_mb: needed.div_ceil(1024 * 1024),
+                available_mb: available / 1024 / 1024,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// 用户指定的工作目录（不存在时自动创建），未指定时回退到系统临时目录。
+fn resolve_work_dir(custom: Option<&str>) -> Result<PathBuf, MergeError> {
+    match custom.map(str::trim).filter(|dir| !dir.is_empty()) {
+        Some(dir) => {
+            let dir = PathBuf::from(dir);
+            fs::create_dir_all(&dir)?;
+            Ok(dir.canonicalize()?)
+        }
+        None => Ok(std::env::temp_dir()),
+    }
+}
+
+/// 决定文件走哪条处理流程：优先按文件头识别（微信保存的 PDF 常被命名为 .jpg），
+/// 识别不出时再看扩展名。
+pub(crate) fn pipeline_ext(ext: &str, path: &Path) -> Option<String> {
+    let ext = ext.to_ascii_lowercase();
+    let known = VALID_EXTENSIONS.contains(&ext.as_str());
+    match sniff::sniff_extension(path) {
+        // 文本格式仅凭文件头区分不了（XHTML 同样以 <?xml 开头），已知扩展名优先
+        Some(sniffed) if sniffed != "xml" || !known => Some(sniffed.to_string()),
+        _ if known => Some(ext),
+        _ => None,
+    }
+}
+
+/// 内置扩展名与识别出的实际格式不一致（如 PNG 内容的 .jpg）。
+pub(crate) fn is_mislabeled(ext: &str, actual: &str) -> bool {
+    let normalize = |ext: &str| match ext.to_ascii_lowercase().as_str() {
+        "jpeg" => "jpg".to_string(),
+        other => other.to_string(),
+    };
+    let ext = normalize(ext);
+    VALID_EXTENSIONS.contains(&ext.as_str()) && ext != normalize(actual)
+}
+
+/// 按扩展名把非 PDF 输入转换为临时 PDF。
+fn convert_to_pdf(
+    ext: &str,
+    path: &Path,
+    work_dir: &Path,
+    opts: &ConvertOptions,
+) -> Result<(PathBuf, TempPath), MergeError> {
+    if IMAGE_EXTENSIONS.contains(&ext) {
+        convert_image_to_pdf(path, work_dir, opts)
+    } else if OFFICE_EXTENSIONS.contains(&ext) {
+        office::convert_office_to_pdf(path, work_dir)
+    } else if HTML_EXTENSIONS.contains(&ext) {
+        html::convert_html_to_pdf(path, work_dir)
+    } else if XML_EXTENSIONS.contains(&ext) {
+        einvoice_xml::render_einvoice_xml(path, work_dir)
+    } else {
+        Err(MergeError::Unsupported(ext.to_string()))
+    }
+}
+
+/// 将 PDF 每页渲染为图片后再逐页生成图片 PDF。
+fn rasterize_to_pdfs(
+    path: &Path,
+    opts: &ConvertOptions,
+    work_dir: &Path,
+) -> Result<Vec<(PathBuf, TempPath)>, MergeError> {
+    raster::rasterize_pdf(path, IMAGE_RENDER_DPI)?
+        .into_iter()
+        .map(|image| image_to_pdf(image, opts, work_dir))
+        .collect()
+}
+
+fn convert_image_to_pdf(
+    path: &Path,
+    work_dir: &Path,
+    opts: &ConvertOptions,
+) -> Result<(PathBuf, TempPath), MergeError> {
+    if let Some(converted) = jpeg_passthrough::convert(path, opts, work_dir)? {
+        return Ok(converted);
+    }
+    image_to_pdf(prepare_image(path, opts)?, opts, work_dir)
+}
+
+/// 解码图片并按选项做透视校正、去噪、去阴影与自动色阶，结果可直接排版。
+fn prepare_image(path: &Path, opts: &ConvertOptions) -> Result<DynamicImage, MergeError> {
+    // 超大 TIFF 边解码边缩小到纸张在渲染 DPI 下所需的像素数
+    let (page_w, page_h) = opts.page_layout.bounds_mm(opts.page_size);
+    let max_width = (page_w / 25.4 * IMAGE_RENDER_DPI).ceil() as u32;
+    let max_height = (page_h / 25.4 * IMAGE_RENDER_DPI).ceil() as u32;
+    let owned = path.to_path_buf();
+    let image = decode_guard::run(decode_guard::limit(opts.decode_timeout_secs), move || {
+        match tiff_stream::decode_fitted(&owned, max_width, max_height)? {
+            Some(image) => Ok(orientation::apply_exif(&owned, image)),
+            None => load_dynamic_image(&owned),
+        }
+    })?;
+    if opts.skip_blank && blank::is_near_blank(&image) {
+        return Err(MergeError::BlankImage);
+    }
+    // 先铺白底，否则透明区域在后续处理转 RGB 时会变黑
+    let mut image = flatten_transparent(image);
+    if opts.correct_perspective {
+        image = perspective::correct_perspective(image);
+    }
+    if opts.denoise {
+        image = enhance::denoise(image);
+    }
+    if opts.remove_shadows {
+        image = enhance::remove_shadows(image);
+    }
+    if opts.enhance {
+        image = enhance::auto_levels(image);
+    }
+    Ok(image)
+}
+
+/// 把攒下的图片拼成一页放入合并输入，页码映射中记为这几个文件共用的一页。
+fn flush_nup(
+    batch: &mut nup::Batch,
+    req: &MergeRequest,
+    work_dir: &Path,
+    pdf_inputs: &mut Vec<PathBuf>,
+    temp_paths: &mut Vec<TempPath>,
+    page_map: &mut PageMapBuilder,
+    page_tags: &mut HashMap<usize, PageTag>,
+) -> Result<(), MergeError> {
+    let Some(layout) = req.n_up.as_ref().filter(|_| !batch.is_empty()) else {
+        return Ok(());
+    };
+    let (images, name, path) = batch.take();
+    let (path_buf, temp_path) = nup::compose(
+        images,
+        layout,
+        req.page_size.unwrap_or_default(),
+        req.page_layout,
+        work_dir,
+    )?;
+    if req.tagged_pdf {
+        let alt = format!("发票图片：{name}");
+        page_tags.insert(pdf_inputs.len(), PageTag::Figure { alt });
+    }
+    page_map.push(
+        pdf_inputs.len()..pdf_inputs.len() + 1,
+        PageSourceKind::Source,
+        &name,
+        &path,
+    );
+    pdf_inputs.push(path_buf);
+    temp_paths.push(temp_path);
+    Ok(())
+}
+
+fn image_to_pdf(
+    image: DynamicImage,
+    opts: &ConvertOptions,
+    work_dir: &Path,
+) -> Result<(PathBuf, TempPath), MergeError> {
+    let mut image = flatten_transparent(image);
+    let (img_w, img_h) = image.dimensions();
+    if opts.page_layout.rotates(img_w, img_h) {
+        // 逆时针转，顺时针转一下纸张即可正常阅读
+        image = image.rotate270();
+    }
+    let (img_w, img_h) = image.dimensions();
+    let (page_w, page_h) = opts.page_layout.page_mm(opts.page_size, img_w, img_h);
+    let (doc, page1, layer1) = printpdf::PdfDocument::new(
+        "Invoice Image",
+        printpdf::Mm(page_w),
+        printpdf::Mm(page_h),
+        "Layer",
+    );
+    let current_layer = doc.get_page(page1).get_layer(layer1);
+
+    let image_object = printpdf::Image::from_dynamic_image(&image);
+
+    let aspect = img_w as f64 / img_h as f64;
+    let mut display_w = page_w;
+    let mut display_h = display_w / aspect;
+    if display_h > page_h {
+        display_h = page_h;
+        display_w = display_h * aspect;
+    }
+
+    let offset_x = (page_w - display_w) / 2.0;
+    let offset_y = (page_h - display_h) / 2.0;
+
+    let base_width_pt = (img_w.max(1) as f64 / IMAGE_RENDER_DPI) * 72.0;
+    let base_height_pt = (img_h.max(1) as f64 / IMAGE_RENDER_DPI) * 72.0;
+    let target_width_pt = (display_w / 25.4) * 72.0;
+    let target_height_pt = (display_h / 25.4) * 72.0;
+    let scale_x = if base_width_pt == 0.0 {
+        1.0
+    } else {
+        target_width_pt / base_width_pt
+    };
+    let scale_y = if base_height_pt == 0.0 {
+        1.0
+    } else {
+        target_height_pt / base_height_pt
+    };
+
+    image_object.add_to_layer(
+        current_layer,
+        printpdf::ImageTransform {
+            translate_x: Some(printpdf::Mm(offset_x)),
+            translate_y: Some(printpdf::Mm(offset_y)),
+            rotate: None,
+            scale_x: Some(scale_x),
+            scale_y: Some(scale_y),
+            dpi: Some(IMAGE_RENDER_DPI),
+        },
+    );
+
+    save_temp_pdf(doc, "mc-image-", work_dir)
+}
+
+fn save_temp_pdf(
+    doc: printpdf::PdfDocumentReference,
+    prefix: &str,
+    work_dir: &Path,
+) -> Result<(PathBuf, TempPath), MergeError> {
+    let temp_file = tempfile::Builder::new()
+        .prefix(prefix)
+        .suffix(".pdf")
+        .tempfile_in(work_dir)?;
+    {
+        let mut writer = BufWriter::new(temp_file.as_file());
+        doc.save(&mut writer)
+            .map_err(|err| MergeError::Pdf(err.to_string()))?;
+        writer.flush()?;
+    }
+    let temp_path = temp_file.into_temp_path();
+    let path_buf = temp_path.to_path_buf();
+    Ok((path_buf, temp_path))
+}
+
+fn load_dynamic_image(path: &Path) -> Result<DynamicImage, MergeError> {
+    let ext = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    if ext == "heic" || sniff::sniff_extension(path) == Some("heic") {
+        decode_heic(path)
+    } else {
+        // 按内容识别格式，兼容 jfif 等 image 库不认识的扩展名
+        let image = image::io::Reader::open(path)?
+            .with_guessed_format()?
+            .decode()
+            .map_err(|err| MergeError::Image(err.to_string()))?;
+        // HEIC 由 libheif 按容器中的变换转正，这里只处理 EXIF 方向标记
+        Ok(orientation::apply_exif(path, image))
+    }
+}
+
+/// 用于预览与校验的小图：HEIC 走内嵌缩略图，其余格式解码后缩小到 `max_side` 以内。
+/// 不可用于合并输出，画质不保证。
+pub(crate) fn load_preview_image(path: &Path, max_side: u32) -> Result<DynamicImage, MergeError> {
+    let owned = path.to_path_buf();
+    let image = decode_guard::run(decode_guard::limit(None), move || {
+        if sniff::sniff_extension(&owned) == Some("heic") {
+            decode_heic_thumbnail(&owned)
+        } else if let Some(image) = tiff_stream::decode_fitted(&owned, max_side, max_side)? {
+            Ok(orientation::apply_exif(&owned, image))
+        } else {
+            load_dynamic_image(&owned)
+        }
+    })?;
+    let (width, height) = image.dimensions();
+    if width.max(height) > max_side {
+        Ok(image.thumbnail(max_side, max_side))
+    } else {
+        Ok(image)
+    }
+}
+
+fn flatten_transparent(image: DynamicImage) -> DynamicImage {
+    match image {
+        DynamicImage::ImageRgba8(ref rgba) => DynamicImage::ImageRgb8(flatten_rgba(rgba)),
+        DynamicImage::ImageRgba16(ref rgba) => {
+            let converted = DynamicImage::ImageRgba16(rgba.clone()).to_rgba8();
+            DynamicImage::ImageRgb8(flatten_rgba(&converted))
+        }
+        _ => image,
+    }
+}
+
+fn flatten_rgba(buffer: &RgbaImage) -> RgbImage {
+    let (width, height) = buffer.dimensions();
+    let mut rgb = ImageBuffer::new(width, height);
+    for (x, y, pixel) in buffer.enumerate_pixels() {
+        let [r, g, b, a] = pixel.0;
+        let alpha = (a as f32) / 255.0;
+        let out_r = blend_channel(r, alpha);
+        let out_g = blend_channel(g, alpha);
+        let out_b = blend_channel(b, alpha);
+        rgb.put_pixel(x, y, Rgb([out_r, out_g, out_b]));
+    }
+    rgb
+}
+
+fn blend_channel(channel: u8, alpha: f32) -> u8 {
+    let value = channel as f32 * alpha + 255.0 * (1.0 - alpha);
+    value.round().clamp(0.0, 255.0) as u8
+}
+
+fn decode_heic(path: &Path) -> Result<DynamicImage, MergeError> {
+    let handle = open_heic(path)?;
+    decode_heif_handle(&handle)
+}
+
+/// 优先解码 HEIC 内嵌的缩略图（手机拍摄的文件通常都带），比完整解码 4800 万像素原图快得多。
+fn decode_heic_thumbnail(path: &Path) -> Result<DynamicImage, MergeError> {
+    let handle = open_heic(path)?;
+    let mut ids = [0; 1];
+    if handle.number_of_thumbnails() > 0 && handle.thumbnail_ids(&mut ids) > 0 {
+        if let Ok(thumbnail) = handle.thumbnail(ids[0]) {
+            return decode_heif_handle(&thumbnail);
+        }
+    }
+    decode_heif_handle(&handle)
+}
+
+fn open_heic(path: &Path) -> Result<ImageHandle, MergeError> {
+    let path_str = path
+        .to_str()
+        .ok_or_else(|| MergeError::Image("HEIC 路径包含非 UTF-8 字符".into()))?;
+    let ctx = HeifContext::read_from_file(path_str).map_err(|err| MergeError::Image(err.to_string()))?;
+    ctx.primary_image_handle()
+        .map_err(|err| MergeError::Image(err.to_string()))
+}
+
+fn decode_heif_handle(handle: &ImageHandle) -> Result<DynamicImage, MergeError> {
+    let image = handle
+        .decode(ColorSpace::Rgb(RgbChroma::Rgb), None)
+        .map_err(|err| MergeError::Image(err.to_string()))?;
+
+    let plane = image
+        .planes()
+        .interleaved
+        .ok_or_else(|| MergeError::Image("HEIC 缺少 interleaved 通道".into()))?;
+
+    let width = plane.width as usize;
+    let height = plane.height as usize;
+    let stride = plane.stride;
+    let channels = ((plane.bits_per_pixel.max(24) / 8) as usize).min(4).max(3);
+    let row_bytes = width * channels;
+
+    if stride < row_bytes {
+        return Err(MergeError::Image("HEIC stride 小于行宽".into()));
+    }
+
+    let mut buffer = vec![0u8; row_bytes * height];
+    for row in 0..height {
+        let start = row * stride;
+        let end = start + row_bytes;
+        let dst_range = row * row_bytes..(row + 1) * row_bytes;
+        buffer[dst_range].copy_from_slice(&plane.data[start..end]);
+    }
+
+    if channels >= 4 {
+        let rgba: RgbaImage = ImageBuffer::from_raw(width as u32, height as u32, buffer)
+            .ok_or_else(|| MergeError::Image("无法生成 RGBA 图像".into()))?;
+        Ok(DynamicImage::ImageRgba8(rgba))
+    } else {
+        let rgb: RgbImage = ImageBuffer::from_raw(width as u32, height as u32, buffer)
+            .ok_or_else(|| MergeError::Image("无法生成 RGB 图像".into()))?;
+        Ok(DynamicImage::ImageRgb8(rgb))
+    }
+}
+
+/// 按 `pdf_inputs` 下标附加到各份输入上的处理。
+struct InputExtras<'a> {
+    page_tags: &'a HashMap<usize, PageTag>,
+    page_selections: &'a HashMap<usize, PageRanges>,
+    /// 每段输入的来源，用于生成书签
+    page_map: &'a PageMapBuilder,
+}
+
+/// 合并并写出 PDF，返回其中写盘所用的时间，以及每份输入实际并入的页数（已去掉未选中的页面）。
+fn merge_pdf_files(
+    window: &Window,
+    files: &[PathBuf],
+    output: &Path,
+    downsample: Option<&DownsampleOptions>,
+    extras: &InputExtras,
+    workers: usize,
+) -> Result<(Duration, Vec<usize>), MergeError> {
+    if files.is_empty() {
+        return Err(MergeError::NoFiles);
+    }
+
+    let mut documents_pages: Vec<(ObjectId, Object)> = Vec::new();
+    let mut documents_objects: BTreeMap<ObjectId, Object> = BTreeMap::new();
+    let sizes: Vec<u64> = files
+        .iter()
+        .map(|path| fs::metadata(path).map(|meta| meta.len()).unwrap_or(0))
+        .collect();
+    let total_bytes: u64 = sizes.iter().sum();
+    let mut qr_protected = 0usize;
+    let mut pages_per_input = Vec::with_capacity(files.len());
+    emit_progress(window, 0, files.len(), (0, total_bytes), ProgressPhase::Merge);
+
+    // 解析与图片处理是合并中最耗时的部分，各文件互不依赖，并行处理后再按顺序拼装
+    let processed = AtomicUsize::new(0);
+    let done_bytes = AtomicU64::new(0);
+    let loaded = parallel::map_ordered(files, workers, |index, path| {
+        cancel::check()?;
+        let mut doc = mmap_pdf::load(path)?;
+        if doc.is_encrypted() {
+            let _ = doc.decrypt(b"");
+        }
+        if let Some(ranges) = extras.page_selections.get(&index) {
+            let pages = doc.get_pages();
+            let keep = ranges.select_some(pages.len() as u32)?;
+            let drop: Vec<u32> = pages.into_keys().filter(|page| !keep.contains(page)).collect();
+            if !drop.is_empty() {
+                doc.delete_pages(&drop);
+                // 删掉未选中页面独占的图片、字体，后续的压缩也不必再处理它们
+                doc.prune_objects();
+            }
+        }
+        // 先转黑白：已转为 G4 的图片不会再被降采样成 JPEG
+        // 需要解压每张 Flate 图片，只在要求压缩时进行
+        if let Some(opts) = downsample {
+            compress::encode_bilevel_images(&mut doc, opts.binarize);
+        }
+        let qr = downsample.map_or(0, |opts| compress::downsample_images(&mut doc, opts).qr_protected);
+        emit_progress(
+            window,
+            processed.fetch_add(1, Ordering::Relaxed) + 1,
+            files.len(),
+            (
+                done_bytes.fetch_add(sizes[index], Ordering::Relaxed) + sizes[index],
+                total_bytes,
+            ),
+            ProgressPhase::Merge,
+        );
+        Ok::<_, MergeError>((doc, qr))
+    });
+    let mut documents = Vec::with_capacity(loaded.len());
+    let mut offsets = Vec::with_capacity(loaded.len());
+    let mut max_id = 1;
+    for result in loaded {
+        let (doc, qr) = result?;
+        qr_protected += qr;
+        // 重新编号后对象号连续，起始号只取决于前面各文件的对象数
+        offsets.push(max_id);
+        max_id += doc.objects.len() as u32;
+        documents.push(doc);
+    }
+    let documents = parallel::map_ordered(documents, workers, |index, mut doc: Document| {
+        doc.renumber_objects_with(offsets[index]);
+        doc
+    });
+
+    for doc in documents {
+        let pages_before = documents_pages.len();
+        // 直接移走对象而不是复制，大文件不会在内存中同时留两份
+        for (object_id, object) in doc.objects {
+            match object.type_name().unwrap_or("") {
+                "Page" => {
+                    documents_pages.push((object_id, object));
+                }
+                _ => {
+                    documents_objects.insert(object_id, object);
+                }
+            }
+        }
+        pages_per_input.push(documents_pages.len() - pages_before);
+    }
+
+    if documents_pages.is_empty() {
+        return Err(MergeError::NoFiles);
+    }
+    if qr_protected > 0 {
+        emit_warning(
+            window,
+            "qr",
+            format!("{qr_protected} 张图片含二维码，已保留足够分辨率以保证可扫描"),
+        );
+    }
+
+    let mut document = Document::with_version("1.5");
+    let mut catalog_object: Option<(ObjectId, Object)> = None;
+    let mut pages_object: Option<(ObjectId, Object)> = None;
+
+    for (object_id, object) in documents_objects.into_iter() {
+        match object.type_name().unwrap_or("") {
+            "Catalog" => {
+                if catalog_object.is_none() {
+                    catalog_object = Some((object_id, object));
+                }
+            }
+            "Pages" => {
+                if let Ok(dictionary) = object.as_dict() {
+                    let mut dictionary = dictionary.clone();
+                    if let Some((_, ref existing)) = pages_object {
+                        if let Ok(old_dictionary) = existing.as_dict() {
+                            dictionary.extend(old_dictionary);
+                        }
+                    }
+                    pages_object = Some((object_id, Object::Dictionary(dictionary)));
+                }
+            }
+            "Outlines" | "Outline" => {}
+            _ => {
+                document.objects.insert(object_id, object);
+            }
+        }
+    }
+
+    let (page_id, page_object) =
+        pages_object.ok_or_else(|| MergeError::Pdf("Pages root not found".into()))?;
+    let (catalog_id, catalog_obj) =
+        catalog_object.ok_or_else(|| MergeError::Pdf("Catalog root not found".into()))?;
+
+    for (object_id, object) in documents_pages.iter() {
+        if let Ok(dictionary) = object.as_dict() {
+            let mut dictionary = dictionary.clone();
+            dictionary.set("Parent", page_id);
+            document
+                .objects
+                .insert(*object_id, Object::Dictionary(dictionary));
+        }
+    }
+
+    if let Ok(dictionary) = page_object.as_dict() {
+        let mut dictionary = dictionary.clone();
+        dictionary.set("Count", documents_pages.len() as u32);
+        dictionary.set(
+            "Kids",
+            documents_pages
+                .iter()
+                .map(|(object_id, _)| Object::Reference(*object_id))
+                .collect::<Vec<_>>(),
+        );
+        document.objects.insert(page_id, Object::Dictionary(dictionary));
+    }
+
+    if let Ok(dictionary) = catalog_obj.as_dict() {
+        let mut dictionary = dictionary.clone();
+        dictionary.set("Pages", page_id);
+        dictionary.remove(b"Outlines");
+        document
+            .objects
+            .insert(catalog_id, Object::Dictionary(dictionary));
+    }
+
+    let mut tagged_pages = Vec::new();
+    let mut offset = 0;
+    for (input, count) in pages_per_input.iter().enumerate() {
+        if let Some(tag) = extras.page_tags.get(&input) {
+            tagged_pages.extend(
+                documents_pages[offset..offset + count]
+                    .iter()
+                    .map(|(object_id, _)| (*object_id, tag)),
+            );
+        }
+        offset += count;
+    }
+    if !tagged_pages.is_empty() {
+        accessibility::tag_pages(&mut document, catalog_id, &tagged_pages, "zh-CN")?;
+    }
+    let ranges = extras.page_map.build(&pages_per_input);
+    // 每个来源文件一项书签，指向它的第一页；没有页面的段不生成书签
+    let bookmarks: Vec<(ObjectId, String)> = ranges
+        .into_iter()
+        .filter(|range| range.page_count() > 0)
+        .filter_map(|range| {
+            let (page_id, _) = documents_pages.get(range.start_page - 1)?;
+            Some((*page_id, range.file_name))
+        })
+        .collect();
+    outline::add_outline(&mut document, catalog_id, &bookmarks)?;
+
+    document.trailer.set("Root", catalog_id);
+    dedupe::deduplicate_resources(&mut document);
+    document.max_id = document.objects.len() as u32;
+    document.renumber_objects();
+
+    cancel::check()?;
+    let write_started = Instant::now();
+    let mut writer = BufWriter::new(fs::File::create(output)?);
+    // 大文件写盘可能要十几秒，按字节上报进度，界面不会停在 99% 不动
+    pdf_writer::save_compact(&document, &mut writer, |written, estimated| {
+        emit_progress(
+            window,
+            files.len(),
+            files.len(),
+            (written, estimated),
+            ProgressPhase::Write,
+        );
+    })?;
+    let write_time = write_started.elapsed();
+    emit_progress(
+        window,
+        files.len(),
+        files.len(),
+        (total_bytes, total_bytes),
+        ProgressPhase::Merge,
+    );
+    Ok((write_time, pages_per_input))
+}
+
+/// 启动桌面应用：托管状态、托盘、插件与全部命令。
+pub fn run() {
+    let launch_folder = shell_menu::folder_from_args(std::env::args().skip(1));
+    let serve_stdio = stdio_rpc::requested(std::env::args().skip(1));
+    let pending_link = deep_link::link_from_args(std::env::args().skip(1))
+        .map(|url| deep_link::parse(&url).map_err(|err| err.to_string()));
+    tauri::Builder::default()
+        .manage(LaunchFolder(std::sync::Mutex::new(launch_folder)))
+        .manage(PendingLink(std::sync::Mutex::new(pending_link)))
+        .manage(TrayState::default())
+        .manage(HttpApiState::default())
+        .manage(ConfigState::default())
+        .plugin(plugin::init())
+        .system_tray(tray::build())
+        .on_system_tray_event(tray::handle_event)
+        .on_window_event(|event| {
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event.event() {
+                if event.window().state::<TrayState>().hide_on_close() {
+                    api.prevent_close();
+                    let _ = event.window().hide();
+                }
+            }
+        })
+        .setup(move |app| {
+            // 主窗口默认隐藏，stdio 自动化模式下始终不显示
+            if serve_stdio {
+                stdio_rpc::serve(app.handle());
+            } else if let Some(window) = app.get_window("main") {
+                window.show()?;
+            }
+            if let Some(dir) = crash_dir(&app.handle()) {
+                crash::install(dir);
+            }
+            config::watch(app.handle());
+            // 每次启动都在当前用户下登记 URL 协议，程序移动位置后也能指向新路径；
+            // 便携模式不写注册表
+            #[cfg(windows)]
+            if portable::root().is_none() {
+                std::thread::spawn(|| {
+                    let _ = deep_link::register_scheme();
+                });
+            }
+            // 启动时在后台清理上次崩溃残留的中间文件，自定义的工作目录也一并清理
+            let state_dir = portable::app_dir(&app.handle(), AppDir::Cache);
+            std::thread::spawn(move || cleanup::sweep_on_startup(state_dir.as_deref()));
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            scan_folder_cmd,
+            scan_folder_stream_cmd,
+            scan_folder_page_cmd,
+            merge_invoices_cmd,
+            preview_merge_cmd,
+            commit_merge_cmd,
+            cancel_merge_cmd,
+            get_thumbnail_cmd,
+            provide_pdf_password_cmd,
+            validate_files_cmd,
+            validate_dropped_paths_cmd,
+            plan_merge_cmd,
+            export_order_cmd,
+            import_order_cmd,
+            launch_folder_cmd,
+            launch_link_cmd,
+            set_tray_mode_cmd,
+            check_update_cmd,
+            install_update_cmd,
+            get_config_cmd,
+            get_policy_cmd,
+            crash_reports_cmd,
+            start_http_api_cmd,
+            stop_http_api_cmd,
+            delete_crash_report_cmd,
+            context_menu_status_cmd,
+            set_context_menu_cmd,
+            clean_temp_cmd
+        ])
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_output_name_cleans_user_input() {
+        let cases: &[(&str, Option<&str>)] = &[
+            ("3月报销", Some("3月报销")),
+            ("  报销单.pdf  ", Some("报销单.pdf")),
+            ("报销:2024?<>|*\"", Some("报销_2024______")),
+            ("a\tb\u{7}", Some("a_b_")),
+            ("name. . ", Some("name")),
+            ("", None),
+            ("   ", None),
+            ("...", None),
+            (". .", None),
+            // Windows 保留设备名，含扩展名、大小写不同时同样保留
+            ("CON", Some("_CON")),
+            ("con.pdf", Some("_con.pdf")),
+            ("Aux.tar.gz", Some("_Aux.tar.gz")),
+            ("nul ", Some("_nul")),
+            ("PRN.", Some("_PRN")),
+            ("com1", Some("_com1")),
+            ("LPT9.pdf", Some("_LPT9.pdf")),
+            // 只是以保留名开头的普通名称
+            ("COM10", Some("COM10")),
+            ("COMA", Some("COMA")),
+            ("console", Some("console")),
+            ("NULL.pdf", Some("NULL.pdf")),
+            ("my con.pdf", Some("my con.pdf")),
+        ];
+        for &(input, expected) in cases {
+            let actual = sanitize_output_name(input).unwrap_or_else(|err| panic!("{input:?}: {err}"));
+            assert_eq!(actual.as_deref(), expected, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn invoice_number_from_name_takes_longest_digit_run() {
+        let cases: &[(&str, Option<u128>)] = &[
+            ("dzfp_24312000000123456789_餐饮.pdf", Some(24312000000123456789)),
+            ("12345678.pdf", Some(12345678)),
+            ("2024-03-15 发票 04400123.pdf", Some(4400123)),
+            ("20240315_12345678901.jpg", Some(12345678901)),
+            // 等长时取后一段
+            ("11111111_22222222.pdf", Some(22222222)),
+            ("1234567.pdf", None),
+            ("2024-03-15.pdf", None),
+            ("无号码.pdf", None),
+            ("", None),
+            // 全角数字不算
+            ("１２３４５６７８９.pdf", None),
+            // 超出 u128 的数字串无法比较大小
+            ("1111111111111111111111111111111111111111.pdf", None),
+        ];
+        for &(name, expected) in cases {
+            assert_eq!(invoice_number_from_name(name), expected, "{name:?}");
+        }
+    }
+
+    #[test]
+    fn sanitize_output_name_rejects_paths() {
+        for input in ["a/b", "a\\b", "/abs", "..\\up", "C:\\out.pdf"] {
+            assert!(
+                matches!(sanitize_output_name(input), Err(MergeError::InvalidOutputName)),
+                "{input:?} 应被拒绝"
+            );
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use fs2::FileExt;
+use lopdf::Document;
+use memmap2::Mmap;
+use std::{fs::File, path::Path};
+
+use crate::MergeError;
+
+/// 超过该大小的 PDF 改为内存映射读取
+const MMAP_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+/// 加载 PDF。`Document::load` 会先把整个文件读进堆内存再解析，
+/// 对上 GB 的扫描归档而言等于同时持有原始字节和解析结果两份数据；
+/// 大文件改为内存映射，原始字节由系统页缓存按需换入换出。
+pub fn load(path: &Path) -> Result<Document, MergeError> {
+    let file = File::open(path)?;
+    if file.metadata()?.len() < MMAP_THRESHOLD {
+        return Document::load_from(file).map_err(|err| MergeError::Pdf(err.to_string()));
+    }
+    // 映射期间文件被截断时，Unix 上访问映射会触发 SIGBUS 直接结束进程，而不是返回读取错误。
+    // 解析期间持有共享锁：Windows 上锁是强制的，其他程序无法写入；Unix 上的 flock 只是建议锁，
+    // 只能挡住同样加锁的程序。拿不到锁（有人正在写）时不冒险映射，退回整份读入内存。
+    if file.try_lock_shared().is_err() {
+        return Document::load_from(file).map_err(|err| MergeError::Pdf(err.to_string()));
+    }
+    // SAFETY: 映射只读且只在本函数内使用，解析结果不借用映射；截断的风险见上
+    let map = unsafe { Mmap::map(&file)? };
+    let doc = Document::load_mem(&map).map_err(|err| MergeError::Pdf(err.to_string()));
+    drop(map);
+    let _ = file.unlock();
+    doc
+}
@@ -0,0 +1,24 @@
+use tauri::{api::notification::Notification, Manager, Window};
+
+use crate::MergeResult;
+
+/// 窗口最小化或隐藏到托盘时，用系统通知告知合并结果。
+/// Tauri 1 的通知不提供点击回调，因此提示用户通过托盘菜单打开输出。
+pub fn merge_complete(window: &Window, result: &MergeResult) {
+    if !result.success || result.needs_confirmation.is_some() {
+        return;
+    }
+    let in_background = window.is_minimized().unwrap_or(false) || !window.is_visible().unwrap_or(true);
+    if !in_background {
+        return;
+    }
+
+    let identifier = window.app_handle().config().tauri.bundle.identifier.clone();
+    let _ = Notification::new(identifier)
+        .title("合并完成")
+        .body(format!(
+            "合并完成：{} 个文件，{} 页，点击托盘图标选择“打开上次输出”",
+            result.merged_files, result.page_count
+        ))
+        .show();
+}
@@ -0,0 +1,232 @@
+use image::{DynamicImage, GenericImageView};
+use printpdf::{Color, Greyscale, Line, LineDashPattern, Mm, PdfDocument, Point};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tempfile::TempPath;
+
+use crate::{
+    page_fit::{PageLayout, PageOrientation, PageSize},
+    qr_guard, save_temp_pdf, MergeError, IMAGE_RENDER_DPI,
+};
+
+const MARGIN_MM: f64 = 8.0;
+
+/// 把多张图片排在同一页纸上，出租车票、餐饮小票不再各占一整页。
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct NUpLayout {
+    /// 每页张数：2、4 或 8
+    pub per_page: usize,
+    /// 相邻图片之间的间距（毫米）
+    pub gutter_mm: f64,
+    /// 在间距中央画虚线，打印后沿线裁开
+    pub cut_lines: bool,
+}
+
+impl Default for NUpLayout {
+    fn default() -> Self {
+        Self {
+            per_page: 2,
+            gutter_mm: 6.0,
+            cut_lines: true,
+        }
+    }
+}
+
+impl NUpLayout {
+    pub fn validate(&self) -> Result<(), MergeError> {
+        if !matches!(self.per_page, 2 | 4 | 8) {
+            return Err(MergeError::InvalidNUp(format!(
+                "每页张数只能是 2、4 或 8，当前为 {}",
+                self.per_page
+            )));
+        }
+        if !(0.0..=50.0).contains(&self.gutter_mm) {
+            return Err(MergeError::InvalidNUp(format!(
+                "间距应在 0–50 mm 之间，当前为 {}",
+                self.gutter_mm
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 等待拼版的图片及其来源文件，攒满一页或遇到其他类型的文件时输出。
+#[derive(Default)]
+pub struct Batch {
+    images: Vec<DynamicImage>,
+    names: Vec<String>,
+    paths: Vec<String>,
+}
+
+impl Batch {
+    pub fn push(&mut self, image: DynamicImage, file_name: &str, path: &str) {
+        self.images.push(image);
+        self.names.push(file_name.to_string());
+        self.paths.push(path.to_string());
+    }
+
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+
+    /// 取出全部图片，以及页码映射中使用的名称（各文件名以顿号连接）和首个文件的路径
+    pub fn take(&mut self) -> (Vec<DynamicImage>, String, String) {
+        let name = self.names.join("、");
+        let path = self.paths.first().cloned().unwrap_or_default();
+        self.names.clear();
+        self.paths.clear();
+        (std::mem::take(&mut self.images), name, path)
+    }
+}
+
+/// 纸张（宽, 高），单位毫米。拼版页没有单一的图片方向，方向为自动时按纵向处理。
+fn sheet_mm(size: PageSize, layout: PageLayout) -> (f64, f64) {
+    let (short, long) = size.dimensions_mm();
+    match layout.orientation {
+        PageOrientation::Landscape => (long, short),
+        PageOrientation::Portrait | PageOrientation::Auto => (short, long),
+    }
+}
+
+/// 排队等待拼版的图片先缩到整张纸在渲染 DPI 下的大小，最多同时保留 8 张也不占太多内存。
+/// 含二维码的图片至少保留能被扫描的分辨率。
+pub fn shrink_for_sheet(image: DynamicImage, size: PageSize) -> DynamicImage {
+    let (_, long) = size.dimensions_mm();
+    let max_side = long / 25.4 * IMAGE_RENDER_DPI;
+    let (width, height) = image.dimensions();
+    let mut scale = max_side / width.max(height).max(1) as f64;
+    if scale >= 1.0 {
+        return image;
+    }
+    if let Some(min_scale) = qr_guard::min_scale_for_qr(&image) {
+        scale = scale.max(min_scale);
+    }
+    if scale >= 1.0 {
+        return image;
+    }
+    let target = |side: u32| ((side as f64 * scale).round() as u32).max(1);
+    image.resize(
+        target(width),
+        target(height),
+        image::imageops::FilterType::Lanczos3,
+    )
+}
+
+/// 在给定的行列数下，每张图片等比放进格子后的总面积，用来挑选最合适的排法。
+fn covered_area(images: &[DynamicImage], cell_w: f64, cell_h: f64, auto_rotate: bool) -> f64 {
+    images
+        .iter()
+        .map(|image| {
+            let (w, h, _) = placed_dimensions(image, cell_w, cell_h, auto_rotate);
+            let scale = (cell_w / w).min(cell_h / h);
+            w * h * scale * scale
+        })
+        .sum()
+}
+
+/// 图片在格子中的宽高（像素）以及是否需要转 90°，转动时宽高已交换
+fn placed_dimensions(image: &DynamicImage, cell_w: f64, cell_h: f64, auto_rotate: bool) -> (f64, f64, bool) {
+    let (w, h) = image.dimensions();
+    let (w, h) = (w.max(1) as f64, h.max(1) as f64);
+    if auto_rotate && (w > h) != (cell_w > cell_h) {
+        (h, w, true)
+    } else {
+        (w, h, false)
+    }
+}
+
+/// 把最多 `per_page` 张图片排到一页上：在各种行列组合中选图片总面积最大的一种，
+/// 横版发票通常上下排列，窄长的小票则左右并排。
+pub fn compose(
+    images: Vec<DynamicImage>,
+    nup: &NUpLayout,
+    size: PageSize,
+    layout: PageLayout,
+    work_dir: &Path,
+) -> Result<(PathBuf, TempPath), MergeError> {
+    let (page_w, page_h) = sheet_mm(size, layout);
+    let gutter = nup.gutter_mm;
+    let cell = |cols: usize, rows: usize| {
+        (
+            (page_w - 2.0 * MARGIN_MM - gutter * (cols - 1) as f64) / cols as f64,
+            (page_h - 2.0 * MARGIN_MM - gutter * (rows - 1) as f64) / rows as f64,
+        )
+    };
+    let (cols, rows) = (1..=nup.per_page)
+        .filter(|cols| nup.per_page % cols == 0)
+        .map(|cols| (cols, nup.per_page / cols))
+        .filter(|&(cols, rows)| {
+            let (cell_w, cell_h) = cell(cols, rows);
+            cell_w > 0.0 && cell_h > 0.0
+        })
+        .max_by(|&a, &b| {
+            let area = |(cols, rows)| {
+                let (cell_w, cell_h) = cell(cols, rows);
+                covered_area(&images, cell_w, cell_h, layout.auto_rotate)
+            };
+            area(a).total_cmp(&area(b))
+        })
+        .ok_or_else(|| MergeError::InvalidNUp("间距过大，纸张上放不下图片".into()))?;
+    let (cell_w, cell_h) = cell(cols, rows);
+
+    let (doc, page, layer) = PdfDocument::new("Invoice Images", Mm(page_w), Mm(page_h), "Layer");
+    let layer = doc.get_page(page).get_layer(layer);
+    for (index, image) in images.into_iter().enumerate() {
+        let (col, row) = (index % cols, index / cols);
+        let (w, h, rotated) = placed_dimensions(&image, cell_w, cell_h, layout.auto_rotate);
+        let image = if rotated { image.rotate270() } else { image };
+        let scale = (cell_w / w).min(cell_h / h);
+        let (display_w, display_h) = (w * scale, h * scale);
+        // 按行从上往下排，格子内居中
+        let cell_x = MARGIN_MM + col as f64 * (cell_w + gutter);
+        let cell_y = page_h - MARGIN_MM - (row + 1) as f64 * cell_h - row as f64 * gutter;
+        let base_w_mm = w / IMAGE_RENDER_DPI * 25.4;
+        let base_h_mm = h / IMAGE_RENDER_DPI * 25.4;
+        printpdf::Image::from_dynamic_image(&image).add_to_layer(
+            layer.clone(),
+            printpdf::ImageTransform {
+                translate_x: Some(Mm(cell_x + (cell_w - display_w) / 2.0)),
+                translate_y: Some(Mm(cell_y + (cell_h - display_h) / 2.0)),
+                rotate: None,
+                scale_x: Some(display_w / base_w_mm),
+                scale_y: Some(display_h / base_h_mm),
+                dpi: Some(IMAGE_RENDER_DPI),
+            },
+        );
+    }
+
+    if nup.cut_lines && gutter > 0.0 {
+        layer.set_outline_color(Color::Greyscale(Greyscale::new(0.6, None)));
+        layer.set_outline_thickness(0.5);
+        layer.set_line_dash_pattern(LineDashPattern {
+            dash_1: Some(3),
+            gap_1: Some(3),
+            ..Default::default()
+        });
+        let segment = |from: (f64, f64), to: (f64, f64)| Line {
+            points: vec![
+                (Point::new(Mm(from.0), Mm(from.1)), false),
+                (Point::new(Mm(to.0), Mm(to.1)), false),
+            ],
+            is_closed: false,
+            has_fill: false,
+            has_stroke: true,
+            is_clipping_path: false,
+        };
+        for col in 1..cols {
+            let x = MARGIN_MM + col as f64 * (cell_w + gutter) - gutter / 2.0;
+            layer.add_shape(segment((x, 0.0), (x, page_h)));
+        }
+        for row in 1..rows {
+            let y = page_h - MARGIN_MM - row as f64 * (cell_h + gutter) + gutter / 2.0;
+            layer.add_shape(segment((0.0, y), (page_w, y)));
+        }
+    }
+
+    save_temp_pdf(doc, "mc-nup-", work_dir)
+}
@@ -0,0 +1,248 @@
+use std::{
+    env, fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+use tempfile::TempPath;
+
+use crate::MergeError;
+
+pub const OFFICE_EXTENSIONS: &[&str] = &["doc", "docx", "xls", "xlsx"];
+
+/// 首次启动要初始化独立的用户配置目录，比平常慢，留足余量
+const CONVERT_TIMEOUT: Duration = Duration::from_secs(180);
+
+const KNOWN_SOFFICE_LOCATIONS: &[&str] = &[
+    r"C:\Program Files\LibreOffice\program\soffice.exe",
+    r"C:\Program Files (x86)\LibreOffice\program\soffice.exe",
+    "/Applications/LibreOffice.app/Contents/MacOS/soffice",
+    "/usr/bin/soffice",
+    "/usr/local/bin/soffice",
+    "/opt/libreoffice/program/soffice",
+];
+
+/// 在 PATH 与常见安装目录中查找 LibreOffice 的 soffice 可执行文件。
+pub fn find_soffice() -> Option<PathBuf> {
+    let names: &[&str] = if cfg!(windows) {
+        &["soffice.exe", "soffice.com"]
+    } else {
+        &["soffice", "libreoffice"]
+    };
+    find_executable(names, KNOWN_SOFFICE_LOCATIONS)
+}
+
+/// 依次在 PATH 和给定的候选安装路径中查找可执行文件。
+pub(crate) fn find_executable(names: &[&str], known_locations: &[&str]) -> Option<PathBuf> {
+    if let Some(paths) = env::var_os("PATH") {
+        for dir in env::split_paths(&paths) {
+            for name in names {
+                let candidate = dir.join(name);
+                if candidate.is_file() {
+                    return Some(candidate);
+                }
+            }
+        }
+    }
+
+    known_locations
+        .iter()
+        .map(PathBuf::from)
+        .find(|candidate| candidate.is_file())
+}
+
+/// 调用 `soffice --headless --convert-to pdf` 将 Word/Excel 文档转为临时 PDF。
+pub fn convert_office_to_pdf(path: &Path, work_dir: &Path) -> Result<(PathBuf, TempPath), MergeError> {
+    let soffice =
+        find_soffice().ok_or_else(|| MergeError::Convert("未检测到 LibreOffice (soffice)".into()))?;
+    let out_dir = tempfile::Builder::new()
+        .prefix("mc-office-")
+        .tempdir_in(work_dir)?;
+    // 独立的用户配置目录，避免与用户正在运行的 LibreOffice 实例互相锁定
+    let profile_dir = out_dir.path().join("profile");
+
+    let mut command = Command::new(&soffice);
+    command
+        .arg("--headless")
+        .arg("--norestore")
+        .arg(format!("-env:UserInstallation={}", file_url(&profile_dir)))
+        .args(["--convert-to", "pdf", "--outdir"])
+        .arg(out_dir.path())
+        .arg(path);
+    hide_console_window(&mut command);
+
+    let output = run_with_timeout(&mut command, CONVERT_TIMEOUT)?;
+    match output.status {
+        Some(status) if status.success() => {}
+        Some(_) => return Err(MergeError::Convert(output.stderr.trim().to_string())),
+        None => {
+            return Err(MergeError::Convert(format!(
+                "LibreOffice 超过 {} 秒未完成转换，已终止",
+                CONVERT_TIMEOUT.as_secs()
+            )))
+        }
+    }
+
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let converted = out_dir.path().join(format!("{stem}.pdf"));
+    if !converted.is_file() {
+        return Err(MergeError::Convert("LibreOffice 未生成 PDF".into()));
+    }
+
+    let temp_file = tempfile::Builder::new()
+        .prefix("mc-office-")
+        .suffix(".pdf")
+        .tempfile_in(work_dir)?;
+    fs::copy(&converted, temp_file.path())?;
+    let temp_path = temp_file.into_temp_path();
+    let path_buf = temp_path.to_path_buf();
+    Ok((path_buf, temp_path))
+}
+
+/// 子进程的输出；超时被终止时 `status` 为 `None`
+pub(crate) struct ProcessOutput {
+    pub status: Option<ExitStatus>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// 启动命令并最多等待 `timeout`，超时则结束进程，避免损坏的文件或弹出的对话框让合并一直挂起。
+pub(crate) fn run_with_timeout(command: &mut Command, timeout: Duration) -> io::Result<ProcessOutput> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // 在独立线程里读取输出，避免管道写满后子进程阻塞
+    let stdout = child.stdout.take().map(capture);
+    let stderr = child.stderr.take().map(capture);
+
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait()? {
+            Some(status) => break Some(status),
+            None if started.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                break None;
+            }
+            None => thread::sleep(Duration::from_millis(100)),
+        }
+    };
+
+    let collect = |handle: Option<thread::JoinHandle<String>>| {
+        handle.and_then(|handle| handle.join().ok()).unwrap_or_default()
+    };
+    Ok(ProcessOutput {
+        status,
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+    })
+}
+
+fn capture<R: Read + Send + 'static>(mut reader: R) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = reader.read_to_end(&mut bytes);
+        String::from_utf8_lossy(&bytes).into_owned()
+    })
+}
+
+/// 去掉 `canonicalize` 在 Windows 上加的 `\\?\` 前缀，外部程序大多不认这种写法。
+pub(crate) fn display_path(path: &Path) -> String {
+    let raw = path.to_string_lossy();
+    match raw.strip_prefix(r"\\?\") {
+        Some(rest) => match rest.strip_prefix(r"UNC\") {
+            Some(unc) => format!(r"\\{unc}"),
+            None => rest.to_string(),
+        },
+        None => raw.into_owned(),
+    }
+}
+
+/// 本地路径转 file URL。逐段做百分号编码，文件名中的 `#`、`%`、`?`、空格不会被当成 URL 语法。
+pub(crate) fn file_url(path: &Path) -> String {
+    let mut normalized = display_path(path);
+    if cfg!(windows) {
+        normalized = normalized.replace('\\', "/");
+    }
+    let encoded: Vec<String> = normalized.split('/').map(percent_encode).collect();
+    let encoded = encoded.join("/");
+    match encoded.strip_prefix("//") {
+        // UNC 路径：主机名放在 authority 部分
+        Some(unc) => format!("file://{unc}"),
+        None => format!("file:///{}", encoded.trim_start_matches('/')),
+    }
+}
+
+fn percent_encode(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~:".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+#[cfg(windows)]
+pub(crate) fn hide_console_window(command: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    command.creation_flags(CREATE_NO_WINDOW);
+}
+
+#[cfg(not(windows))]
+pub(crate) fn hide_console_window(_command: &mut Command) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_verbatim_prefix() {
+        let cases: &[(&str, &str)] = &[
+            (r"\\?\C:\发票\a.html", r"C:\发票\a.html"),
+            (r"\\?\UNC\server\share\a.html", r"\\server\share\a.html"),
+            (r"C:\发票\a.html", r"C:\发票\a.html"),
+            ("/home/user/a.html", "/home/user/a.html"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(display_path(Path::new(input)), *expected, "输入 {input}");
+        }
+    }
+
+    #[test]
+    fn file_url_encodes_each_segment() {
+        let cases: &[(&str, &str)] = &[
+            ("/home/user/a.html", "file:///home/user/a.html"),
+            (
+                "/home/user/发票 #1/100%?.html",
+                "file:///home/user/%E5%8F%91%E7%A5%A8%20%231/100%25%3F.html",
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(file_url(Path::new(input)), *expected, "输入 {input}");
+        }
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn file_url_of_windows_paths() {
+        let cases: &[(&str, &str)] = &[
+            (r"\\?\C:\Users\a b.html", "file:///C:/Users/a%20b.html"),
+            (r"C:\Users\a.html", "file:///C:/Users/a.html"),
+            (r"\\?\UNC\server\share\a.html", "file://server/share/a.html"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(file_url(Path::new(input)), *expected, "输入 {input}");
+        }
+    }
+}
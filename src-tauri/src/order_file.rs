@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+use crate::{InvoiceFile, MergeError};
+
+/// 导出的顺序文件，放在发票文件夹中；下月同名文件放进新文件夹后可直接沿用
+pub const SIDECAR_FILE: &str = "合并顺序.txt";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OrderImport {
+    /// 按导入顺序排列的文件，未列出的文件保持原有相对顺序排在最后
+    pub files: Vec<InvoiceFile>,
+    /// 列表中有、文件夹中找不到的名称
+    pub unmatched: Vec<String>,
+    /// 文件夹中有、列表中未提到的文件数
+    pub unlisted: usize,
+}
+
+/// 把当前顺序写成每行一个相对路径的纯文本，写入文件夹中的 `SIDECAR_FILE` 并返回其路径。
+pub fn export(folder: &Path, files: &[InvoiceFile]) -> Result<String, MergeError> {
+    let mut text = String::from("# 自定义合并顺序，每行一个文件，以 # 开头的行会被忽略\n");
+    for file in files {
+        text.push_str(&relative_name(folder, file));
+        text.push('\n');
+    }
+    let path = folder.join(SIDECAR_FILE);
+    fs::write(&path, text)?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// 读取文件夹中导出的顺序文件
+pub fn read_sidecar(folder: &Path) -> Result<String, MergeError> {
+    Ok(fs::read_to_string(folder.join(SIDECAR_FILE))?)
+}
+
+/// 按粘贴或读取的名称列表重排文件。名称可以是相对路径或仅文件名，
+/// 先按相对路径精确匹配，再按文件名不区分大小写匹配；每个文件只会被用一次。
+pub fn apply(folder: &Path, files: Vec<InvoiceFile>, text: &str) -> OrderImport {
+    let mut remaining: Vec<Option<InvoiceFile>> = files.into_iter().map(Some).collect();
+    let mut ordered = Vec::with_capacity(remaining.len());
+    let mut unmatched = Vec::new();
+    for name in parse_names(text) {
+        let normalized = name.replace('\\', "/");
+        let position = remaining
+            .iter()
+            .position(|slot| {
+                slot.as_ref()
+                    .is_some_and(|file| relative_name(folder, file) == normalized)
+            })
+            .or_else(|| {
+                let base = normalized
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or(&normalized)
+                    .to_lowercase();
+                remaining.iter().position(|slot| {
+                    slot.as_ref()
+                        .is_some_and(|file| file.file_name.to_lowercase() == base)
+                })
+            });
+        match position.and_then(|index| remaining[index].take()) {
+            Some(file) => ordered.push(file),
+            None => unmatched.push(name),
+        }
+    }
+    let rest: Vec<InvoiceFile> = remaining.into_iter().flatten().collect();
+    let unlisted = rest.len();
+    ordered.extend(rest);
+    OrderImport {
+        files: ordered,
+        unmatched,
+        unlisted,
+    }
+}
+
+/// 每行一个名称；忽略空行和 `#` 注释，去掉从资源管理器“复制路径”带来的引号
+fn parse_names(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.trim().trim_matches('"').trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// 相对文件夹的路径，统一用 `/` 分隔，便于在不同电脑之间共享
+fn relative_name(folder: &Path, file: &InvoiceFile) -> String {
+    Path::new(&file.path)
+        .strip_prefix(folder)
+        .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_else(|_| file.file_name.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn file(folder: &Path, relative: &str) -> InvoiceFile {
+        let path: PathBuf = relative
+            .split('/')
+            .fold(folder.to_path_buf(), |path, part| path.join(part));
+        InvoiceFile {
+            path: path.to_string_lossy().into_owned(),
+            file_name: relative.rsplit('/').next().unwrap().to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn names(files: &[InvoiceFile], folder: &Path) -> Vec<String> {
+        files.iter().map(|file| relative_name(folder, file)).collect()
+    }
+
+    #[test]
+    fn parse_names_skips_comments_and_quotes() {
+        let text = "# 注释\r\n\r\n  a.pdf  \r\n\"C:\\发票\\b.jpg\"\n#c.pdf\n \" d.png \" \n\"\"\n";
+        assert_eq!(parse_names(text), ["a.pdf", "C:\\发票\\b.jpg", "d.png"]);
+        assert!(parse_names("").is_empty());
+        assert!(parse_names("# 只有注释\n   \n").is_empty());
+    }
+
+    #[test]
+    fn apply_matches_by_path_then_name() {
+        let folder = PathBuf::from("invoices");
+        let files = vec![
+            file(&folder, "a.pdf"),
+            file(&folder, "sub/x.pdf"),
+            file(&folder, "other/x.pdf"),
+            file(&folder, "B.jpg"),
+            file(&folder, "c.png"),
+        ];
+        let text = "other/x.pdf\nb.JPG\nsub\\x.pdf\nmissing.pdf\nB.jpg\nx.pdf\n";
+        let import = apply(&folder, files, text);
+        // 未列出的 a.pdf、c.png 保持原顺序排在最后
+        assert_eq!(
+            names(&import.files, &folder),
+            ["other/x.pdf", "B.jpg", "sub/x.pdf", "a.pdf", "c.png"]
+        );
+        // 每个文件只用一次：重复的 B.jpg 与已被用完的 x.pdf 都算未匹配
+        assert_eq!(import.unmatched, ["missing.pdf", "B.jpg", "x.pdf"]);
+        assert_eq!(import.unlisted, 2);
+    }
+
+    #[test]
+    fn apply_with_empty_list_keeps_order() {
+        let folder = PathBuf::from("invoices");
+        let files = vec![file(&folder, "b.pdf"), file(&folder, "a.pdf")];
+        let import = apply(&folder, files, "# nothing\n");
+        assert_eq!(names(&import.files, &folder), ["b.pdf", "a.pdf"]);
+        assert!(import.unmatched.is_empty());
+        assert_eq!(import.unlisted, 2);
+    }
+
+    #[test]
+    fn export_then_import_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path();
+        let files = vec![
+            file(folder, "餐饮/午餐.jpg"),
+            file(folder, "酒店 发票.pdf"),
+            file(folder, "a.pdf"),
+        ];
+        let written = export(folder, &files).unwrap();
+        assert_eq!(Path::new(&written), folder.join(SIDECAR_FILE));
+
+        let mut shuffled = files.clone();
+        shuffled.reverse();
+        let import = apply(folder, shuffled, &read_sidecar(folder).unwrap());
+        assert_eq!(names(&import.files, folder), names(&files, folder));
+        assert!(import.unmatched.is_empty());
+        assert_eq!(import.unlisted, 0);
+    }
+}
@@ -0,0 +1,31 @@
+use exif::{In, Reader, Tag};
+use image::DynamicImage;
+use std::{fs::File, io::BufReader, path::Path};
+
+/// 读取 EXIF 方向标记（1–8），没有 EXIF 或读取失败时返回 `None`。
+/// JPEG、TIFF、PNG、WebP 中的 EXIF 都能识别。
+fn exif_orientation(path: &Path) -> Option<u32> {
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    let exif = Reader::new().read_from_container(&mut reader).ok()?;
+    exif.get_field(Tag::Orientation, In::PRIMARY)?.value.get_uint(0)
+}
+
+/// 像素已是正向（没有方向标记或标记为 1），无需旋转。
+pub fn is_upright(path: &Path) -> bool {
+    matches!(exif_orientation(path), None | Some(1))
+}
+
+/// 按 EXIF 方向标记把像素转正。手机拍的照片像素按传感器方向存储，只靠标记告诉查看器如何旋转，
+/// image 库解码时不处理该标记，不转正的话横拍、倒拿的发票会躺着或倒着排进 PDF。
+pub fn apply_exif(path: &Path, image: DynamicImage) -> DynamicImage {
+    match exif_orientation(path) {
+        Some(2) => image.fliph(),
+        Some(3) => image.rotate180(),
+        Some(4) => image.flipv(),
+        Some(5) => image.rotate90().fliph(),
+        Some(6) => image.rotate90(),
+        Some(7) => image.rotate270().fliph(),
+        Some(8) => image.rotate270(),
+        _ => image,
+    }
+}
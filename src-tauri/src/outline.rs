@@ -0,0 +1,56 @@
+use lopdf::{Dictionary, Document, Object, ObjectId};
+
+use crate::{accessibility::text_string, MergeError};
+
+/// 为合并结果建立一级书签：每项一个标题，跳转到对应的页面并整页显示。
+/// 源文件自带的书签在合并时已丢弃，这里重新生成，打开文件时默认展开书签栏。
+pub fn add_outline(
+    doc: &mut Document,
+    catalog_id: ObjectId,
+    entries: &[(ObjectId, String)],
+) -> Result<(), MergeError> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    // 合并时对象是直接插入的，max_id 可能未同步
+    doc.max_id = doc
+        .objects
+        .keys()
+        .map(|(id, _)| *id)
+        .max()
+        .unwrap_or(0)
+        .max(doc.max_id);
+    let root_id = doc.new_object_id();
+    let item_ids: Vec<ObjectId> = entries.iter().map(|_| doc.new_object_id()).collect();
+    for (index, ((page_id, title), item_id)) in entries.iter().zip(&item_ids).enumerate() {
+        let mut item = Dictionary::new();
+        item.set("Title", text_string(title));
+        item.set("Parent", Object::Reference(root_id));
+        if index > 0 {
+            item.set("Prev", Object::Reference(item_ids[index - 1]));
+        }
+        if let Some(next) = item_ids.get(index + 1) {
+            item.set("Next", Object::Reference(*next));
+        }
+        item.set(
+            "Dest",
+            vec![Object::Reference(*page_id), Object::Name(b"Fit".to_vec())],
+        );
+        doc.objects.insert(*item_id, Object::Dictionary(item));
+    }
+
+    let mut root = Dictionary::new();
+    root.set("Type", Object::Name(b"Outlines".to_vec()));
+    root.set("First", Object::Reference(item_ids[0]));
+    root.set("Last", Object::Reference(item_ids[item_ids.len() - 1]));
+    root.set("Count", item_ids.len() as i64);
+    doc.objects.insert(root_id, Object::Dictionary(root));
+
+    let catalog = doc
+        .get_object_mut(catalog_id)
+        .and_then(|obj| obj.as_dict_mut())
+        .map_err(|err| MergeError::Pdf(err.to_string()))?;
+    catalog.set("Outlines", Object::Reference(root_id));
+    catalog.set("PageMode", Object::Name(b"UseOutlines".to_vec()));
+    Ok(())
+}
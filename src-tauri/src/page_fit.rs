@@ -0,0 +1,271 @@
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+use tempfile::TempPath;
+
+use crate::{
+    compress::{page_box, page_media_box},
+    crop, MergeError,
+};
+
+const MM_TO_PT: f64 = 72.0 / 25.4;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PageSize {
+    #[default]
+    A4,
+    A5,
+    A3,
+    Letter,
+}
+
+impl PageSize {
+    /// 纵向尺寸（宽, 高），单位毫米
+    pub fn dimensions_mm(self) -> (f64, f64) {
+        match self {
+            PageSize::A4 => (210.0, 297.0),
+            PageSize::A5 => (148.0, 210.0),
+            PageSize::A3 => (297.0, 420.0),
+            PageSize::Letter => (215.9, 279.4),
+        }
+    }
+}
+
+/// 图片等生成页的纸张方向
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PageOrientation {
+    #[default]
+    Portrait,
+    Landscape,
+    /// 宽图用横向纸张，其余用纵向
+    Auto,
+}
+
+/// 图片等生成页的版式，纸张大小仍由 `page_size` 决定。
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct PageLayout {
+    pub orientation: PageOrientation,
+    /// 页面缩到图片在纸张上等比放大后的大小，不留白边；小票等窄长图片不再夹在大片空白中
+    pub fit_to_image: bool,
+    /// 图片方向与纸张方向不一致时把图片转 90°，让宽图在纵向纸上也能占满版面
+    pub auto_rotate: bool,
+}
+
+impl PageLayout {
+    /// 是否需要把图片转 90° 再排版；纸张方向为自动时总与图片一致，无需旋转
+    pub fn rotates(self, image_w: u32, image_h: u32) -> bool {
+        self.auto_rotate
+            && match self.orientation {
+                PageOrientation::Portrait => image_w > image_h,
+                PageOrientation::Landscape => image_h > image_w,
+                PageOrientation::Auto => false,
+            }
+    }
+
+    /// 放置 `image_w` × `image_h` 像素图片的页面尺寸（宽, 高），单位毫米
+    pub fn page_mm(self, size: PageSize, image_w: u32, image_h: u32) -> (f64, f64) {
+        let (short, long) = size.dimensions_mm();
+        let landscape = match self.orientation {
+            PageOrientation::Portrait => false,
+            PageOrientation::Landscape => true,
+            PageOrientation::Auto => image_w > image_h,
+        };
+        let (page_w, page_h) = if landscape { (long, short) } else { (short, long) };
+        if !self.fit_to_image || image_w == 0 || image_h == 0 {
+            return (page_w, page_h);
+        }
+        let scale = (page_w / image_w as f64).min(page_h / image_h as f64);
+        (image_w as f64 * scale, image_h as f64 * scale)
+    }
+
+    /// 任意图片所在页面都不会超出的范围（宽, 高），单位毫米
+    pub fn bounds_mm(self, size: PageSize) -> (f64, f64) {
+        let (short, long) = size.dimensions_mm();
+        if self.auto_rotate {
+            return (long, long);
+        }
+        match self.orientation {
+            PageOrientation::Portrait => (short, long),
+            PageOrientation::Landscape => (long, short),
+            PageOrientation::Auto => (long, long),
+        }
+    }
+}
+
+/// 按文件级设置调整 PDF 页面（缩放到指定纸张、逐页旋转），结果写入临时 PDF。
+/// `crop_padding_mm` 为 `Some` 时先按内容裁掉多余的页边距。
+pub fn adjust_pdf(
+    path: &Path,
+    size: Option<PageSize>,
+    rotations: &BTreeMap<u32, i64>,
+    crop_padding_mm: Option<f64>,
+    work_dir: &Path,
+) -> Result<(PathBuf, TempPath), MergeError> {
+    let mut doc = Document::load(path).map_err(|err| MergeError::Pdf(err.to_string()))?;
+    if doc.is_encrypted() {
+        let _ = doc.decrypt(b"");
+    }
+    if let Some(padding) = crop_padding_mm {
+        crop::crop_pages_to_content(&mut doc, path, padding)?;
+    }
+    if let Some(size) = size {
+        fit_pages(&mut doc, size)?;
+    }
+    if !rotations.is_empty() {
+        rotate_pages(&mut doc, rotations)?;
+    }
+    save_temp_document(&mut doc, "mc-fit-", work_dir)
+}
+
+/// 把每一页等比缩放并居中到指定纸张上。
+/// 横向页面套用横向的纸张，避免被缩得过小；/Rotate 作用于缩放之后，无需特别处理。
+fn fit_pages(doc: &mut Document, size: PageSize) -> Result<(), MergeError> {
+    let (width_mm, height_mm) = size.dimensions_mm();
+    let (portrait_w, portrait_h) = (width_mm * MM_TO_PT, height_mm * MM_TO_PT);
+
+    for page_id in doc.get_pages().into_values() {
+        let Some([x0, y0, x1, y1]) = page_visible_box(doc, page_id) else {
+            continue;
+        };
+        let (width, height) = ((x1 - x0).abs(), (y1 - y0).abs());
+        if width <= 0.0 || height <= 0.0 {
+            continue;
+        }
+        let (left, bottom) = (x0.min(x1), y0.min(y1));
+        let (target_w, target_h) = if width > height {
+            (portrait_h, portrait_w)
+        } else {
+            (portrait_w, portrait_h)
+        };
+
+        let scale = (target_w / width).min(target_h / height);
+        let dx = (target_w - width * scale) / 2.0 - left * scale;
+        let dy = (target_h - height * scale) / 2.0 - bottom * scale;
+        // 先裁剪到原可见区域，避免 CropBox 之外的内容在新纸张的留白处露出来
+        let transform = format!(
+            "q {scale:.5} 0 0 {scale:.5} {dx:.3} {dy:.3} cm {left:.3} {bottom:.3} {width:.3} {height:.3} re W n\n"
+        );
+        wrap_page_contents(doc, page_id, transform.into_bytes(), b"\nQ\n".to_vec())?;
+
+        let page = doc
+            .get_object_mut(page_id)
+            .and_then(|obj| obj.as_dict_mut())
+            .map_err(|err| MergeError::Pdf(err.to_string()))?;
+        page.set(
+            "MediaBox",
+            vec![
+                0.into(),
+                0.into(),
+                Object::Real(target_w as _),
+                Object::Real(target_h as _),
+            ],
+        );
+        for key in [b"CropBox".as_slice(), b"BleedBox", b"TrimBox", b"ArtBox"] {
+            page.remove(key);
+        }
+    }
+
+    Ok(())
+}
+
+/// 在已有 /Rotate 的基础上叠加旋转；键为从 0 开始的页序号，角度须为 90 的倍数。
+fn rotate_pages(doc: &mut Document, rotations: &BTreeMap<u32, i64>) -> Result<(), MergeError> {
+    let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    for (&index, &degrees) in rotations {
+        if degrees % 90 != 0 {
+            return Err(MergeError::Pdf(format!(
+                "第 {} 页的旋转角度 {degrees} 不是 90 的倍数",
+                index + 1
+            )));
+        }
+        let Some(&page_id) = pages.get(index as usize) else {
+            continue;
+        };
+        let rotate = (page_rotation(doc, page_id) + degrees).rem_euclid(360);
+        doc.get_object_mut(page_id)
+            .and_then(|obj| obj.as_dict_mut())
+            .map_err(|err| MergeError::Pdf(err.to_string()))?
+            .set("Rotate", rotate);
+    }
+    Ok(())
+}
+
+/// 页面实际显示的区域：CropBox，未设置时为 MediaBox。
+pub(crate) fn page_visible_box(doc: &Document, page_id: ObjectId) -> Option<[f64; 4]> {
+    page_box(doc, page_id, b"CropBox").or_else(|| page_media_box(doc, page_id))
+}
+
+/// 页面（含从父节点继承）的 /Rotate，归一化到 0..360。
+pub(crate) fn page_rotation(doc: &Document, page_id: ObjectId) -> i64 {
+    let mut current = doc.get_dictionary(page_id).ok();
+    while let Some(dict) = current {
+        if let Ok(rotate) = dict.get(b"Rotate").and_then(|obj| obj.as_i64()) {
+            return rotate.rem_euclid(360);
+        }
+        current = dict
+            .get(b"Parent")
+            .and_then(|obj| obj.as_reference())
+            .and_then(|parent| doc.get_dictionary(parent))
+            .ok();
+    }
+    0
+}
+
+/// 在页面原有内容流的前后各插入一段内容流（常用于 `q … cm` / `Q` 包裹变换）。
+pub(crate) fn wrap_page_contents(
+    doc: &mut Document,
+    page_id: ObjectId,
+    prefix: Vec<u8>,
+    suffix: Vec<u8>,
+) -> Result<(), MergeError> {
+    let existing: Vec<Object> = match doc
+        .get_dictionary(page_id)
+        .ok()
+        .and_then(|page| page.get(b"Contents").ok())
+    {
+        Some(Object::Array(items)) => items.clone(),
+        Some(Object::Reference(id)) => match doc.get_object(*id) {
+            Ok(Object::Array(items)) => items.clone(),
+            _ => vec![Object::Reference(*id)],
+        },
+        _ => Vec::new(),
+    };
+
+    let prefix_id = doc.add_object(Stream::new(Dictionary::new(), prefix));
+    let suffix_id = doc.add_object(Stream::new(Dictionary::new(), suffix));
+    let mut contents = vec![Object::Reference(prefix_id)];
+    contents.extend(existing);
+    contents.push(Object::Reference(suffix_id));
+
+    doc.get_object_mut(page_id)
+        .and_then(|obj| obj.as_dict_mut())
+        .map_err(|err| MergeError::Pdf(err.to_string()))?
+        .set("Contents", contents);
+    Ok(())
+}
+
+pub(crate) fn save_temp_document(
+    doc: &mut Document,
+    prefix: &str,
+    work_dir: &Path,
+) -> Result<(PathBuf, TempPath), MergeError> {
+    let temp_file = tempfile::Builder::new()
+        .prefix(prefix)
+        .suffix(".pdf")
+        .tempfile_in(work_dir)?;
+    {
+        let mut writer = BufWriter::new(temp_file.as_file());
+        doc.save_to(&mut writer)
+            .map_err(|err| MergeError::Pdf(err.to_string()))?;
+        writer.flush()?;
+    }
+    let temp_path = temp_file.into_temp_path();
+    let path_buf = temp_path.to_path_buf();
+    Ok((path_buf, temp_path))
+}
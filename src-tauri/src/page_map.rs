@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PageSourceKind {
+    /// 源文件夹中的文件
+    Source,
+    Cover,
+    /// 按规则插入的模板或分隔页
+    Insert,
+    /// 失败文件的占位页
+    Placeholder,
+    /// 末尾的失败清单
+    Appendix,
+    /// 追加模式下原有的合并文件
+    Existing,
+    /// 开头生成的合并摘要页
+    Summary,
+}
+
+/// 输出中的一段页码及其来源，如第 5–7 页来自“酒店发票.pdf”。页码从 1 开始。
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PageRange {
+    pub start_page: usize,
+    pub end_page: usize,
+    pub file_name: String,
+    /// 生成的分隔页、失败清单等没有源路径，为空字符串
+    pub path: String,
+    pub kind: PageSourceKind,
+}
+
+impl PageRange {
+    /// 该段的页数；页码不成立（如读到损坏的增量状态）时为 0
+    pub fn page_count(&self) -> usize {
+        if self.start_page == 0 {
+            return 0;
+        }
+        (self.end_page + 1).saturating_sub(self.start_page)
+    }
+}
+
+struct Segment {
+    inputs: Range<usize>,
+    /// 复用上次输出时，每段的页数已知
+    pages: Option<usize>,
+    file_name: String,
+    path: String,
+    kind: PageSourceKind,
+}
+
+/// 合并过程中按顺序登记每段输入（`pdf_inputs` 的下标范围）来自哪里，合并后再换算成页码。
+#[derive(Default)]
+pub struct PageMapBuilder {
+    segments: Vec<Segment>,
+}
+
+impl PageMapBuilder {
+    pub fn push(&mut self, inputs: Range<usize>, kind: PageSourceKind, file_name: &str, path: &str) {
+        if inputs.is_empty() {
+            return;
+        }
+        self.segments.push(Segment {
+            inputs,
+            pages: None,
+            file_name: file_name.to_string(),
+            path: path.to_string(),
+            kind,
+        });
+    }
+
+    /// 复用的上次输出是单独一份输入，沿用上次记录的页码分布。
+    pub fn push_reused(&mut self, input: usize, ranges: &[PageRange]) {
+        for range in ranges.iter().filter(|range| range.page_count() > 0) {
+            self.segments.push(Segment {
+                inputs: input..input + 1,
+                pages: Some(range.page_count()),
+                file_name: range.file_name.clone(),
+                path: range.path.clone(),
+                kind: range.kind,
+            });
+        }
+    }
+
+    /// `pages_per_input` 为每份输入实际并入的页数。
+    pub fn build(&self, pages_per_input: &[usize]) -> Vec<PageRange> {
+        let mut ranges = Vec::with_capacity(self.segments.len());
+        let mut next_page = 1;
+        for segment in &self.segments {
+            let count = segment.pages.unwrap_or_else(|| {
+                segment
+                    .inputs
+                    .clone()
+                    .filter_map(|index| pages_per_input.get(index))
+                    .sum()
+            });
+            if count == 0 {
+                continue;
+            }
+            ranges.push(PageRange {
+                start_page: next_page,
+                end_page: next_page + count - 1,
+                file_name: segment.file_name.clone(),
+                path: segment.path.clone(),
+                kind: segment.kind,
+            });
+            next_page += count;
+        }
+        ranges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start_page: usize, end_page: usize, file_name: &str) -> PageRange {
+        PageRange {
+            start_page,
+            end_page,
+            file_name: file_name.to_string(),
+            path: String::new(),
+            kind: PageSourceKind::Source,
+        }
+    }
+
+    #[test]
+    fn page_count_of_malformed_ranges_is_zero() {
+        let cases: &[((usize, usize), usize)] =
+            &[((1, 1), 1), ((3, 7), 5), ((5, 4), 0), ((5, 2), 0), ((0, 3), 0)];
+        for &((start, end), expected) in cases {
+            assert_eq!(
+                range(start, end, "a.pdf").page_count(),
+                expected,
+                "第 {start}–{end} 页"
+            );
+        }
+    }
+
+    #[test]
+    fn build_skips_empty_segments() {
+        let mut builder = PageMapBuilder::default();
+        builder.push(0..1, PageSourceKind::Source, "a.pdf", "a.pdf");
+        // 选中的页面全部被去掉的输入
+        builder.push(1..2, PageSourceKind::Source, "b.pdf", "b.pdf");
+        builder.push(2..2, PageSourceKind::Insert, "分隔页", "");
+        builder.push(2..4, PageSourceKind::Source, "c.pdf", "c.pdf");
+        let ranges = builder.build(&[2, 0, 1, 3]);
+        let summary: Vec<(usize, usize, &str)> = ranges
+            .iter()
+            .map(|range| (range.start_page, range.end_page, range.file_name.as_str()))
+            .collect();
+        assert_eq!(summary, [(1, 2, "a.pdf"), (3, 6, "c.pdf")]);
+    }
+
+    #[test]
+    fn reused_ranges_skip_malformed_entries() {
+        let mut builder = PageMapBuilder::default();
+        builder.push_reused(
+            0,
+            &[range(1, 2, "a.pdf"), range(3, 2, "b.pdf"), range(3, 3, "c.pdf")],
+        );
+        builder.push(1..2, PageSourceKind::Source, "d.pdf", "d.pdf");
+        let ranges = builder.build(&[3, 1]);
+        let summary: Vec<(usize, usize, &str)> = ranges
+            .iter()
+            .map(|range| (range.start_page, range.end_page, range.file_name.as_str()))
+            .collect();
+        assert_eq!(summary, [(1, 2, "a.pdf"), (3, 3, "c.pdf"), (4, 4, "d.pdf")]);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::MergeError;
+
+/// 解析后的页码范围（从 1 开始，含两端）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageRanges {
+    spec: String,
+    /// `end` 为 `None` 表示到最后一页
+    ranges: Vec<(u32, Option<u32>)>,
+}
+
+impl PageRanges {
+    /// 解析 `"1"`、`"1-3,5"`、`"2-"` 这样的写法，逗号可用中文逗号或空格代替。
+    pub fn parse(spec: &str) -> Result<Self, MergeError> {
+        let invalid = || MergeError::InvalidPageRange(spec.to_string());
+        let mut ranges = Vec::new();
+        for part in spec
+            .split([',', '，', ' '])
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+        {
+            let range = match part.split_once(['-', '~']) {
+                Some((start, end)) => {
+                    let start: u32 = start.trim().parse().map_err(|_| invalid())?;
+                    let end = end.trim();
+                    let end = if end.is_empty() {
+                        None
+                    } else {
+                        Some(end.parse::<u32>().map_err(|_| invalid())?)
+                    };
+                    (start, end)
+                }
+                None => {
+                    let page: u32 = part.parse().map_err(|_| invalid())?;
+                    (page, Some(page))
+                }
+            };
+            if range.0 == 0 || range.1.is_some_and(|end| end < range.0) {
+                return Err(invalid());
+            }
+            ranges.push(range);
+        }
+        if ranges.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            spec: spec.to_string(),
+            ranges,
+        })
+    }
+
+    /// 共 `page_count` 页的文档中被选中的页码，按原顺序去重；超出页数的部分忽略。
+    pub fn select(&self, page_count: u32) -> Vec<u32> {
+        (1..=page_count)
+            .filter(|page| {
+                self.ranges
+                    .iter()
+                    .any(|(start, end)| page >= start && !end.is_some_and(|end| *page > end))
+            })
+            .collect()
+    }
+
+    /// 与 `select` 相同，但一页都没选中时报错，避免合并出缺页的结果而不自知。
+    pub fn select_some(&self, page_count: u32) -> Result<Vec<u32>, MergeError> {
+        let pages = self.select(page_count);
+        if pages.is_empty() {
+            Err(MergeError::PageRangeOutOfBounds {
+                spec: self.spec.clone(),
+                page_count,
+            })
+        } else {
+            Ok(pages)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selects_pages() {
+        let cases: &[(&str, u32, &[u32])] = &[
+            ("1", 5, &[1]),
+            ("1-3,5", 5, &[1, 2, 3, 5]),
+            ("2-", 4, &[2, 3, 4]),
+            ("1~2", 3, &[1, 2]),
+            ("1，3 5", 5, &[1, 3, 5]),
+            (" 2- , ", 3, &[2, 3]),
+            // 重叠与乱序的范围按原页序去重
+            ("3-5,1-4", 6, &[1, 2, 3, 4, 5]),
+            ("4,2,4", 5, &[2, 4]),
+            // 超出页数的部分忽略
+            ("2-10", 3, &[2, 3]),
+            ("3-3", 3, &[3]),
+        ];
+        for &(spec, page_count, expected) in cases {
+            let ranges = PageRanges::parse(spec).unwrap_or_else(|err| panic!("{spec:?}: {err}"));
+            assert_eq!(ranges.select(page_count), expected, "{spec:?} / {page_count}");
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_specs() {
+        for spec in [
+            "",
+            " ",
+            ",",
+            "0",
+            "0-2",
+            "3-1",
+            "-3",
+            "a",
+            "1-b",
+            "1--3",
+            "1.5",
+            "-",
+            "4294967296",
+            "2-1,1",
+        ] {
+            assert!(
+                matches!(PageRanges::parse(spec), Err(MergeError::InvalidPageRange(_))),
+                "{spec:?} 应被拒绝"
+            );
+        }
+    }
+
+    #[test]
+    fn out_of_range_selection_is_an_error() {
+        let ranges = PageRanges::parse("5-9").unwrap();
+        assert!(ranges.select(3).is_empty());
+        assert!(matches!(
+            ranges.select_some(3),
+            Err(MergeError::PageRangeOutOfBounds { page_count: 3, .. })
+        ));
+        assert_eq!(ranges.select_some(6).unwrap(), [5, 6]);
+        assert!(ranges.select_some(0).is_err());
+    }
+}
@@ -0,0 +1,62 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+/// 默认线程数：CPU 核数
+pub fn default_workers() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// 用户设置的线程数上限，未设置或为 0 时取 CPU 核数
+pub fn workers(limit: Option<usize>) -> usize {
+    limit.filter(|limit| *limit > 0).unwrap_or_else(default_workers)
+}
+
+/// 用至多 `workers` 个线程处理各项，结果按输入顺序返回。各项按值交给处理函数，
+/// 整份文档这样的大对象不必复制。线程逐个领取下一项而不是预先分块，
+/// 文件大小悬殊时也不会让个别线程拖到最后。
+pub fn map_ordered<T, R, F>(items: impl IntoIterator<Item = T>, workers: usize, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(usize, T) -> R + Sync,
+{
+    let items: Vec<T> = items.into_iter().collect();
+    let workers = workers.clamp(1, items.len().max(1));
+    if workers == 1 {
+        return items
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| f(index, item))
+            .collect();
+    }
+    let slots: Vec<Mutex<Option<T>>> = items.into_iter().map(|item| Mutex::new(Some(item))).collect();
+    let results: Vec<Mutex<Option<R>>> = slots.iter().map(|_| Mutex::new(None)).collect();
+    let next = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(slot) = slots.get(index) else {
+                    break;
+                };
+                let Some(item) = slot.lock().ok().and_then(|mut slot| slot.take()) else {
+                    continue;
+                };
+                let result = f(index, item);
+                if let Ok(mut slot) = results[index].lock() {
+                    *slot = Some(result);
+                }
+            });
+        }
+    });
+    // 线程中的 panic 会在 scope 结束时继续抛出，走到这里时每一项都已有结果
+    results
+        .into_iter()
+        .map(|slot| slot.into_inner().ok().flatten().expect("worker thread panicked"))
+        .collect()
+}
@@ -0,0 +1,193 @@
+use serde::Serialize;
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, RecvTimeoutError, Sender},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tauri::Window;
+use tempfile::TempPath;
+
+use crate::{cancel, emit_event, mmap_pdf, page_fit::save_temp_document, MergeError};
+
+pub const TEMP_PREFIX: &str = "mc-unlock-";
+/// 同一文件最多允许输错的次数
+const MAX_ATTEMPTS: u32 = 3;
+/// 无人应答（如通过 HTTP 接口或命令行调用）时放弃等待的时限
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(300);
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// 判断是否加密时在文件末尾和交叉引用处各读取的字节数，足以容纳 trailer 字典
+const PROBE_BYTES: u64 = 4096;
+
+/// 正在等待用户输入密码的合并；同一时间只有一个合并在运行，最多一个等待者。
+/// 以插件形式嵌入时也要可用，因此不放在托管状态里
+static WAITING: Mutex<Option<Sender<Option<String>>>> = Mutex::new(None);
+
+#[derive(Serialize, Clone)]
+struct PasswordRequest<'a> {
+    path: &'a str,
+    file_name: &'a str,
+    /// 第几次询问，从 1 开始；大于 1 表示上次输入的密码不正确
+    attempt: u32,
+}
+
+/// 前端对 `password-required` 的答复；`None` 表示跳过该文件。没有合并在等待时返回 `false`。
+pub fn provide(password: Option<String>) -> bool {
+    let sender = WAITING.lock().ok().and_then(|mut pending| pending.take());
+    sender.is_some_and(|sender| sender.send(password).is_ok())
+}
+
+/// 空密码打不开的加密 PDF：发出 `password-required` 并等待 `provide_pdf_password_cmd` 的答复，
+/// 解密成功后写出一份不加密的临时副本供后续步骤使用。
+/// 未加密或空密码即可打开的文件返回 `None`，保持原有处理方式。
+pub fn unlock(
+    window: &Window,
+    path: &Path,
+    file_name: &str,
+    work_dir: &Path,
+) -> Result<Option<(PathBuf, TempPath)>, MergeError> {
+    // 绝大多数文件没有加密，先看 trailer，免得为此把每份 PDF 都完整解析一遍
+    if !may_be_encrypted(path) {
+        return Ok(None);
+    }
+    let mut doc = mmap_pdf::load(path)?;
+    if !doc.is_encrypted() || doc.decrypt(b"").is_ok() {
+        return Ok(None);
+    }
+    let display_path = path.to_string_lossy();
+    for attempt in 1..=MAX_ATTEMPTS {
+        let request = PasswordRequest {
+            path: &display_path,
+            file_name,
+            attempt,
+        };
+        let Some(password) = ask(window, request)? else {
+            return Err(MergeError::PasswordRequired);
+        };
+        if doc.decrypt(password.as_bytes()).is_ok() {
+            return save_temp_document(&mut doc, TEMP_PREFIX, work_dir).map(Some);
+        }
+    }
+    Err(MergeError::WrongPassword)
+}
+
+/// 不解析整份文件，只找 trailer 中的 `/Encrypt`：经典 trailer 在文件末尾，
+/// 交叉引用流的字典在 `startxref` 指向处。读不到或结构看不懂时按可能加密处理，交给完整解析判断。
+fn may_be_encrypted(path: &Path) -> bool {
+    probe_encrypt(path).unwrap_or(true)
+}
+
+fn probe_encrypt(path: &Path) -> Option<bool> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    let mut tail = Vec::new();
+    file.seek(SeekFrom::Start(len.saturating_sub(PROBE_BYTES))).ok()?;
+    file.read_to_end(&mut tail).ok()?;
+    if contains(&tail, b"/Encrypt") {
+        return Some(true);
+    }
+    let xref_offset = startxref_offset(&tail)?;
+    let mut xref = Vec::new();
+    file.seek(SeekFrom::Start(xref_offset)).ok()?;
+    (&mut file).take(PROBE_BYTES).read_to_end(&mut xref).ok()?;
+    Some(contains(&xref, b"/Encrypt"))
+}
+
+/// 文件末尾最后一个 `startxref` 之后的偏移量
+fn startxref_offset(tail: &[u8]) -> Option<u64> {
+    const KEYWORD: &[u8] = b"startxref";
+    let start = tail
+        .windows(KEYWORD.len())
+        .rposition(|window| window == KEYWORD)?
+        + KEYWORD.len();
+    let digits: String = tail[start..]
+        .iter()
+        .skip_while(|byte| byte.is_ascii_whitespace())
+        .take_while(|byte| byte.is_ascii_digit())
+        .map(|&byte| byte as char)
+        .collect();
+    digits.parse().ok()
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+fn ask(window: &Window, request: PasswordRequest) -> Result<Option<String>, MergeError> {
+    let (sender, receiver) = mpsc::channel();
+    if let Ok(mut pending) = WAITING.lock() {
+        *pending = Some(sender);
+    }
+    emit_event(window, "password-required", request);
+
+    let deadline = Instant::now() + ANSWER_TIMEOUT;
+    let answer = loop {
+        // 等待期间仍可取消合并
+        if let Err(err) = cancel::check() {
+            break Err(err);
+        }
+        match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(answer) => break Ok(answer),
+            Err(RecvTimeoutError::Timeout) if Instant::now() < deadline => continue,
+            Err(_) => break Ok(None),
+        }
+    };
+    if let Ok(mut pending) = WAITING.lock() {
+        pending.take();
+    }
+    answer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn pdf_with_trailer(padding: usize, trailer: &str, xref_dict: &str) -> Vec<u8> {
+        let mut bytes = b"%PDF-1.7\n".to_vec();
+        bytes.extend(vec![b'%'; padding]);
+        bytes.push(b'\n');
+        let xref_offset = bytes.len();
+        bytes.extend(format!("5 0 obj\n{xref_dict}\nstream\nendstream\nendobj\n").bytes());
+        // 交叉引用流的数据可能很长，把字典推到末尾探测范围之外
+        bytes.extend(vec![b'0'; PROBE_BYTES as usize * 2]);
+        bytes.extend(format!("\n{trailer}\nstartxref\n{xref_offset}\n%%EOF\n").bytes());
+        bytes
+    }
+
+    #[test]
+    fn encrypt_probe_reads_trailer_and_xref_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let cases: &[(&str, Vec<u8>, bool)] = &[
+            (
+                "经典 trailer 带 /Encrypt",
+                pdf_with_trailer(
+                    16,
+                    "trailer\n<< /Root 1 0 R /Encrypt 2 0 R >>",
+                    "<< /Type /XRef >>",
+                ),
+                true,
+            ),
+            (
+                "交叉引用流字典带 /Encrypt",
+                pdf_with_trailer(16, "", "<< /Type /XRef /Root 1 0 R /Encrypt 2 0 R >>"),
+                true,
+            ),
+            (
+                "未加密",
+                pdf_with_trailer(16, "trailer\n<< /Root 1 0 R >>", "<< /Type /XRef /Root 1 0 R >>"),
+                false,
+            ),
+            ("没有 startxref", b"%PDF-1.7\nnot really a pdf".to_vec(), true),
+        ];
+        for (name, bytes, expected) in cases {
+            let path = dir.path().join("probe.pdf");
+            fs::write(&path, bytes).unwrap();
+            assert_eq!(may_be_encrypted(&path), *expected, "{name}");
+        }
+    }
+}
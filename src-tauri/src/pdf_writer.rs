@@ -0,0 +1,229 @@
+use flate2::{write::ZlibEncoder, Compression};
+use lopdf::{Dictionary, Document, Object, ObjectId, StringFormat};
+use std::io::{self, Write};
+
+/// 每个对象流最多容纳的对象数，过大时阅读器随机访问单个对象需要解压的数据变多
+const OBJECTS_PER_STREAM: usize = 200;
+/// 非流对象压缩进对象流后平均占用的字节数，只用于估算进度
+const PACKED_OBJECT_ESTIMATE: u64 = 40;
+
+/// 以 PDF 1.5 的对象流与交叉引用流写出文档：字典、数组等非流对象打包进压缩的对象流，
+/// 交叉引用表也以压缩流的形式写出。由成千上万个小对象组成的合并结果能明显变小。
+/// 流对象（图片、内容流）以及生成号非 0 的对象仍按普通间接对象写出。
+///
+/// 写出过程中以 `(已写字节, 预计总字节)` 调用 `progress`，大约每写完 1% 调用一次。
+/// 预计总量按流内容长度估算，可能略小于实际值，调用方应自行截断。
+pub fn save_compact<W: Write>(
+    doc: &Document,
+    writer: W,
+    mut progress: impl FnMut(u64, u64),
+) -> io::Result<u64> {
+    let estimated: u64 = doc
+        .objects
+        .values()
+        .map(|object| match object {
+            Object::Stream(stream) => stream.content.len() as u64,
+            _ => PACKED_OBJECT_ESTIMATE,
+        })
+        .sum();
+    let step = (estimated / 100).max(64 * 1024);
+    let mut reported = 0u64;
+    let mut report = |written: u64| {
+        if written >= reported + step {
+            reported = written;
+            progress(written, estimated.max(written));
+        }
+    };
+
+    let mut out = CountingWriter {
+        inner: writer,
+        written: 0,
+    };
+    // 对象流至少需要 1.5；第二行的高位字节告诉传输工具这是二进制文件
+    writeln!(out, "%PDF-{}", doc.version.clone().max("1.5".to_string()))?;
+    out.write_all(b"%\xE2\xE3\xCF\xD3\n")?;
+
+    let max_id = doc.objects.keys().map(|(id, _)| *id).max().unwrap_or(0);
+    // 下标为对象号：(类型, 字段 2, 字段 3)，含义同交叉引用流
+    let mut entries: Vec<(u8, u64, u64)> = vec![(0, 0, 65535); max_id as usize + 1];
+    let mut packable: Vec<(ObjectId, &Object)> = Vec::new();
+    for (&(id, generation), object) in &doc.objects {
+        if matches!(object, Object::Stream(_)) || generation != 0 {
+            entries[id as usize] = (1, out.written, generation as u64);
+            writeln!(out, "{id} {generation} obj")?;
+            write_object(&mut out, object)?;
+            out.write_all(b"\nendobj\n")?;
+            report(out.written);
+        } else {
+            packable.push(((id, generation), object));
+        }
+    }
+
+    let mut next_id = max_id + 1;
+    for chunk in packable.chunks(OBJECTS_PER_STREAM) {
+        let stream_id = next_id;
+        next_id += 1;
+        let mut header = Vec::new();
+        let mut body = Vec::new();
+        for (index, ((id, _), object)) in chunk.iter().enumerate() {
+            write!(header, "{id} {} ", body.len())?;
+            write_object(&mut body, object)?;
+            body.push(b'\n');
+            entries[*id as usize] = (2, stream_id as u64, index as u64);
+        }
+        let mut content = header.clone();
+        content.extend_from_slice(&body);
+
+        let mut dict = Dictionary::new();
+        dict.set("Type", Object::Name(b"ObjStm".to_vec()));
+        dict.set("N", chunk.len() as i64);
+        dict.set("First", header.len() as i64);
+        entries.push((1, out.written, 0));
+        write_stream(&mut out, stream_id, dict, &content)?;
+        report(out.written);
+    }
+
+    // 交叉引用流本身也要登记
+    let xref_id = next_id;
+    let xref_offset = out.written;
+    entries.push((1, xref_offset, 0));
+    let offset_width = bytes_needed(entries.iter().map(|&(_, field, _)| field).max().unwrap_or(0));
+    let index_width = bytes_needed(entries.iter().map(|&(_, _, field)| field).max().unwrap_or(0));
+    let mut table = Vec::with_capacity(entries.len() * (1 + offset_width + index_width));
+    for (kind, field2, field3) in &entries {
+        table.push(*kind);
+        table.extend_from_slice(&field2.to_be_bytes()[8 - offset_width..]);
+        table.extend_from_slice(&field3.to_be_bytes()[8 - index_width..]);
+    }
+
+    let mut dict = Dictionary::new();
+    for key in [b"Root".as_slice(), b"Info", b"ID"] {
+        if let Ok(value) = doc.trailer.get(key) {
+            dict.set(key.to_vec(), value.clone());
+        }
+    }
+    dict.set("Type", Object::Name(b"XRef".to_vec()));
+    dict.set("Size", entries.len() as i64);
+    dict.set(
+        "W",
+        vec![
+            Object::Integer(1),
+            Object::Integer(offset_width as i64),
+            Object::Integer(index_width as i64),
+        ],
+    );
+    write_stream(&mut out, xref_id, dict, &table)?;
+    write!(out, "startxref\n{xref_offset}\n%%EOF\n")?;
+    out.flush()?;
+    progress(out.written, out.written);
+    Ok(out.written)
+}
+
+fn write_stream<W: Write>(out: &mut W, id: u32, mut dict: Dictionary, content: &[u8]) -> io::Result<()> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(content)?;
+    let compressed = encoder.finish()?;
+    dict.set("Filter", Object::Name(b"FlateDecode".to_vec()));
+    dict.set("Length", compressed.len() as i64);
+    writeln!(out, "{id} 0 obj")?;
+    write_dictionary(out, &dict)?;
+    out.write_all(b"\nstream\n")?;
+    out.write_all(&compressed)?;
+    out.write_all(b"\nendstream\nendobj\n")
+}
+
+fn bytes_needed(value: u64) -> usize {
+    (((64 - value.leading_zeros()) as usize).div_ceil(8)).max(1)
+}
+
+fn write_object<W: Write>(out: &mut W, object: &Object) -> io::Result<()> {
+    match object {
+        Object::Null => out.write_all(b"null"),
+        Object::Boolean(value) => out.write_all(if *value { b"true" } else { b"false" }),
+        Object::Integer(value) => write!(out, "{value}"),
+        Object::Real(value) if value.is_finite() => write!(out, "{value}"),
+        Object::Real(_) => out.write_all(b"0"),
+        Object::Name(name) => write_name(out, name),
+        Object::String(bytes, StringFormat::Literal) => write_literal_string(out, bytes),
+        Object::String(bytes, StringFormat::Hexadecimal) => {
+            out.write_all(b"<")?;
+            for byte in bytes {
+                write!(out, "{byte:02X}")?;
+            }
+            out.write_all(b">")
+        }
+        Object::Array(items) => {
+            out.write_all(b"[")?;
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    out.write_all(b" ")?;
+                }
+                write_object(out, item)?;
+            }
+            out.write_all(b"]")
+        }
+        Object::Dictionary(dict) => write_dictionary(out, dict),
+        Object::Stream(stream) => {
+            let mut dict = stream.dict.clone();
+            dict.set("Length", stream.content.len() as i64);
+            write_dictionary(out, &dict)?;
+            out.write_all(b"\nstream\n")?;
+            out.write_all(&stream.content)?;
+            out.write_all(b"\nendstream")
+        }
+        Object::Reference((id, generation)) => write!(out, "{id} {generation} R"),
+    }
+}
+
+fn write_dictionary<W: Write>(out: &mut W, dict: &Dictionary) -> io::Result<()> {
+    out.write_all(b"<<")?;
+    for (key, value) in dict.iter() {
+        write_name(out, key)?;
+        out.write_all(b" ")?;
+        write_object(out, value)?;
+    }
+    out.write_all(b">>")
+}
+
+/// 名称中的空白、分隔符和非 ASCII 字节写成 `#xx`。
+fn write_name<W: Write>(out: &mut W, name: &[u8]) -> io::Result<()> {
+    out.write_all(b"/")?;
+    for &byte in name {
+        if (0x21..=0x7e).contains(&byte) && !b"#()<>[]{}/%".contains(&byte) {
+            out.write_all(&[byte])?;
+        } else {
+            write!(out, "#{byte:02X}")?;
+        }
+    }
+    Ok(())
+}
+
+fn write_literal_string<W: Write>(out: &mut W, bytes: &[u8]) -> io::Result<()> {
+    out.write_all(b"(")?;
+    for &byte in bytes {
+        match byte {
+            b'(' | b')' | b'\\' => out.write_all(&[b'\\', byte])?,
+            b'\r' => out.write_all(b"\\r")?,
+            b'\n' => out.write_all(b"\\n")?,
+            _ => out.write_all(&[byte])?,
+        }
+    }
+    out.write_all(b")")
+}
+
+struct CountingWriter<W> {
+    inner: W,
+    written: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
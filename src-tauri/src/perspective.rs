@@ -0,0 +1,107 @@
+use image::{imageops::FilterType, DynamicImage, GenericImageView, Rgb, RgbImage};
+use imageproc::{
+    contours::{find_contours, BorderType},
+    contrast::{otsu_level, threshold},
+    geometric_transformations::{warp_into, Interpolation, Projection},
+};
+
+/// 检测纸张轮廓时使用的长边像素数
+const DETECT_SIZE: u32 = 600;
+/// 纸张至少要占画面的比例，避免把票面上的表格框当成纸张
+const MIN_AREA_RATIO: f64 = 0.2;
+/// 四角都贴近画面边缘时视为已经是正面扫描，无需矫正
+const EDGE_TOLERANCE: f32 = 0.02;
+
+/// 找出照片中纸张的四边形并拉直为矩形；检测不到可信的纸张轮廓时原样返回。
+pub fn correct_perspective(image: DynamicImage) -> DynamicImage {
+    let (width, height) = image.dimensions();
+    let scale = DETECT_SIZE as f32 / width.max(height) as f32;
+    if scale >= 1.0 {
+        return image;
+    }
+    let small = image
+        .resize(DETECT_SIZE, DETECT_SIZE, FilterType::Triangle)
+        .to_luma8();
+    // 纸张通常比桌面亮，用 Otsu 阈值分出前景
+    let binary = threshold(&small, otsu_level(&small));
+
+    let Some(corners) = find_contours::<i32>(&binary)
+        .into_iter()
+        .filter(|contour| contour.border_type == BorderType::Outer)
+        .map(|contour| quad_corners(&contour.points))
+        .filter(|corners| quad_area(corners) >= MIN_AREA_RATIO * (small.width() * small.height()) as f64)
+        .max_by(|a, b| quad_area(a).total_cmp(&quad_area(b)))
+    else {
+        return image;
+    };
+
+    let (sw, sh) = (small.width() as f32, small.height() as f32);
+    let image_corners = [(0.0, 0.0), (sw, 0.0), (sw, sh), (0.0, sh)];
+    let already_flat = corners.iter().zip(image_corners).all(|(corner, edge)| {
+        (corner.0 - edge.0).abs() <= sw * EDGE_TOLERANCE && (corner.1 - edge.1).abs() <= sh * EDGE_TOLERANCE
+    });
+    if already_flat {
+        return image;
+    }
+
+    let source = corners.map(|(x, y)| (x / scale, y / scale));
+    let [tl, tr, br, bl] = source;
+    let out_w = distance(tl, tr).max(distance(bl, br)).round() as u32;
+    let out_h = distance(tl, bl).max(distance(tr, br)).round() as u32;
+    if out_w == 0 || out_h == 0 {
+        return image;
+    }
+    let target = [
+        (0.0, 0.0),
+        (out_w as f32, 0.0),
+        (out_w as f32, out_h as f32),
+        (0.0, out_h as f32),
+    ];
+    let Some(projection) = Projection::from_control_points(source, target) else {
+        return image;
+    };
+
+    let rgb = image.to_rgb8();
+    let mut output = RgbImage::new(out_w, out_h);
+    warp_into(
+        &rgb,
+        &projection,
+        Interpolation::Bilinear,
+        Rgb([255, 255, 255]),
+        &mut output,
+    );
+    DynamicImage::ImageRgb8(output)
+}
+
+/// 取轮廓的四个极点作为角点，顺序为左上、右上、右下、左下。
+fn quad_corners(points: &[imageproc::point::Point<i32>]) -> [(f32, f32); 4] {
+    let pick = |key: fn(i32, i32) -> i32, max: bool| {
+        let point = if max {
+            points.iter().max_by_key(|p| key(p.x, p.y))
+        } else {
+            points.iter().min_by_key(|p| key(p.x, p.y))
+        };
+        point.map_or((0.0, 0.0), |p| (p.x as f32, p.y as f32))
+    };
+    [
+        pick(|x, y| x + y, false),
+        pick(|x, y| x - y, true),
+        pick(|x, y| x + y, true),
+        pick(|x, y| x - y, false),
+    ]
+}
+
+/// 鞋带公式计算四边形面积
+fn quad_area(corners: &[(f32, f32); 4]) -> f64 {
+    let mut sum = 0.0;
+    for i in 0..4 {
+        let (x0, y0) = corners[i];
+        let (x1, y1) = corners[(i + 1) % 4];
+        sum += (x0 * y1 - x1 * y0) as f64;
+    }
+    sum.abs() / 2.0
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
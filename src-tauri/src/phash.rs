@@ -0,0 +1,23 @@
+use image::{imageops::FilterType, DynamicImage};
+
+/// 汉明距离不超过该值即视为视觉上几乎相同（64 位哈希）
+const NEAR_DUPLICATE_DISTANCE: u32 = 6;
+
+/// 差值哈希（dHash）：缩成 9×8 灰度图，逐行比较相邻像素亮度。
+/// 对缩放、轻微压缩和亮度变化不敏感，适合识别"同一张发票拍了两次"。
+pub fn dhash(image: &DynamicImage) -> u64 {
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = small.get_pixel(x, y).0[0];
+            let right = small.get_pixel(x + 1, y).0[0];
+            hash = (hash << 1) | u64::from(left > right);
+        }
+    }
+    hash
+}
+
+pub fn is_near_duplicate(a: u64, b: u64) -> bool {
+    (a ^ b).count_ones() <= NEAR_DUPLICATE_DISTANCE
+}
@@ -0,0 +1,305 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::{
+    append,
+    config::AppConfig,
+    convert_to_pdf, cover,
+    insert::{self, InsertRule, Insertion},
+    mmap_pdf,
+    page_map::{PageRange, PageSourceKind},
+    page_range::PageRanges,
+    pipeline_ext, resolve_file_ids, resolve_work_dir, sort_files, summary, ConvertOptions, InvoiceFile,
+    MergeError, MergeRequest, IMAGE_EXTENSIONS,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlanEntry {
+    pub path: String,
+    pub file_name: String,
+    pub page_count: u32,
+    /// 在合并结果中的起止页码（从 1 开始）；无法解析的文件为 `None`
+    pub start_page: Option<u32>,
+    pub end_page: Option<u32>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MergePlan {
+    pub entries: Vec<PlanEntry>,
+    pub total_pages: u32,
+}
+
+/// 按请求的排序方式排好文件，并逐个解析页数，得到最终的页码分布。
+/// 与实际合并一样先套用方案，追加模式下原有文件占据开头的页码。
+pub fn plan_merge(config: &AppConfig, mut req: MergeRequest) -> Result<MergePlan, MergeError> {
+    let folder = Path::new(&req.folder_path)
+        .canonicalize()
+        .map_err(|_| MergeError::InvalidFolder)?;
+    config.ensure_allowed(&folder)?;
+    config.apply_profile(&mut req, &folder)?;
+    resolve_file_ids(&mut req)?;
+    let append_base = req
+        .append_to
+        .as_deref()
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(append::resolve_base)
+        .transpose()?;
+    if let Some(base) = &append_base {
+        cover::exclude(&mut req.files, base);
+    }
+    if req.files.is_empty() {
+        return Err(MergeError::NoFiles);
+    }
+    sort_files(&mut req.files, req.sort_mode, req.descending);
+    let work_dir = resolve_work_dir(req.temp_dir.as_deref())?;
+    let convert_opts = ConvertOptions::from_request(&req);
+
+    let templates = req
+        .insertions
+        .iter()
+        .map(InsertRule::resolve_pdf)
+        .collect::<Result<Vec<_>, _>>()?;
+    for template in templates.iter().flatten() {
+        cover::exclude(&mut req.files, template);
+    }
+
+    let mut entries = Vec::with_capacity(req.files.len() + 1);
+    let mut next_page = 1u32;
+    if let Some(base) = &append_base {
+        let path = base.to_string_lossy();
+        let name = file_name_of(&path);
+        push_entry(&mut entries, &mut next_page, &path, name, pdf_page_count(base));
+    }
+    // 追加模式下保留原文件的封面，不再插入指定的封面
+    if let Some(path) = req
+        .cover_pdf
+        .as_deref()
+        .map(str::trim)
+        .filter(|path| !path.is_empty() && append_base.is_none())
+    {
+        let count = cover::resolve(path).and_then(|cover_path| {
+            cover::exclude(&mut req.files, &cover_path);
+            pdf_page_count(&cover_path)
+        });
+        push_entry(&mut entries, &mut next_page, path, file_name_of(path), count);
+    }
+    if let Some(nup) = &req.n_up {
+        nup.validate()?;
+    }
+    let mut file_count = 0;
+    // 拼版模式下相邻的图片共用一页，与合并时的 `flush_nup` 规则相同
+    let mut nup_run: Vec<&InvoiceFile> = Vec::new();
+    for (index, file) in req.files.iter().enumerate() {
+        let insertions = insert::before(&req.insertions, &templates, &req.files, index);
+        let path = Path::new(&file.path);
+        let nup_image = req.n_up.is_some()
+            && pipeline_ext(&file.ext, path).is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.as_str()));
+        if let Some(nup) = &req.n_up {
+            if nup_run.len() >= nup.per_page || !nup_image || !insertions.is_empty() {
+                flush_nup(&mut nup_run, &mut entries, &mut next_page);
+            }
+        }
+        for insertion in insertions {
+            match insertion {
+                Insertion::Pdf(pdf) => {
+                    let path = pdf.to_string_lossy();
+                    let name = file_name_of(&path);
+                    push_entry(&mut entries, &mut next_page, &path, name, pdf_page_count(&pdf));
+                }
+                Insertion::Divider(label) => push_entry(&mut entries, &mut next_page, "", label, Ok(1)),
+            }
+        }
+        if nup_image {
+            nup_run.push(file);
+            file_count += 1;
+            continue;
+        }
+        let mut count = count_pages(path, &file.ext, &work_dir, &convert_opts.for_file(file));
+        if let Some(spec) = req
+            .page_ranges_for(file)
+            .filter(|_| pipeline_ext(&file.ext, path).as_deref() == Some("pdf"))
+        {
+            count = count.and_then(|count| {
+                let pages = PageRanges::parse(spec)?.select_some(count)?;
+                Ok(pages.len() as u32)
+            });
+        }
+        file_count += usize::from(count.is_ok());
+        push_entry(
+            &mut entries,
+            &mut next_page,
+            &file.path,
+            file.file_name.clone(),
+            count,
+        );
+    }
+
+    flush_nup(&mut nup_run, &mut entries, &mut next_page);
+
+    if req.summary_page && append_base.is_none() {
+        next_page += prepend_summary(&mut entries, &req.folder_path, file_count, &work_dir);
+    }
+
+    Ok(MergePlan {
+        entries,
+        total_pages: next_page - 1,
+    })
+}
+
+/// 攒下的图片拼成一页，按合并时页码映射的写法登记：文件名以顿号连接，路径取第一个文件。
+fn flush_nup(run: &mut Vec<&InvoiceFile>, entries: &mut Vec<PlanEntry>, next_page: &mut u32) {
+    let Some(first) = run.first() else {
+        return;
+    };
+    let path = first.path.clone();
+    let name = run
+        .iter()
+        .map(|file| file.file_name.as_str())
+        .collect::<Vec<_>>()
+        .join("、");
+    push_entry(entries, next_page, &path, name, Ok(1));
+    run.clear();
+}
+
+/// 合并时摘要页插在最前面：在开头登记摘要页并把其余页码整体后移，返回摘要页数。
+fn prepend_summary(entries: &mut Vec<PlanEntry>, folder: &str, file_count: usize, work_dir: &Path) -> u32 {
+    let page_map: Vec<PageRange> = entries
+        .iter()
+        .filter_map(|entry| {
+            Some(PageRange {
+                start_page: entry.start_page? as usize,
+                end_page: entry.end_page? as usize,
+                file_name: entry.file_name.clone(),
+                path: entry.path.clone(),
+                kind: PageSourceKind::Source,
+            })
+        })
+        .collect();
+    let count = summary::page_count(folder, file_count, &page_map, work_dir).map(|pages| pages as u32);
+    let added = *count.as_ref().unwrap_or(&0);
+    for entry in entries.iter_mut() {
+        entry.start_page = entry.start_page.map(|page| page + added);
+        entry.end_page = entry.end_page.map(|page| page + added);
+    }
+    let mut summary_entry = Vec::with_capacity(1);
+    push_entry(&mut summary_entry, &mut 1, "", "合并摘要".to_string(), count);
+    entries.splice(0..0, summary_entry);
+    added
+}
+
+fn push_entry(
+    entries: &mut Vec<PlanEntry>,
+    next_page: &mut u32,
+    path: &str,
+    file_name: String,
+    count: Result<u32, MergeError>,
+) {
+    let (page_count, error) = match count {
+        Ok(count) => (count, None),
+        Err(err) => (0, Some(err.to_string())),
+    };
+    let (start_page, end_page) = if page_count > 0 {
+        (Some(*next_page), Some(*next_page + page_count - 1))
+    } else {
+        (None, None)
+    };
+    *next_page += page_count;
+    entries.push(PlanEntry {
+        path: path.to_string(),
+        file_name,
+        page_count,
+        start_page,
+        end_page,
+        error,
+    });
+}
+
+fn file_name_of(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+pub(crate) fn count_pages(
+    path: &Path,
+    ext: &str,
+    work_dir: &Path,
+    opts: &ConvertOptions,
+) -> Result<u32, MergeError> {
+    let ext = pipeline_ext(ext, path).ok_or_else(|| MergeError::Unsupported(ext.to_string()))?;
+    if ext == "pdf" {
+        return pdf_page_count(path);
+    }
+    let (converted, _temp_path) = convert_to_pdf(&ext, path, work_dir, opts)?;
+    pdf_page_count(&converted)
+}
+
+pub(crate) fn pdf_page_count(path: &Path) -> Result<u32, MergeError> {
+    let doc = mmap_pdf::load(path)?;
+    Ok(doc.get_pages().len() as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+    use lopdf::{dictionary, Document, Object};
+
+    fn one_page_pdf(path: &Path) {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+        });
+        doc.objects.insert(
+            pages_id,
+            dictionary! { "Type" => "Pages", "Kids" => vec![Object::Reference(page_id)], "Count" => 1 }
+                .into(),
+        );
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+        doc.save(path).unwrap();
+    }
+
+    #[test]
+    fn packs_adjacent_images_in_n_up_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a1.png", "a2.png", "a3.png", "c1.png"] {
+            RgbImage::from_pixel(8, 8, Rgb([200, 10, 10]))
+                .save(dir.path().join(name))
+                .unwrap();
+        }
+        one_page_pdf(&dir.path().join("b.pdf"));
+        let files = crate::scan_folder(dir.path(), &[]).unwrap();
+        let req: MergeRequest = serde_json::from_value(serde_json::json!({
+            "folder_path": dir.path().to_string_lossy(),
+            "files": files,
+            "sort_mode": "FileNameAsc",
+            "n_up": { "per_page": 2 },
+        }))
+        .unwrap();
+
+        let plan = plan_merge(&AppConfig::default(), req).unwrap();
+        let pages: Vec<(&str, Option<u32>)> = plan
+            .entries
+            .iter()
+            .map(|entry| (entry.file_name.as_str(), entry.start_page))
+            .collect();
+        // PDF 打断图片的连续段，段内每 2 张一页
+        assert_eq!(
+            pages,
+            [
+                ("a1.png、a2.png", Some(1)),
+                ("a3.png", Some(2)),
+                ("b.pdf", Some(3)),
+                ("c1.png", Some(4)),
+            ]
+        );
+        assert_eq!(plan.total_pages, 4);
+    }
+}
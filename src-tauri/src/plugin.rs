@@ -0,0 +1,32 @@
+use tauri::{
+    plugin::{Builder, TauriPlugin},
+    Wry,
+};
+
+/// 插件名，前端以 `plugin:invoice-merge|<命令>` 调用，见 `src/lib/invoiceMerge.ts`。
+pub const PLUGIN_NAME: &str = "invoice-merge";
+
+/// 把扫描、校验、规划与合并命令打包成 Tauri 插件，其他应用 `.plugin(tauri_plugin_invoice_merge::init())` 即可接入。
+/// 这里只包含不依赖本应用托管状态（托盘、启动参数等）的命令；取消与密码答复用到的状态是全局的，
+/// 不需要宿主应用额外 `manage`。
+pub fn init() -> TauriPlugin<Wry> {
+    Builder::new(PLUGIN_NAME)
+        .invoke_handler(tauri::generate_handler![
+            crate::scan_folder_cmd,
+            crate::scan_folder_stream_cmd,
+            crate::scan_folder_page_cmd,
+            crate::validate_files_cmd,
+            crate::validate_dropped_paths_cmd,
+            crate::plan_merge_cmd,
+            crate::export_order_cmd,
+            crate::import_order_cmd,
+            crate::get_thumbnail_cmd,
+            crate::merge_invoices_cmd,
+            crate::preview_merge_cmd,
+            crate::commit_merge_cmd,
+            crate::cancel_merge_cmd,
+            crate::provide_pdf_password_cmd,
+            crate::clean_temp_cmd
+        ])
+        .build()
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use crate::{ConflictAction, MergeError, MergeRequest};
+
+/// 强制输出目录的环境变量，优先于策略文件。
+pub const ENV_OUTPUT_DIR: &str = "INVOICEMERGE_FORCED_OUTPUT_DIR";
+/// 逗号分隔的禁用项，与策略文件中的 `forbidden` 合并。
+pub const ENV_FORBIDDEN: &str = "INVOICEMERGE_FORBIDDEN";
+
+/// 可被禁用的功能名称。
+pub const OVERWRITE: &str = "overwrite";
+pub const SECONDARY_OUTPUT: &str = "secondary_output";
+pub const POST_MERGE_HOOK: &str = "post_merge_hook";
+pub const WEBHOOK: &str = "webhook";
+pub const HTTP_API: &str = "http_api";
+pub const CONTEXT_MENU: &str = "context_menu";
+
+static POLICY: OnceLock<Policy> = OnceLock::new();
+
+/// IT 通过机器级策略文件或环境变量下发的锁定设置，用户配置与请求都不能绕过。
+/// 只在启动时读取一次，修改后需重启程序。
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Policy {
+    /// 所有合并结果都写到该目录
+    pub forced_output_dir: Option<PathBuf>,
+    /// 禁用的功能，如 `overwrite`、`webhook`、`http_api`
+    pub forbidden: Vec<String>,
+}
+
+impl Policy {
+    pub fn forbids(&self, feature: &str) -> bool {
+        self.forbidden
+            .iter()
+            .any(|item| item.eq_ignore_ascii_case(feature))
+    }
+
+    pub fn ensure_allowed(&self, feature: &str) -> Result<(), MergeError> {
+        if self.forbids(feature) {
+            Err(MergeError::PolicyForbidden(feature.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// 检查请求是否用到了被禁用的功能。
+    pub fn check_request(&self, req: &MergeRequest) -> Result<(), MergeError> {
+        if req.on_conflict == Some(ConflictAction::Overwrite) {
+            self.ensure_allowed(OVERWRITE)?;
+        }
+        if req
+            .secondary_output_dir
+            .as_deref()
+            .is_some_and(|dir| !dir.trim().is_empty())
+        {
+            self.ensure_allowed(SECONDARY_OUTPUT)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn get() -> &'static Policy {
+    POLICY.get_or_init(load)
+}
+
+/// 机器级策略文件位置：Windows 为 `%ProgramData%\InvoiceMerge\policy.toml`。
+pub fn policy_path() -> PathBuf {
+    if cfg!(windows) {
+        let program_data = std::env::var_os("ProgramData").unwrap_or_else(|| r"C:\ProgramData".into());
+        Path::new(&program_data).join("InvoiceMerge").join("policy.toml")
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from("/Library/Application Support/InvoiceMerge/policy.toml")
+    } else {
+        PathBuf::from("/etc/invoicemerge/policy.toml")
+    }
+}
+
+fn load() -> Policy {
+    // 策略文件损坏时按空策略处理，但环境变量仍然生效
+    let mut policy: Policy = fs::read_to_string(policy_path())
+        .ok()
+        .and_then(|text| toml::from_str(&text).ok())
+        .unwrap_or_default();
+
+    if let Some(dir) = std::env::var_os(ENV_OUTPUT_DIR).filter(|dir| !dir.is_empty()) {
+        policy.forced_output_dir = Some(PathBuf::from(dir));
+    }
+    if let Ok(items) = std::env::var(ENV_FORBIDDEN) {
+        policy.forbidden.extend(
+            items
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string),
+        );
+    }
+    policy
+}
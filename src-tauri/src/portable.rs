@@ -0,0 +1,45 @@
+use std::{path::PathBuf, sync::OnceLock};
+use tauri::AppHandle;
+
+/// 与可执行文件放在同一目录下的标记文件，存在时启用便携模式。
+pub const MARKER_FILE: &str = "portable.ini";
+/// 便携模式下所有数据所在的目录（位于可执行文件旁）。
+const DATA_DIR: &str = "data";
+
+static PORTABLE_ROOT: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy)]
+pub enum AppDir {
+    Config,
+    Cache,
+    Log,
+}
+
+/// 便携模式的数据根目录；非便携模式返回 `None`。启动后结果不变。
+pub fn root() -> Option<&'static PathBuf> {
+    PORTABLE_ROOT
+        .get_or_init(|| {
+            let exe = std::env::current_exe().ok()?;
+            let dir = exe.parent()?;
+            dir.join(MARKER_FILE).is_file().then(|| dir.join(DATA_DIR))
+        })
+        .as_ref()
+}
+
+/// 配置、缓存、日志目录：便携模式下位于可执行文件旁的 `data/`，否则使用系统的应用目录。
+pub fn app_dir(app: &AppHandle, kind: AppDir) -> Option<PathBuf> {
+    if let Some(root) = root() {
+        let name = match kind {
+            AppDir::Config => "config",
+            AppDir::Cache => "cache",
+            AppDir::Log => "logs",
+        };
+        return Some(root.join(name));
+    }
+    let resolver = app.path_resolver();
+    match kind {
+        AppDir::Config => resolver.app_config_dir(),
+        AppDir::Cache => resolver.app_cache_dir(),
+        AppDir::Log => resolver.app_log_dir(),
+    }
+}
@@ -0,0 +1,65 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::{MergeError, MergeRequest, MergeResult};
+
+pub(crate) const PREVIEW_PREFIX: &str = "mc-preview-";
+
+/// 已生成、等待确认的预览。确认时只认这里登记的预览文件与目标路径，不接受前端传来的任意路径。
+pub struct PendingPreview {
+    /// 合并时已按冲突处理解析过的最终路径；确认时若又被占用，会再解析一次
+    pub target: PathBuf,
+    pub req: MergeRequest,
+    pub result: MergeResult,
+}
+
+/// 以插件形式嵌入时也要可用，因此不放在托管状态里
+static PENDING: Mutex<BTreeMap<PathBuf, PendingPreview>> = Mutex::new(BTreeMap::new());
+
+/// 在工作目录中占一个预览文件名，合并结果先写到这里供前端查看。
+pub fn reserve_preview_path(work_dir: &Path) -> Result<PathBuf, MergeError> {
+    tempfile::Builder::new()
+        .prefix(PREVIEW_PREFIX)
+        .suffix(".pdf")
+        .tempfile_in(work_dir)?
+        .into_temp_path()
+        .keep()
+        .map_err(|err| MergeError::Io(err.error))
+}
+
+/// 登记一份预览，等待 `commit_merge_cmd` 确认或放弃。
+pub fn record(preview: PathBuf, pending: PendingPreview) {
+    if let Ok(mut map) = PENDING.lock() {
+        map.insert(preview, pending);
+    }
+}
+
+/// 取出登记的预览；未登记（路径被篡改、已确认过）时返回 `PreviewNotFound`。
+pub fn take(preview: &Path) -> Result<PendingPreview, MergeError> {
+    PENDING
+        .lock()
+        .ok()
+        .and_then(|mut map| map.remove(preview))
+        .filter(|_| preview.is_file())
+        .ok_or(MergeError::PreviewNotFound)
+}
+
+/// 放弃预览：删除文件。
+pub fn discard(preview: &Path) -> Result<(), MergeError> {
+    fs::remove_file(preview)?;
+    Ok(())
+}
+
+/// 确认预览：移动到已解析好冲突的最终路径。
+pub fn move_into_place(preview: &Path, target: &Path) -> Result<(), MergeError> {
+    if fs::rename(preview, target).is_err() {
+        // 工作目录与输出目录可能不在同一磁盘，退回复制后删除
+        fs::copy(preview, target)?;
+        fs::remove_file(preview)?;
+    }
+    Ok(())
+}
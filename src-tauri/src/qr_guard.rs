@@ -0,0 +1,30 @@
+use image::DynamicImage;
+
+/// 缩小后每个二维码模块至少保留的像素数，低于此值手机基本无法识别
+const MIN_PIXELS_PER_MODULE: f64 = 3.0;
+
+/// 图片中若有可识别的二维码（如发票查验码），返回缩放时允许的最小比例，
+/// 保证缩小后每个模块仍不少于 `MIN_PIXELS_PER_MODULE` 像素。没有二维码时返回 `None`。
+pub fn min_scale_for_qr(image: &DynamicImage) -> Option<f64> {
+    let gray = image.to_luma8();
+    let mut prepared =
+        rqrr::PreparedImage::prepare_from_greyscale(gray.width() as usize, gray.height() as usize, |x, y| {
+            gray.get_pixel(x as u32, y as u32).0[0]
+        });
+
+    prepared
+        .detect_grids()
+        .iter()
+        .filter_map(|grid| {
+            let (meta, _) = grid.decode().ok()?;
+            let modules = (17 + 4 * meta.version.0) as f64;
+            let [a, b, _, d] = grid.bounds;
+            let side = distance(a, b).min(distance(a, d));
+            (side > 0.0).then(|| MIN_PIXELS_PER_MODULE * modules / side)
+        })
+        .reduce(f64::max)
+}
+
+fn distance(a: rqrr::Point, b: rqrr::Point) -> f64 {
+    (((a.x - b.x) as f64).powi(2) + ((a.y - b.y) as f64).powi(2)).sqrt()
+}
@@ -0,0 +1,71 @@
+use image::{DynamicImage, RgbaImage};
+use pdfium_render::prelude::*;
+use std::{env, path::Path};
+
+use crate::MergeError;
+
+/// 优先加载可执行文件所在目录下随包分发的 Pdfium 动态库，其次查找系统库。
+/// 不按当前工作目录查找：从快捷方式或深层链接启动时工作目录并不固定，且可能被放入同名的恶意库。
+/// 动态 XFA 表单需要启用 XFA 的 Pdfium 构建才能渲染出真实内容。
+pub fn bind_pdfium() -> Result<Pdfium, MergeError> {
+    let bundled = env::current_exe().ok().and_then(|exe| {
+        exe.parent()
+            .map(|dir| Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path(dir)))
+    });
+    let bindings = match bundled {
+        Some(Ok(bindings)) => Ok(bindings),
+        _ => Pdfium::bind_to_system_library(),
+    }
+    .map_err(|err| MergeError::Pdf(format!("未找到 Pdfium 库: {err}")))?;
+    Ok(Pdfium::new(bindings))
+}
+
+/// 渲染 PDF 第一页，长边不超过 `max_side` 像素。
+pub fn render_first_page(path: &Path, max_side: u32) -> Result<DynamicImage, MergeError> {
+    let pdfium = bind_pdfium()?;
+    let document = pdfium
+        .load_pdf_from_file(path, None)
+        .map_err(|err| MergeError::Pdf(err.to_string()))?;
+    let page = document
+        .pages()
+        .first()
+        .map_err(|_| MergeError::Pdf("文档没有页面".into()))?;
+    let config = PdfRenderConfig::new()
+        .set_maximum_width(max_side as i32)
+        .set_maximum_height(max_side as i32);
+    let bitmap = page
+        .render_with_config(&config)
+        .map_err(|err| MergeError::Pdf(err.to_string()))?;
+    let rgba = RgbaImage::from_raw(
+        bitmap.width() as u32,
+        bitmap.height() as u32,
+        bitmap.as_rgba_bytes(),
+    )
+    .ok_or_else(|| MergeError::Image("无法生成页面位图".into()))?;
+    Ok(DynamicImage::ImageRgba8(rgba))
+}
+
+/// 将 PDF 的每一页按指定 DPI 渲染为位图。
+pub fn rasterize_pdf(path: &Path, dpi: f64) -> Result<Vec<DynamicImage>, MergeError> {
+    let pdfium = bind_pdfium()?;
+    let document = pdfium
+        .load_pdf_from_file(path, None)
+        .map_err(|err| MergeError::Pdf(err.to_string()))?;
+
+    let mut images = Vec::new();
+    for page in document.pages().iter() {
+        let target_width = (page.width().value as f64 / 72.0 * dpi).round() as i32;
+        let config = PdfRenderConfig::new().set_target_width(target_width.max(1));
+        let bitmap = page
+            .render_with_config(&config)
+            .map_err(|err| MergeError::Pdf(err.to_string()))?;
+        let rgba = RgbaImage::from_raw(
+            bitmap.width() as u32,
+            bitmap.height() as u32,
+            bitmap.as_rgba_bytes(),
+        )
+        .ok_or_else(|| MergeError::Image("无法生成页面位图".into()))?;
+        images.push(DynamicImage::ImageRgba8(rgba));
+    }
+    Ok(images)
+}
@@ -0,0 +1,52 @@
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use crate::MergeError;
+
+/// 把合并结果复制到第二个目录（如网络归档共享），先写 `.part` 再改名，
+/// 并比对两端的 SHA-256，确认副本完整。目标已存在同名文件时改用 `name (1).pdf`。
+pub fn copy_verified(source: &Path, dir: &Path) -> Result<PathBuf, MergeError> {
+    let file_name = source.file_name().ok_or(MergeError::InvalidOutputName)?;
+    fs::create_dir_all(dir)
+        .map_err(|err| MergeError::OutputNotWritable(format!("{}: {err}", dir.display())))?;
+
+    let mut target = dir.join(file_name);
+    if target.exists() {
+        target = crate::unique_output_path(&target);
+    }
+    let partial = target.with_extension("pdf.part");
+    fs::copy(source, &partial)?;
+    fs::File::open(&partial)?.sync_all()?;
+
+    if file_sha256(source)? != file_sha256(&partial)? {
+        let _ = fs::remove_file(&partial);
+        return Err(MergeError::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("副本校验失败: {}", target.display()),
+        )));
+    }
+    fs::rename(&partial, &target)?;
+    Ok(target)
+}
+
+pub(crate) fn file_sha256(path: &Path) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
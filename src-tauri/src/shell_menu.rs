@@ -0,0 +1,103 @@
+use std::{path::PathBuf, sync::Mutex};
+
+use crate::MergeError;
+
+/// 资源管理器中文件夹右键菜单的显示文字。
+pub const MENU_LABEL: &str = "用发票合并工具合并";
+
+/// 启动参数中用于预载文件夹的开关，右键菜单以 `--folder "%1"` 启动本程序。
+pub const FOLDER_ARG: &str = "--folder";
+
+#[cfg(windows)]
+const MENU_KEY: &str = r"HKCU\Software\Classes\Directory\shell\InvoiceMerge";
+
+/// 从命令行参数中取出 `--folder <路径>` 或 `--folder=<路径>`，仅接受存在的目录。
+pub fn folder_from_args<I>(args: I) -> Option<PathBuf>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let value = if arg == FOLDER_ARG {
+            args.next()
+        } else {
+            arg.strip_prefix(FOLDER_ARG)
+                .and_then(|rest| rest.strip_prefix('='))
+                .map(str::to_string)
+        };
+        if let Some(value) = value {
+            let path = PathBuf::from(value.trim_matches('"'));
+            return path.is_dir().then_some(path);
+        }
+    }
+    None
+}
+
+/// 右键菜单是否已注册（仅检查当前用户的注册表项）。
+#[cfg(windows)]
+pub fn is_registered() -> bool {
+    use std::process::Command;
+
+    let mut command = Command::new("reg");
+    command.args(["query", MENU_KEY]);
+    crate::office::hide_console_window(&mut command);
+    command
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+#[cfg(not(windows))]
+pub fn is_registered() -> bool {
+    false
+}
+
+/// 在 HKCU 下注册或移除文件夹右键菜单，无需管理员权限。
+#[cfg(windows)]
+pub fn set_registered(enabled: bool) -> Result<(), MergeError> {
+    let exe = std::env::current_exe()?;
+    if enabled {
+        let command_line = format!("\"{}\" {FOLDER_ARG} \"%1\"", exe.display());
+        run_reg(&["add", MENU_KEY, "/ve", "/d", MENU_LABEL, "/f"])?;
+        run_reg(&["add", MENU_KEY, "/v", "Icon", "/d", &exe.to_string_lossy(), "/f"])?;
+        run_reg(&[
+            "add",
+            &format!(r"{MENU_KEY}\command"),
+            "/ve",
+            "/d",
+            &command_line,
+            "/f",
+        ])
+    } else if is_registered() {
+        run_reg(&["delete", MENU_KEY, "/f"])
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(not(windows))]
+pub fn set_registered(_enabled: bool) -> Result<(), MergeError> {
+    Err(MergeError::Unsupported("右键菜单集成仅支持 Windows".into()))
+}
+
+#[cfg(windows)]
+pub(crate) fn run_reg(args: &[&str]) -> Result<(), MergeError> {
+    use std::process::Command;
+
+    let mut command = Command::new("reg");
+    command.args(args);
+    crate::office::hide_console_window(&mut command);
+    let output = command.output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(MergeError::Io(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        )))
+    }
+}
+
+/// 启动时通过命令行传入的文件夹，前端加载后取走一次。
+#[derive(Default)]
+pub struct LaunchFolder(pub Mutex<Option<PathBuf>>);
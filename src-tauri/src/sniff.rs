@@ -0,0 +1,46 @@
+use std::{fs::File, io::Read, path::Path};
+
+/// 读取文件头部的魔数，返回对应的标准扩展名；无法识别时返回 `None`。
+/// Office 文档（zip / OLE 容器）无法仅凭文件头区分具体格式，不在识别范围内。
+pub fn sniff_extension(path: &Path) -> Option<&'static str> {
+    let mut head = [0u8; 1024];
+    let mut file = File::open(path).ok()?;
+    let len = file.read(&mut head).ok()?;
+    sniff_bytes(&head[..len])
+}
+
+fn sniff_bytes(head: &[u8]) -> Option<&'static str> {
+    if head.starts_with(b"\xFF\xD8\xFF") {
+        Some("jpg")
+    } else if head.starts_with(b"\x89PNG\r\n\x1A\n") {
+        Some("png")
+    } else if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+        Some("gif")
+    } else if head.starts_with(b"BM") {
+        Some("bmp")
+    } else if head.starts_with(b"II*\0") || head.starts_with(b"MM\0*") {
+        Some("tiff")
+    } else if head.len() >= 12 && &head[..4] == b"RIFF" && &head[8..12] == b"WEBP" {
+        Some("webp")
+    } else if head.len() >= 12
+        && &head[4..8] == b"ftyp"
+        && matches!(&head[8..12], b"heic" | b"heix" | b"mif1" | b"msf1" | b"hevc")
+    {
+        Some("heic")
+    } else if contains(head, b"%PDF-") {
+        // 部分生成器会在 %PDF 之前写入少量垃圾字节，规范允许在前 1024 字节内出现
+        Some("pdf")
+    } else if head
+        .strip_prefix(b"\xEF\xBB\xBF")
+        .unwrap_or(head)
+        .starts_with(b"<?xml")
+    {
+        Some("xml")
+    } else {
+        None
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
@@ -0,0 +1,417 @@
+use lopdf::{Document, Object, ObjectId};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    mmap_pdf, outline,
+    page_map::{PageRange, PageSourceKind},
+    pdf_writer, unique_output_path, MergeError,
+};
+
+/// 估算体积时为每个对象计入的字典、交叉引用等开销（字节）
+const OBJECT_OVERHEAD: u64 = 64;
+
+/// 按大小或文件数把输出拆成多份，如报销系统限制单个附件不超过 20 MB。
+/// 只在文件之间拆开，同一份源文件的页面总在同一份输出中。
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct SplitOptions {
+    /// 每份输出的大小上限（MB）
+    pub max_mb: Option<f64>,
+    /// 每份输出最多包含的文件数
+    pub max_files: Option<usize>,
+}
+
+impl SplitOptions {
+    fn max_bytes(&self) -> Option<u64> {
+        self.max_mb
+            .filter(|mb| *mb > 0.0)
+            .map(|mb| (mb * 1024.0 * 1024.0) as u64)
+    }
+}
+
+/// 拆分结果：各份的路径，以及单个文件就已超出大小上限、无法再拆的文件名
+pub struct SplitOutput {
+    pub paths: Vec<PathBuf>,
+    pub oversized: Vec<String>,
+    /// 有分卷的目标路径原本已存在（已按 `overwrite` 覆盖或改名）
+    pub existing: bool,
+}
+
+/// 输出不超过限制时原样返回；否则写出 `名称_part1.pdf`、`名称_part2.pdf`……并删除合并后的整份文件。
+/// 每份重新生成书签，页码映射中的每一段（源文件、封面、分隔页等）不会被拆开。
+/// 分卷已存在时，除非 `overwrite`，改用 `名称_part1 (1).pdf` 这样不冲突的名称。
+pub fn split_output(
+    path: &Path,
+    page_map: &[PageRange],
+    options: &SplitOptions,
+    overwrite: bool,
+) -> Result<SplitOutput, MergeError> {
+    let unchanged = SplitOutput {
+        paths: vec![path.to_path_buf()],
+        oversized: Vec::new(),
+        existing: false,
+    };
+    let max_bytes = options.max_bytes();
+    let max_files = options.max_files.filter(|count| *count > 0);
+    if page_map.len() < 2 || (max_bytes.is_none() && max_files.is_none()) {
+        return Ok(unchanged);
+    }
+    if max_files.is_none()
+        && max_bytes.is_some_and(|limit| fs::metadata(path).map_or(0, |meta| meta.len()) <= limit)
+    {
+        return Ok(unchanged);
+    }
+
+    let doc = mmap_pdf::load(path)?;
+    let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let range_bytes: Vec<u64> = page_map
+        .iter()
+        .map(|range| estimate_bytes(&doc, &pages[range.start_page - 1..range.end_page]))
+        .collect();
+
+    let (groups, oversized) = group_ranges(page_map, &range_bytes, max_bytes, max_files);
+    let oversized: Vec<String> = oversized
+        .into_iter()
+        .map(|index| page_map[index].file_name.clone())
+        .collect();
+    if groups.len() < 2 {
+        return Ok(SplitOutput {
+            oversized,
+            ..unchanged
+        });
+    }
+
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let parent = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut paths = Vec::with_capacity(groups.len());
+    let mut existing = false;
+    for (part, group) in groups.iter().enumerate() {
+        let mut part_path = parent.join(format!("{stem}_part{}.pdf", part + 1));
+        if part_path.exists() {
+            existing = true;
+            if !overwrite {
+                part_path = unique_output_path(&part_path);
+            }
+        }
+        write_part(&doc, &pages, page_map, group, &part_path)?;
+        paths.push(part_path);
+    }
+    fs::remove_file(path)?;
+    Ok(SplitOutput {
+        paths,
+        oversized,
+        existing,
+    })
+}
+
+/// 按顺序装入各段，放不下时另起一份。返回每份包含的段下标，以及单独就超出大小上限的段下标。
+fn group_ranges(
+    page_map: &[PageRange],
+    range_bytes: &[u64],
+    max_bytes: Option<u64>,
+    max_files: Option<usize>,
+) -> (Vec<Vec<usize>>, Vec<usize>) {
+    let mut groups: Vec<Vec<usize>> = vec![Vec::new()];
+    let mut group_bytes = 0;
+    let mut group_files = 0;
+    let mut oversized = Vec::new();
+    for (index, range) in page_map.iter().enumerate() {
+        let counts_as_file = is_file(range);
+        let current = groups.last().expect("至少有一组");
+        let over_count = counts_as_file && max_files.is_some_and(|max| group_files >= max);
+        let over_size = max_bytes.is_some_and(|limit| group_bytes + range_bytes[index] > limit);
+        if !current.is_empty() && (over_count || over_size) {
+            groups.push(Vec::new());
+            group_bytes = 0;
+            group_files = 0;
+        }
+        if max_bytes.is_some_and(|limit| range_bytes[index] > limit) {
+            oversized.push(index);
+        }
+        groups.last_mut().expect("至少有一组").push(index);
+        group_bytes += range_bytes[index];
+        group_files += usize::from(counts_as_file);
+    }
+    (groups, oversized)
+}
+
+/// 计入文件数的段：源文件与失败占位页；封面、插入页、失败清单不算
+fn is_file(range: &PageRange) -> bool {
+    matches!(
+        range.kind,
+        PageSourceKind::Source | PageSourceKind::Placeholder | PageSourceKind::Existing
+    )
+}
+
+fn write_part(
+    doc: &Document,
+    pages: &[ObjectId],
+    page_map: &[PageRange],
+    group: &[usize],
+    path: &Path,
+) -> Result<(), MergeError> {
+    let keep: HashSet<u32> = group
+        .iter()
+        .flat_map(|&index| page_map[index].start_page as u32..=page_map[index].end_page as u32)
+        .collect();
+    let mut part = doc.clone();
+    let remove: Vec<u32> = (1..=pages.len() as u32)
+        .filter(|page| !keep.contains(page))
+        .collect();
+    part.delete_pages(&remove);
+
+    // 原书签指向被删掉的页面，按本份包含的段重新生成
+    let catalog_id = part
+        .trailer
+        .get(b"Root")
+        .and_then(Object::as_reference)
+        .map_err(|err| MergeError::Pdf(err.to_string()))?;
+    let part_pages: Vec<ObjectId> = part.get_pages().into_values().collect();
+    let mut next = 0;
+    let bookmarks: Vec<(ObjectId, String)> = group
+        .iter()
+        .filter_map(|&index| {
+            let range = &page_map[index];
+            if range.page_count() == 0 {
+                return None;
+            }
+            let page_id = *part_pages.get(next)?;
+            next += range.page_count();
+            Some((page_id, range.file_name.clone()))
+        })
+        .collect();
+    if let Ok(catalog) = part.get_object_mut(catalog_id).and_then(Object::as_dict_mut) {
+        catalog.remove(b"Outlines");
+    }
+    outline::add_outline(&mut part, catalog_id, &bookmarks)?;
+    part.prune_objects();
+
+    let mut writer = BufWriter::new(fs::File::create(path)?);
+    pdf_writer::save_compact(&part, &mut writer, |_, _| {})?;
+    Ok(())
+}
+
+/// 估算若干页单独保存时的大小：页面可达的全部对象（不经 /Parent 回到页树），共享对象只计一次。
+fn estimate_bytes(doc: &Document, pages: &[ObjectId]) -> u64 {
+    let mut seen = HashSet::new();
+    let mut pending: Vec<ObjectId> = pages.to_vec();
+    let mut total = 0;
+    while let Some(id) = pending.pop() {
+        if !seen.insert(id) {
+            continue;
+        }
+        let Ok(object) = doc.get_object(id) else {
+            continue;
+        };
+        total += OBJECT_OVERHEAD;
+        collect_references(object, &mut pending, &mut total);
+    }
+    total
+}
+
+fn collect_references(object: &Object, pending: &mut Vec<ObjectId>, total: &mut u64) {
+    match object {
+        Object::Reference(id) => pending.push(*id),
+        Object::Array(items) => {
+            for item in items {
+                collect_references(item, pending, total);
+            }
+        }
+        Object::Dictionary(dict) => {
+            for (key, value) in dict.iter() {
+                if key != b"Parent" {
+                    collect_references(value, pending, total);
+                }
+            }
+        }
+        Object::Stream(stream) => {
+            *total += stream.content.len() as u64;
+            for (key, value) in stream.dict.iter() {
+                if key != b"Parent" {
+                    collect_references(value, pending, total);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, Dictionary, Stream};
+
+    fn range(start_page: usize, end_page: usize, file_name: &str, kind: PageSourceKind) -> PageRange {
+        PageRange {
+            start_page,
+            end_page,
+            file_name: file_name.to_string(),
+            path: String::new(),
+            kind,
+        }
+    }
+
+    /// 每段一页的源文件
+    fn sources(count: usize) -> Vec<PageRange> {
+        (1..=count)
+            .map(|page| range(page, page, &format!("{page}.pdf"), PageSourceKind::Source))
+            .collect()
+    }
+
+    fn sample_pdf(dir: &Path, pages: usize) -> PathBuf {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let kids: Vec<Object> = (0..pages)
+            .map(|index| {
+                let content = doc.add_object(Stream::new(
+                    Dictionary::new(),
+                    format!("% page {index}").into_bytes(),
+                ));
+                doc.add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+                    "Contents" => content,
+                })
+                .into()
+            })
+            .collect();
+        doc.objects.insert(
+            pages_id,
+            dictionary! { "Type" => "Pages", "Kids" => kids, "Count" => pages as i64 }.into(),
+        );
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+        let path = dir.join("merged.pdf");
+        doc.save(&path).unwrap();
+        path
+    }
+
+    fn page_count(path: &Path) -> usize {
+        Document::load(path).unwrap().get_pages().len()
+    }
+
+    #[test]
+    fn groups_by_file_count() {
+        let mut page_map = vec![range(1, 1, "封面", PageSourceKind::Cover)];
+        page_map.extend(sources(5).into_iter().map(|mut range| {
+            range.start_page += 1;
+            range.end_page += 1;
+            range
+        }));
+        // 封面不计入文件数
+        let (groups, oversized) = group_ranges(&page_map, &[1; 6], None, Some(2));
+        assert_eq!(groups, [vec![0, 1, 2], vec![3, 4], vec![5]]);
+        assert!(oversized.is_empty());
+
+        let (groups, _) = group_ranges(&page_map, &[1; 6], None, Some(10));
+        assert_eq!(groups, [vec![0, 1, 2, 3, 4, 5]]);
+    }
+
+    #[test]
+    fn groups_by_size() {
+        let page_map = sources(5);
+        let (groups, oversized) = group_ranges(&page_map, &[40, 40, 40, 100, 10], Some(90), None);
+        assert_eq!(groups, [vec![0, 1], vec![2], vec![3], vec![4]]);
+        // 单个文件超出上限时单独成份
+        assert_eq!(oversized, [3]);
+
+        // 恰好等于上限时仍放在同一份
+        let (groups, _) = group_ranges(&page_map, &[45, 45, 45, 45, 45], Some(90), None);
+        assert_eq!(groups, [vec![0, 1], vec![2, 3], vec![4]]);
+    }
+
+    #[test]
+    fn groups_by_size_and_count() {
+        let page_map = sources(4);
+        let (groups, _) = group_ranges(&page_map, &[10, 10, 80, 10], Some(90), Some(2));
+        assert_eq!(groups, [vec![0, 1], vec![2, 3]]);
+        let (groups, _) = group_ranges(&page_map, &[10, 10, 75, 10], Some(90), Some(3));
+        assert_eq!(groups, [vec![0, 1], vec![2, 3]]);
+    }
+
+    #[test]
+    fn unsplit_when_disabled_or_single_range() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = sample_pdf(dir.path(), 3);
+        for options in [
+            SplitOptions::default(),
+            SplitOptions {
+                max_mb: Some(0.0),
+                max_files: Some(0),
+            },
+            SplitOptions {
+                max_mb: Some(-1.0),
+                max_files: None,
+            },
+        ] {
+            let output = split_output(&path, &sources(3), &options, false).unwrap();
+            assert_eq!(output.paths, [path.clone()]);
+        }
+        let single = [range(1, 3, "a.pdf", PageSourceKind::Source)];
+        let options = SplitOptions {
+            max_mb: None,
+            max_files: Some(1),
+        };
+        let output = split_output(&path, &single, &options, false).unwrap();
+        assert_eq!(output.paths, [path.clone()]);
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn writes_parts_without_splitting_a_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = sample_pdf(dir.path(), 5);
+        let page_map = [
+            range(1, 2, "a.pdf", PageSourceKind::Source),
+            range(3, 3, "b.jpg", PageSourceKind::Source),
+            range(4, 5, "c.pdf", PageSourceKind::Source),
+        ];
+        let options = SplitOptions {
+            max_mb: None,
+            max_files: Some(1),
+        };
+        let output = split_output(&path, &page_map, &options, false).unwrap();
+        let expected: Vec<PathBuf> = (1..=3)
+            .map(|part| dir.path().join(format!("merged_part{part}.pdf")))
+            .collect();
+        assert_eq!(output.paths, expected);
+        assert!(!output.existing);
+        assert!(!path.exists(), "合并后的整份文件应被删除");
+        let counts: Vec<usize> = output.paths.iter().map(|part| page_count(part)).collect();
+        assert_eq!(counts, [2, 1, 2]);
+    }
+
+    #[test]
+    fn existing_parts_are_renamed_unless_overwriting() {
+        let page_map = sources(2);
+        let options = SplitOptions {
+            max_mb: None,
+            max_files: Some(1),
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().join("merged_part1.pdf");
+        fs::write(&existing, b"keep").unwrap();
+        let path = sample_pdf(dir.path(), 2);
+        let output = split_output(&path, &page_map, &options, false).unwrap();
+        assert!(output.existing);
+        assert_eq!(output.paths[0], dir.path().join("merged_part1 (1).pdf"));
+        assert_eq!(fs::read(&existing).unwrap(), b"keep");
+
+        let path = sample_pdf(dir.path(), 2);
+        let output = split_output(&path, &page_map, &options, true).unwrap();
+        assert!(output.existing);
+        assert_eq!(output.paths[0], existing);
+        assert_eq!(page_count(&existing), 1);
+    }
+}
@@ -0,0 +1,319 @@
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use printpdf::{Mm, PdfDocument};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+use tempfile::TempPath;
+
+use crate::{
+    compress::resolve,
+    mmap_pdf,
+    page_fit::{page_rotation, page_visible_box, save_temp_document, wrap_page_contents},
+    page_map::{PageRange, PageSourceKind},
+    pdf_writer,
+    text_page::{load_text_font, text_width_mm, to_lopdf},
+    MergeError,
+};
+
+pub const TEMP_PREFIX: &str = "mc-stamp-";
+/// 同一页可能先后盖附件编号和 Bates 编号，名称后缀用表单对象号区分
+const XOBJECT_PREFIX: &str = "MCStamp";
+const PT_TO_MM: f64 = 25.4 / 72.0;
+const MARGIN_MM: f64 = 8.0;
+
+/// 在每份源文件的首页右上角盖“附件 1”“附件 2”……，与报销单上的附件编号对应。
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct AttachmentStamp {
+    pub prefix: String,
+    pub start: u32,
+    /// 字号（pt）
+    pub font_size: f64,
+}
+
+impl Default for AttachmentStamp {
+    fn default() -> Self {
+        Self {
+            prefix: "附件 ".to_string(),
+            start: 1,
+            font_size: 12.0,
+        }
+    }
+}
+
+impl AttachmentStamp {
+    /// 第 `offset` 份（从 0 开始）盖章文件的编号文字
+    pub fn label(&self, offset: usize) -> String {
+        format!("{}{}", self.prefix, self.start as usize + offset)
+    }
+}
+
+/// 在输出的每一页底部注明来源文件和页码，如“午餐小票.jpg — 3/57”，打印后散落的纸张也能对上来源。
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct SourceFooter {
+    /// 字号（pt）
+    pub font_size: f64,
+    pub position: StampPosition,
+}
+
+impl Default for SourceFooter {
+    fn default() -> Self {
+        Self {
+            font_size: 7.0,
+            position: StampPosition::BottomCenter,
+        }
+    }
+}
+
+impl SourceFooter {
+    /// 第 `page` 页（从 1 开始，共 `total` 页）的页脚文字
+    pub fn label(&self, file_name: &str, page: usize, total: usize) -> String {
+        format!("{file_name} — {page}/{total}")
+    }
+
+    /// 按最终的页码映射给已写出的输出加盖页脚，需在插入摘要页等改变页码的步骤之后调用。
+    /// 摘要页和追加模式下原有的页面不加盖。
+    pub fn apply(&self, path: &Path, page_map: &[PageRange]) -> Result<(), MergeError> {
+        let mut doc = mmap_pdf::load(path)?;
+        let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+        let total = pages.len();
+        let labels: Vec<(ObjectId, String)> = page_map
+            .iter()
+            .filter(|range| !matches!(range.kind, PageSourceKind::Existing | PageSourceKind::Summary))
+            .flat_map(|range| (range.start_page..=range.end_page).map(move |page| (range, page)))
+            .filter_map(|(range, page)| {
+                let page_id = *pages.get(page.checked_sub(1)?)?;
+                Some((page_id, self.label(&range.file_name, page, total)))
+            })
+            .collect();
+        if labels.is_empty() {
+            return Ok(());
+        }
+        stamp_pages(&mut doc, &labels, self.font_size, self.position)?;
+
+        // 先写到同目录的临时文件再替换，中途失败不会留下半份输出
+        let parent = path.parent().unwrap_or_else(|| Path::new("."));
+        let temp_file = tempfile::NamedTempFile::new_in(parent)?;
+        {
+            let mut writer = BufWriter::new(temp_file.as_file());
+            pdf_writer::save_compact(&doc, &mut writer, |_, _| {})?;
+        }
+        temp_file.persist(path).map_err(|err| err.error)?;
+        Ok(())
+    }
+}
+
+/// 文字在页面上的位置（按页面显示方向）
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum StampPosition {
+    TopLeft,
+    #[default]
+    TopRight,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+}
+
+/// 复制一份 PDF 并在首页加盖文字，原文件（可能是转换缓存）保持不变。
+pub fn stamp_first_page(
+    path: &Path,
+    text: &str,
+    font_size: f64,
+    work_dir: &Path,
+) -> Result<(PathBuf, TempPath), MergeError> {
+    let mut doc = Document::load(path).map_err(|err| MergeError::Pdf(err.to_string()))?;
+    if doc.is_encrypted() {
+        let _ = doc.decrypt(b"");
+    }
+    let page_id = *doc
+        .get_pages()
+        .values()
+        .next()
+        .ok_or_else(|| MergeError::Pdf("文档没有页面".into()))?;
+    stamp_pages(
+        &mut doc,
+        &[(page_id, text.to_string())],
+        font_size,
+        StampPosition::TopRight,
+    )?;
+    save_temp_document(&mut doc, TEMP_PREFIX, work_dir)
+}
+
+/// 在多个页面上各盖一段文字。所有文字排在同一份 printpdf 文档里，字体只嵌入一次。
+/// 带 /Rotate 的页面按显示方向定位，保证文字总在看到的位置且不倒置。
+pub fn stamp_pages(
+    doc: &mut Document,
+    labels: &[(ObjectId, String)],
+    font_size: f64,
+    position: StampPosition,
+) -> Result<(), MergeError> {
+    let mut geometry = Vec::with_capacity(labels.len());
+    for (page_id, _) in labels {
+        let [x0, y0, x1, y1] =
+            page_visible_box(doc, *page_id).ok_or_else(|| MergeError::Pdf("无法读取页面尺寸".into()))?;
+        geometry.push((
+            x0.min(x1),
+            y0.min(y1),
+            (x1 - x0).abs(),
+            (y1 - y0).abs(),
+            page_rotation(doc, *page_id),
+        ));
+    }
+    let display: Vec<(f64, f64)> = geometry
+        .iter()
+        .map(|&(_, _, width, height, rotation)| {
+            if rotation % 180 == 0 {
+                (width, height)
+            } else {
+                (height, width)
+            }
+        })
+        .collect();
+    let texts: Vec<&str> = labels.iter().map(|(_, text)| text.as_str()).collect();
+    let forms = text_forms(doc, &texts, &display, font_size, position)?;
+
+    for (((page_id, _), form_id), (left, bottom, width, height, rotation)) in
+        labels.iter().zip(forms).zip(geometry)
+    {
+        let name = format!("{XOBJECT_PREFIX}{}", form_id.0);
+        add_xobject(doc, *page_id, &name, form_id)?;
+        // 把显示方向上的坐标映射回页面坐标
+        let [a, b, c, d, e, f] = match rotation {
+            90 => [0.0, 1.0, -1.0, 0.0, left + width, bottom],
+            180 => [-1.0, 0.0, 0.0, -1.0, left + width, bottom + height],
+            270 => [0.0, -1.0, 1.0, 0.0, left, bottom + height],
+            _ => [1.0, 0.0, 0.0, 1.0, left, bottom],
+        };
+        // 标为版面附加物（Artifact），带标签的 PDF 中读屏软件会跳过
+        let suffix = format!("\nQ\n/Artifact BMC q {a} {b} {c} {d} {e:.3} {f:.3} cm /{name} Do Q EMC\n");
+        wrap_page_contents(doc, *page_id, b"q\n".to_vec(), suffix.into_bytes())?;
+    }
+    Ok(())
+}
+
+/// 用 printpdf 排出只有文字的页面（复用中文字体的查找与嵌入），再逐页导入为表单 XObject。
+fn text_forms(
+    doc: &mut Document,
+    texts: &[&str],
+    sizes_pt: &[(f64, f64)],
+    font_size: f64,
+    position: StampPosition,
+) -> Result<Vec<ObjectId>, MergeError> {
+    let Some(&(first_w, first_h)) = sizes_pt.first() else {
+        return Ok(Vec::new());
+    };
+    let (overlay, first_page, first_layer) =
+        PdfDocument::new("stamp", Mm(first_w * PT_TO_MM), Mm(first_h * PT_TO_MM), "Layer");
+    let font = load_text_font(&overlay)?;
+    for (index, (text, &(width_pt, height_pt))) in texts.iter().zip(sizes_pt).enumerate() {
+        let (width_mm, height_mm) = (width_pt * PT_TO_MM, height_pt * PT_TO_MM);
+        let (page, layer) = if index == 0 {
+            (first_page, first_layer)
+        } else {
+            overlay.add_page(Mm(width_mm), Mm(height_mm), "Layer")
+        };
+        let text_w = text_width_mm(text, font_size);
+        let x = match position {
+            StampPosition::TopLeft | StampPosition::BottomLeft => MARGIN_MM,
+            StampPosition::BottomCenter => (width_mm - text_w) / 2.0,
+            StampPosition::TopRight | StampPosition::BottomRight => width_mm - MARGIN_MM - text_w,
+        };
+        let y = match position {
+            StampPosition::TopLeft | StampPosition::TopRight => height_mm - MARGIN_MM - font_size * PT_TO_MM,
+            _ => MARGIN_MM,
+        };
+        overlay.get_page(page).get_layer(layer).use_text(
+            *text,
+            font_size,
+            Mm(x.max(0.0)),
+            Mm(y.max(0.0)),
+            &font,
+        );
+    }
+    let mut overlay = to_lopdf(overlay)?;
+    overlay.renumber_objects_with(doc.max_id + 1);
+    let mut parts = Vec::with_capacity(texts.len());
+    for overlay_page in overlay.get_pages().into_values() {
+        let content = overlay
+            .get_page_content(overlay_page)
+            .map_err(|err| MergeError::Pdf(err.to_string()))?;
+        let resources = overlay
+            .get_dictionary(overlay_page)
+            .and_then(|page| page.get(b"Resources"))
+            .cloned()
+            .unwrap_or_else(|_| Object::Dictionary(Dictionary::new()));
+        parts.push((content, resources));
+    }
+
+    doc.max_id = doc.max_id.max(overlay.max_id);
+    for (id, object) in overlay.objects {
+        if !matches!(object.type_name().unwrap_or(""), "Page" | "Pages" | "Catalog") {
+            doc.objects.insert(id, object);
+        }
+    }
+
+    Ok(parts
+        .into_iter()
+        .zip(sizes_pt)
+        .map(|((content, resources), &(width_pt, height_pt))| {
+            let mut form = Dictionary::new();
+            form.set("Type", Object::Name(b"XObject".to_vec()));
+            form.set("Subtype", Object::Name(b"Form".to_vec()));
+            form.set(
+                "BBox",
+                vec![
+                    0.into(),
+                    0.into(),
+                    Object::Real(width_pt as _),
+                    Object::Real(height_pt as _),
+                ],
+            );
+            form.set("Resources", resources);
+            doc.add_object(Stream::new(form, content))
+        })
+        .collect())
+}
+
+/// 把表单加入页面的 /XObject 资源。资源可能是引用或从父节点继承，统一复制为页面自己的字典。
+fn add_xobject(
+    doc: &mut Document,
+    page_id: ObjectId,
+    name: &str,
+    form_id: ObjectId,
+) -> Result<(), MergeError> {
+    let mut resources = inherited_resources(doc, page_id).unwrap_or_default();
+    let mut xobjects = resources
+        .get(b"XObject")
+        .ok()
+        .and_then(|obj| resolve(doc, obj))
+        .and_then(|obj| obj.as_dict().ok())
+        .cloned()
+        .unwrap_or_default();
+    xobjects.set(name, Object::Reference(form_id));
+    resources.set("XObject", Object::Dictionary(xobjects));
+    doc.get_object_mut(page_id)
+        .and_then(|obj| obj.as_dict_mut())
+        .map_err(|err| MergeError::Pdf(err.to_string()))?
+        .set("Resources", Object::Dictionary(resources));
+    Ok(())
+}
+
+fn inherited_resources(doc: &Document, page_id: ObjectId) -> Option<Dictionary> {
+    let mut current = doc.get_dictionary(page_id).ok()?;
+    loop {
+        if let Some(resources) = current
+            .get(b"Resources")
+            .ok()
+            .and_then(|obj| resolve(doc, obj))
+            .and_then(|obj| obj.as_dict().ok())
+        {
+            return Some(resources.clone());
+        }
+        let parent = current.get(b"Parent").ok()?.as_reference().ok()?;
+        current = doc.get_dictionary(parent).ok()?;
+    }
+}
@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    io::{BufRead, Write},
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use tauri::{AppHandle, Manager};
+
+use crate::{InvoiceFile, MergeRequest, MergeResult};
+
+/// 以该参数启动时不显示窗口，改为从 stdin 读取 JSON-RPC 请求、向 stdout 输出结果与进度。
+pub const SERVE_STDIO_ARG: &str = "--serve-stdio";
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// 扫描、合并本身失败（文件夹无效、没有文件等）
+const MERGE_FAILED: i64 = -32000;
+
+#[derive(Deserialize)]
+struct RpcRequest {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct FolderParams {
+    folder_path: String,
+    #[serde(default)]
+    extra_extensions: Vec<String>,
+}
+
+pub fn requested<I>(args: I) -> bool
+where
+    I: IntoIterator<Item = String>,
+{
+    args.into_iter().any(|arg| arg == SERVE_STDIO_ARG)
+}
+
+/// 在后台线程中逐行处理请求，stdin 关闭后退出程序。
+/// 支持的方法：`scan`、`validate`、`plan`、`merge`；合并期间的事件以同名通知转发。
+pub fn serve(app: AppHandle) {
+    ACTIVE.store(true, Ordering::Relaxed);
+    std::thread::spawn(move || {
+        let stdin = std::io::stdin();
+        for line in stdin.lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = match serde_json::from_str::<RpcRequest>(&line) {
+                Ok(request) => {
+                    let id = request.id.clone();
+                    match dispatch(&app, request) {
+                        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                        Err((code, message)) => error_response(id, code, message),
+                    }
+                }
+                Err(err) => {
+                    let code = if serde_json::from_str::<Value>(&line).is_ok() {
+                        INVALID_REQUEST
+                    } else {
+                        PARSE_ERROR
+                    };
+                    error_response(Value::Null, code, err.to_string())
+                }
+            };
+            write_line(&response);
+        }
+        app.exit(0);
+    });
+}
+
+/// 合并过程中的事件在 stdio 模式下同时作为 JSON-RPC 通知输出。
+pub fn forward<S: Serialize>(event: &str, payload: &S) {
+    if ACTIVE.load(Ordering::Relaxed) {
+        write_line(&json!({ "jsonrpc": "2.0", "method": event, "params": payload }));
+    }
+}
+
+fn dispatch(app: &AppHandle, request: RpcRequest) -> Result<Value, (i64, String)> {
+    match request.method.as_str() {
+        "scan" => {
+            let params: FolderParams = parse_params(request.params)?;
+            let files = scan_allowed(app, params)?;
+            to_value(&files)
+        }
+        "validate" => {
+            let params: FolderParams = parse_params(request.params)?;
+            let files = scan_allowed(app, params)?;
+            let size_limit = crate::validate::size_limit_bytes(crate::config::current(app).max_file_mb);
+            to_value(&crate::validate::validate_files(&files, size_limit))
+        }
+        "plan" => {
+            let req: MergeRequest = parse_params(request.params)?;
+            let config = crate::config::current(app);
+            to_value(&crate::plan::plan_merge(&config, req).map_err(merge_failed)?)
+        }
+        "merge" => {
+            let req: MergeRequest = parse_params(request.params)?;
+            let window = app
+                .get_window("main")
+                .ok_or_else(|| (MERGE_FAILED, "主窗口不可用".to_string()))?;
+            let result: MergeResult = crate::merge_invoices(&window, req, false).map_err(merge_failed)?;
+            to_value(&result)
+        }
+        other => Err((METHOD_NOT_FOUND, format!("未知方法 {other}"))),
+    }
+}
+
+/// 与界面的扫描命令一样，只扫描管理员允许列表内的文件夹。
+fn scan_allowed(app: &AppHandle, params: FolderParams) -> Result<Vec<InvoiceFile>, (i64, String)> {
+    let folder = Path::new(&params.folder_path);
+    crate::config::current(app)
+        .ensure_allowed(folder)
+        .map_err(merge_failed)?;
+    let extra = crate::normalize_extensions(Some(params.extra_extensions));
+    crate::scan_folder(folder, &extra).map_err(merge_failed)
+}
+
+fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, (i64, String)> {
+    serde_json::from_value(params).map_err(|err| (INVALID_PARAMS, err.to_string()))
+}
+
+fn to_value<T: Serialize>(value: &T) -> Result<Value, (i64, String)> {
+    serde_json::to_value(value).map_err(|err| (MERGE_FAILED, err.to_string()))
+}
+
+fn merge_failed(err: crate::MergeError) -> (i64, String) {
+    (MERGE_FAILED, err.to_string())
+}
+
+fn error_response(id: Value, code: i64, message: String) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn write_line(value: &Value) {
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "{value}");
+    let _ = stdout.flush();
+}
@@ -0,0 +1,167 @@
+use chrono::Local;
+use lopdf::{Object, ObjectId};
+use std::{
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+use tempfile::TempPath;
+
+use crate::{
+    mmap_pdf,
+    page_map::{PageRange, PageSourceKind},
+    pdf_writer, plan,
+    text_page::{self, TextLine},
+    MergeError,
+};
+
+/// 摘要页的页数取决于条目数量与页码长度，重新排版时最多尝试的次数
+const MAX_LAYOUT_PASSES: usize = 4;
+
+/// 在合并结果前插入摘要页：合并时间、文件夹、文件数，以及每个来源文件在输出中的页码范围，
+/// 供财务审核时对照。摘要本身也占页，列出的页码已计入摘要页。
+///
+/// 成功后更新 `page_map`（整体后移并在开头登记摘要页），返回插入的页数。
+pub fn prepend(
+    output: &Path,
+    folder: &str,
+    file_count: usize,
+    page_map: &mut Vec<PageRange>,
+    work_dir: &Path,
+) -> Result<usize, MergeError> {
+    let Some((summary_path, _temp, _)) = render(folder, file_count, page_map, work_dir)? else {
+        return Ok(0);
+    };
+    let added = insert_front(output, &summary_path)?;
+
+    for range in page_map.iter_mut() {
+        range.start_page += added;
+        range.end_page += added;
+    }
+    page_map.insert(
+        0,
+        PageRange {
+            start_page: 1,
+            end_page: added,
+            file_name: "合并摘要".to_string(),
+            path: String::new(),
+            kind: PageSourceKind::Summary,
+        },
+    );
+    Ok(added)
+}
+
+/// 摘要页会占用的页数，供合并预估使用；与 `prepend` 排版方式相同。
+pub fn page_count(
+    folder: &str,
+    file_count: usize,
+    page_map: &[PageRange],
+    work_dir: &Path,
+) -> Result<usize, MergeError> {
+    Ok(render(folder, file_count, page_map, work_dir)?.map_or(0, |(_, _, pages)| pages))
+}
+
+/// 排版摘要页：页码要计入摘要自身的页数，反复排版直到页数稳定。返回摘要 PDF 及其页数。
+fn render(
+    folder: &str,
+    file_count: usize,
+    page_map: &[PageRange],
+    work_dir: &Path,
+) -> Result<Option<(PathBuf, TempPath, usize)>, MergeError> {
+    let total: usize = page_map.last().map_or(0, |range| range.end_page);
+    let mut pages = 1;
+    let mut rendered = None;
+    for _ in 0..MAX_LAYOUT_PASSES {
+        let lines = summary_lines(folder, file_count, total + pages, page_map, pages);
+        let (path, temp) = text_page::render_text_document("合并摘要", &lines, work_dir)?;
+        let actual = plan::pdf_page_count(&path)? as usize;
+        rendered = Some((path, temp, actual));
+        if actual == pages {
+            break;
+        }
+        pages = actual;
+    }
+    Ok(rendered)
+}
+
+fn summary_lines(
+    folder: &str,
+    file_count: usize,
+    total_pages: usize,
+    page_map: &[PageRange],
+    offset: usize,
+) -> Vec<TextLine> {
+    let mut lines = vec![
+        TextLine::new("合并摘要", 18.0),
+        TextLine::blank(),
+        TextLine::new(
+            format!("合并时间: {}", Local::now().format("%Y-%m-%d %H:%M")),
+            11.0,
+        ),
+        TextLine::new(format!("文件夹: {folder}"), 11.0),
+        TextLine::new(format!("文件数: {file_count}"), 11.0),
+        TextLine::new(format!("总页数: {total_pages}"), 11.0),
+        TextLine::blank(),
+    ];
+    for (index, range) in page_map.iter().enumerate() {
+        let (start, end) = (range.start_page + offset, range.end_page + offset);
+        let pages = if start == end {
+            format!("第 {start} 页")
+        } else {
+            format!("第 {start}–{end} 页")
+        };
+        lines.push(TextLine::new(
+            format!("{}. {}    {pages}", index + 1, range.file_name),
+            10.0,
+        ));
+    }
+    lines
+}
+
+/// 把 `front` 的全部页面插到 `output` 的最前面并写回。原有书签指向的页面对象不变，无需调整。
+fn insert_front(output: &Path, front: &Path) -> Result<usize, MergeError> {
+    let mut doc = mmap_pdf::load(output)?;
+    let mut cover = mmap_pdf::load(front)?;
+    let pages_id = doc
+        .catalog()
+        .and_then(|catalog| catalog.get(b"Pages"))
+        .and_then(Object::as_reference)
+        .map_err(|err| MergeError::Pdf(err.to_string()))?;
+
+    cover.renumber_objects_with(doc.max_id + 1);
+    let cover_pages: Vec<ObjectId> = cover.get_pages().into_values().collect();
+    for (id, mut object) in std::mem::take(&mut cover.objects) {
+        match object.type_name().unwrap_or("") {
+            "Catalog" | "Pages" => continue,
+            "Page" => {
+                if let Ok(page) = object.as_dict_mut() {
+                    page.set("Parent", pages_id);
+                }
+            }
+            _ => {}
+        }
+        doc.max_id = doc.max_id.max(id.0);
+        doc.objects.insert(id, object);
+    }
+
+    let pages = doc
+        .get_object_mut(pages_id)
+        .and_then(Object::as_dict_mut)
+        .map_err(|err| MergeError::Pdf(err.to_string()))?;
+    let mut kids: Vec<Object> = cover_pages.iter().map(|id| Object::Reference(*id)).collect();
+    if let Ok(existing) = pages.get(b"Kids").and_then(Object::as_array) {
+        kids.extend(existing.iter().cloned());
+    }
+    let count = pages.get(b"Count").and_then(Object::as_i64).unwrap_or(0);
+    pages.set("Kids", kids);
+    pages.set("Count", count + cover_pages.len() as i64);
+
+    // 先写到同目录的临时文件再替换，中途失败不会留下半份输出
+    let parent = output.parent().unwrap_or_else(|| Path::new("."));
+    let temp_file = tempfile::NamedTempFile::new_in(parent)?;
+    {
+        let mut writer = BufWriter::new(temp_file.as_file());
+        pdf_writer::save_compact(&doc, &mut writer, |_, _| {})?;
+    }
+    temp_file.persist(output).map_err(|err| err.error)?;
+    Ok(cover_pages.len())
+}
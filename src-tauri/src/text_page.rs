@@ -0,0 +1,229 @@
+use lopdf::{Document, Object, ObjectId};
+use printpdf::{BuiltinFont, IndirectFontRef, Mm, PdfDocument, PdfDocumentReference};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::{BufWriter, Cursor},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+};
+use tempfile::TempPath;
+
+use crate::{page_fit::save_temp_document, MergeError};
+
+pub const PAGE_WIDTH_MM: f64 = 210.0;
+pub const PAGE_HEIGHT_MM: f64 = 297.0;
+const MARGIN_MM: f64 = 18.0;
+const PT_TO_MM: f64 = 25.4 / 72.0;
+
+/// 生成页需要显示中文，依次尝试各平台自带的中文字体；都不可用时退回 Helvetica。
+const CJK_FONT_CANDIDATES: &[&str] = &[
+    r"C:\Windows\Fonts\simhei.ttf",
+    r"C:\Windows\Fonts\msyh.ttc",
+    r"C:\Windows\Fonts\simsun.ttc",
+    "/System/Library/Fonts/STHeiti Medium.ttc",
+    "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+    "/Library/Fonts/Arial Unicode.ttf",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/wqy-microhei/wqy-microhei.ttc",
+    "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+];
+
+#[derive(Debug, Clone)]
+pub struct TextLine {
+    pub text: String,
+    pub size: f64,
+}
+
+impl TextLine {
+    pub fn new(text: impl Into<String>, size: f64) -> Self {
+        Self {
+            text: text.into(),
+            size,
+        }
+    }
+
+    pub fn blank() -> Self {
+        Self::new("", 6.0)
+    }
+}
+
+/// 系统中文字体动辄 10–20 MB，整个进程只读一次
+static CJK_FONT: OnceLock<Option<Vec<u8>>> = OnceLock::new();
+/// 上次取走提示后是否又退回过 Helvetica
+static FONT_FALLBACK_USED: AtomicBool = AtomicBool::new(false);
+
+fn cjk_font_data() -> Option<&'static [u8]> {
+    CJK_FONT
+        .get_or_init(|| {
+            CJK_FONT_CANDIDATES
+                .iter()
+                .filter_map(|candidate| fs::read(candidate).ok())
+                .find(|data| can_subset(data))
+        })
+        .as_deref()
+}
+
+/// printpdf 只取集合（.ttc）中的第一个字体，先确认它能被裁剪
+fn can_subset(data: &[u8]) -> bool {
+    subsetter::subset(data, 0, subsetter::Profile::pdf(&[0])).is_ok()
+}
+
+/// 加入中文字体。printpdf 会嵌入整个字体文件，保存后须经 [`subset_fonts`] 裁剪为只含用到的字形。
+pub fn load_text_font(doc: &PdfDocumentReference) -> Result<IndirectFontRef, MergeError> {
+    if let Some(font) = cjk_font_data().and_then(|data| doc.add_external_font(Cursor::new(data)).ok()) {
+        return Ok(font);
+    }
+    FONT_FALLBACK_USED.store(true, Ordering::Relaxed);
+    doc.add_builtin_font(BuiltinFont::Helvetica)
+        .map_err(|err| MergeError::Pdf(err.to_string()))
+}
+
+/// 自上次调用以来是否有生成页因缺少中文字体而退回 Helvetica（中文会显示为乱码）。
+pub fn take_font_fallback() -> bool {
+    FONT_FALLBACK_USED.swap(false, Ordering::Relaxed)
+}
+
+/// 把嵌入的 TrueType 字体裁剪为只含 /W 中列出的字形。printpdf 按原字形号写入文字且
+/// /CIDToGIDMap 为 Identity，subsetter 保留字形号，因此内容流无需改动。
+pub fn subset_fonts(doc: &mut Document) {
+    let mut glyphs: BTreeMap<ObjectId, BTreeSet<u16>> = BTreeMap::new();
+    for object in doc.objects.values() {
+        let Ok(font) = object.as_dict() else {
+            continue;
+        };
+        if font.get(b"Subtype").and_then(Object::as_name).ok() != Some(b"CIDFontType2".as_slice()) {
+            continue;
+        }
+        let Some(file_id) = font
+            .get(b"FontDescriptor")
+            .and_then(Object::as_reference)
+            .and_then(|id| doc.get_dictionary(id))
+            .and_then(|descriptor| descriptor.get(b"FontFile2"))
+            .and_then(Object::as_reference)
+            .ok()
+        else {
+            continue;
+        };
+        let used = glyphs.entry(file_id).or_insert_with(|| BTreeSet::from([0]));
+        if let Ok(widths) = font.get(b"W").and_then(Object::as_array) {
+            used.extend(glyphs_in_widths(widths));
+        }
+    }
+
+    for (file_id, used) in glyphs {
+        let Ok(Object::Stream(stream)) = doc.get_object_mut(file_id) else {
+            continue;
+        };
+        let data = stream
+            .decompressed_content()
+            .unwrap_or_else(|_| stream.content.clone());
+        let used: Vec<u16> = used.into_iter().collect();
+        let Ok(subset) = subsetter::subset(&data, 0, subsetter::Profile::pdf(&used)) else {
+            continue;
+        };
+        stream.dict.remove(b"DecodeParms");
+        stream.dict.set("Length1", subset.len() as i64);
+        stream.set_plain_content(subset);
+        let _ = stream.compress();
+    }
+}
+
+/// /W 数组的两种写法：`c [w1 w2 …]` 为从 c 起的连续字形，`c_first c_last w` 为同宽的一段
+fn glyphs_in_widths(widths: &[Object]) -> Vec<u16> {
+    let mut glyphs = Vec::new();
+    let mut index = 0;
+    while index < widths.len() {
+        let Ok(first) = widths[index].as_i64() else {
+            break;
+        };
+        match widths.get(index + 1) {
+            Some(Object::Array(run)) => {
+                glyphs.extend((first..first + run.len() as i64).filter_map(|gid| u16::try_from(gid).ok()));
+                index += 2;
+            }
+            Some(Object::Integer(last)) => {
+                glyphs.extend((first..=*last).filter_map(|gid| u16::try_from(gid).ok()));
+                index += 3;
+            }
+            _ => break,
+        }
+    }
+    glyphs
+}
+
+/// 保存 printpdf 文档到临时文件，并裁剪嵌入的字体。
+pub fn save_text_pdf(
+    doc: PdfDocumentReference,
+    prefix: &str,
+    work_dir: &Path,
+) -> Result<(PathBuf, TempPath), MergeError> {
+    let mut doc = to_lopdf(doc)?;
+    save_temp_document(&mut doc, prefix, work_dir)
+}
+
+/// 转为 lopdf 文档并裁剪嵌入的字体，供需要继续加工的调用方使用。
+pub fn to_lopdf(doc: PdfDocumentReference) -> Result<Document, MergeError> {
+    let mut bytes = Vec::new();
+    doc.save(&mut BufWriter::new(&mut bytes))
+        .map_err(|err| MergeError::Pdf(err.to_string()))?;
+    let mut doc = Document::load_mem(&bytes).map_err(|err| MergeError::Pdf(err.to_string()))?;
+    subset_fonts(&mut doc);
+    Ok(doc)
+}
+
+/// 把若干行文字按 A4 排版（超出一页自动换页、超宽自动折行）并写入临时 PDF。
+pub fn render_text_document(
+    title: &str,
+    lines: &[TextLine],
+    work_dir: &Path,
+) -> Result<(PathBuf, TempPath), MergeError> {
+    let (doc, page, layer) = PdfDocument::new(title, Mm(PAGE_WIDTH_MM), Mm(PAGE_HEIGHT_MM), "Layer");
+    let font = load_text_font(&doc)?;
+    let mut current_layer = doc.get_page(page).get_layer(layer);
+    let mut cursor_y = PAGE_HEIGHT_MM - MARGIN_MM;
+
+    for line in lines {
+        let line_height = line.size * PT_TO_MM * 1.5;
+        for segment in wrap_text(&line.text, line.size, PAGE_WIDTH_MM - 2.0 * MARGIN_MM) {
+            if cursor_y - line_height < MARGIN_MM {
+                let (next_page, next_layer) = doc.add_page(Mm(PAGE_WIDTH_MM), Mm(PAGE_HEIGHT_MM), "Layer");
+                current_layer = doc.get_page(next_page).get_layer(next_layer);
+                cursor_y = PAGE_HEIGHT_MM - MARGIN_MM;
+            }
+            cursor_y -= line_height;
+            if !segment.is_empty() {
+                current_layer.use_text(segment, line.size, Mm(MARGIN_MM), Mm(cursor_y), &font);
+            }
+        }
+    }
+
+    save_text_pdf(doc, "mc-text-", work_dir)
+}
+
+/// 估算文字宽度（mm）：中文等全角字符按一个字号宽，ASCII 按半个多字号宽。
+pub fn text_width_mm(text: &str, size: f64) -> f64 {
+    text.chars()
+        .map(|c| if c.is_ascii() { 0.55 } else { 1.0 })
+        .sum::<f64>()
+        * size
+        * PT_TO_MM
+}
+
+fn wrap_text(text: &str, size: f64, max_width_mm: f64) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    for c in text.chars() {
+        current.push(c);
+        if text_width_mm(&current, size) > max_width_mm {
+            current.pop();
+            segments.push(std::mem::take(&mut current));
+            current.push(c);
+        }
+    }
+    segments.push(current);
+    segments
+}
@@ -0,0 +1,37 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::{DynamicImage, ImageOutputFormat};
+use std::{io::Cursor, path::Path};
+
+use crate::{load_preview_image, raster, sniff, MergeError, IMAGE_EXTENSIONS};
+
+const MIN_SIDE: u32 = 32;
+const MAX_SIDE: u32 = 1024;
+
+/// 生成文件列表用的缩略图，返回 base64 编码的 PNG：PDF 取第一页，图片缩小到 `max_px` 以内。
+/// WebView 不能直接显示的格式（HEIC、TIFF）也能得到预览。
+pub fn render(path: &Path, max_px: u32) -> Result<String, MergeError> {
+    let max_px = max_px.clamp(MIN_SIDE, MAX_SIDE);
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    // 以文件内容为准，扩展名写错的文件也能预览
+    let kind = sniff::sniff_extension(path).unwrap_or(ext.as_str());
+    let image = if kind == "pdf" {
+        raster::render_first_page(path, max_px)?
+    } else if IMAGE_EXTENSIONS.contains(&kind) {
+        load_preview_image(path, max_px)?
+    } else {
+        return Err(MergeError::Unsupported(ext));
+    };
+    encode_png(&image)
+}
+
+fn encode_png(image: &DynamicImage) -> Result<String, MergeError> {
+    let mut bytes = Cursor::new(Vec::new());
+    image
+        .write_to(&mut bytes, ImageOutputFormat::Png)
+        .map_err(|err| MergeError::Image(err.to_string()))?;
+    Ok(STANDARD.encode(bytes.into_inner()))
+}
@@ -0,0 +1,148 @@
+use image::{DynamicImage, GrayImage, RgbImage};
+use std::{fs::File, io::BufReader, path::Path};
+use tiff::{
+    decoder::{ChunkType, Decoder, DecodingResult},
+    ColorType, TiffError,
+};
+
+use crate::{sniff, MergeError};
+
+/// 完整解码后超过该大小（字节）的 TIFF 改为逐条带/逐块解码
+const STREAM_THRESHOLD_BYTES: u64 = 256 * 1024 * 1024;
+
+/// 逐个条带（strip）或图块（tile）解码超大 TIFF，边读边按整数倍区域平均缩小，
+/// 使图片放进 `max_width` × `max_height` 像素时分辨率仍不低于目标值。
+/// 内存中只保留当前条带、正在累加的几行和缩小后的结果，几百 MB 的扫描件也不会整张展开。
+///
+/// 不是 TIFF、解码后不算大，或是调色板、CMYK 等需要整体转换的格式时返回 `None`，
+/// 由调用方按普通方式解码。多页 TIFF 只取第一页，与普通解码一致。
+pub fn decode_fitted(
+    path: &Path,
+    max_width: u32,
+    max_height: u32,
+) -> Result<Option<DynamicImage>, MergeError> {
+    if sniff::sniff_extension(path) != Some("tiff") {
+        return Ok(None);
+    }
+    let mut decoder = Decoder::new(BufReader::new(File::open(path)?)).map_err(tiff_error)?;
+    let (width, height) = decoder.dimensions().map_err(tiff_error)?;
+    let (channels, bits) = match decoder.colortype().map_err(tiff_error)? {
+        ColorType::Gray(bits @ (8 | 16)) => (1, bits),
+        ColorType::GrayA(bits @ (8 | 16)) => (2, bits),
+        ColorType::RGB(bits @ (8 | 16)) => (3, bits),
+        ColorType::RGBA(bits @ (8 | 16)) => (4, bits),
+        _ => return Ok(None),
+    };
+    let decoded_bytes = width as u64 * height as u64 * channels as u64 * bits as u64 / 8;
+    if decoded_bytes <= STREAM_THRESHOLD_BYTES || width == 0 || height == 0 {
+        return Ok(None);
+    }
+
+    let factor = (width as f64 / max_width.max(1) as f64)
+        .max(height as f64 / max_height.max(1) as f64)
+        .floor()
+        .max(1.0) as u32;
+    let out_width = width.div_ceil(factor) as usize;
+    let out_height = height.div_ceil(factor) as usize;
+    let out_channels = if channels >= 3 { 3 } else { 1 };
+    let row_len = out_width * out_channels;
+    let mut out = vec![0u8; row_len * out_height];
+
+    let (chunk_width, chunk_height) = decoder.chunk_dimensions();
+    let across = match decoder.get_chunk_type() {
+        ChunkType::Strip => 1,
+        ChunkType::Tile => width.div_ceil(chunk_width),
+    };
+    let down = height.div_ceil(chunk_height);
+
+    // 尚未写出的输出行的累加值，第一行对应输出的第 `pending_top` 行
+    let mut pending_top = 0usize;
+    let mut sums: Vec<u32> = Vec::new();
+    for band in 0..down {
+        let band_top = band * chunk_height;
+        let band_height = chunk_height.min(height - band_top);
+        let last_row = ((band_top + band_height - 1) / factor) as usize;
+        sums.resize((last_row + 1 - pending_top) * row_len, 0);
+
+        for column in 0..across {
+            let index = band * across + column;
+            let (data_width, data_height) = decoder.chunk_data_dimensions(index);
+            let data = decoder.read_chunk(index).map_err(tiff_error)?;
+            let left = column * chunk_width;
+            for y in 0..data_height.min(band_height) {
+                let out_row = ((band_top + y) / factor) as usize - pending_top;
+                for x in 0..data_width.min(width - left) {
+                    let offset = (y as usize * data_width as usize + x as usize) * channels;
+                    let pixel = composite(&data, offset, channels);
+                    let out_col = ((left + x) / factor) as usize;
+                    let base = out_row * row_len + out_col * out_channels;
+                    for (sum, value) in sums[base..base + out_channels].iter_mut().zip(pixel) {
+                        *sum += value as u32;
+                    }
+                }
+            }
+        }
+
+        // 输出行覆盖的原始行全部读完后才能求平均
+        let band_bottom = band_top + band_height;
+        let done = if band_bottom == height {
+            out_height
+        } else {
+            (band_bottom / factor) as usize
+        };
+        for row in pending_top..done {
+            let rows = factor.min(height - row as u32 * factor);
+            for col in 0..out_width {
+                let count = rows * factor.min(width - col as u32 * factor);
+                let base = col * out_channels;
+                for channel in 0..out_channels {
+                    let sum = sums[(row - pending_top) * row_len + base + channel];
+                    out[row * row_len + base + channel] = (sum / count.max(1)) as u8;
+                }
+            }
+        }
+        sums.drain(..(done - pending_top) * row_len);
+        pending_top = done;
+    }
+
+    let (out_width, out_height) = (out_width as u32, out_height as u32);
+    let image = if out_channels == 3 {
+        RgbImage::from_raw(out_width, out_height, out).map(DynamicImage::ImageRgb8)
+    } else {
+        GrayImage::from_raw(out_width, out_height, out).map(DynamicImage::ImageLuma8)
+    };
+    image
+        .map(Some)
+        .ok_or_else(|| MergeError::Image("TIFF 缩小结果尺寸不符".into()))
+}
+
+/// 取一个像素并转为 8 位；带透明通道时铺在白底上，与普通解码路径的处理一致。
+fn composite(data: &DecodingResult, offset: usize, channels: usize) -> [u8; 3] {
+    let sample = |index: usize| -> u8 {
+        match data {
+            DecodingResult::U8(values) => values.get(offset + index).copied().unwrap_or(255),
+            DecodingResult::U16(values) => values.get(offset + index).map_or(255, |value| (value >> 8) as u8),
+            _ => 255,
+        }
+    };
+    let over_white = |value: u8, alpha: u8| -> u8 {
+        ((value as u32 * alpha as u32 + 255 * (255 - alpha as u32)) / 255) as u8
+    };
+    match channels {
+        1 => [sample(0); 3],
+        2 => [over_white(sample(0), sample(1)); 3],
+        3 => [sample(0), sample(1), sample(2)],
+        _ => {
+            let alpha = sample(3);
+            [
+                over_white(sample(0), alpha),
+                over_white(sample(1), alpha),
+                over_white(sample(2), alpha),
+            ]
+        }
+    }
+}
+
+fn tiff_error(err: TiffError) -> MergeError {
+    MergeError::Image(err.to_string())
+}
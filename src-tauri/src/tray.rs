@@ -0,0 +1,150 @@
+use std::{
+    path::Path,
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+use tauri::{
+    AppHandle, CustomMenuItem, Manager, SystemTray, SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem,
+    Window,
+};
+
+use crate::{office::hide_console_window, ConflictAction, MergeError, MergeRequest, MergeResult};
+
+const MENU_SHOW: &str = "show";
+const MENU_OPEN_LAST: &str = "open_last";
+const MENU_RERUN: &str = "rerun";
+const MENU_QUIT: &str = "quit";
+
+/// 托盘菜单需要的运行时状态：最近一次成功合并的请求与输出，以及关闭窗口时是否仅隐藏。
+#[derive(Default)]
+pub struct TrayState {
+    last_merge: Mutex<Option<(MergeRequest, String)>>,
+    hide_on_close: AtomicBool,
+}
+
+impl TrayState {
+    pub fn record(&self, req: &MergeRequest, output_path: &str) {
+        if let Ok(mut last) = self.last_merge.lock() {
+            *last = Some((req.clone(), output_path.to_string()));
+        }
+    }
+
+    pub fn last_output(&self) -> Option<String> {
+        self.last_merge
+            .lock()
+            .ok()?
+            .as_ref()
+            .map(|(_, path)| path.clone())
+    }
+
+    pub fn set_hide_on_close(&self, enabled: bool) {
+        self.hide_on_close.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn hide_on_close(&self) -> bool {
+        self.hide_on_close.load(Ordering::Relaxed)
+    }
+}
+
+pub fn build() -> SystemTray {
+    let menu = SystemTrayMenu::new()
+        .add_item(CustomMenuItem::new(MENU_SHOW, "显示主窗口"))
+        .add_native_item(SystemTrayMenuItem::Separator)
+        .add_item(CustomMenuItem::new(MENU_OPEN_LAST, "打开上次输出"))
+        .add_item(CustomMenuItem::new(MENU_RERUN, "重新执行上次合并"))
+        .add_native_item(SystemTrayMenuItem::Separator)
+        .add_item(CustomMenuItem::new(MENU_QUIT, "退出"));
+    SystemTray::new().with_menu(menu)
+}
+
+pub fn handle_event(app: &AppHandle, event: SystemTrayEvent) {
+    let Some(window) = app.get_window("main") else {
+        return;
+    };
+    match event {
+        SystemTrayEvent::LeftClick { .. } => show_window(&window),
+        SystemTrayEvent::MenuItemClick { id, .. } => match id.as_str() {
+            MENU_SHOW => show_window(&window),
+            MENU_OPEN_LAST => {
+                if let Some(path) = app.state::<TrayState>().last_output() {
+                    if let Err(err) = open_path(Path::new(&path)) {
+                        crate::emit_warning(&window, "tray", err.to_string());
+                    }
+                }
+            }
+            MENU_RERUN => rerun_last(app, window),
+            MENU_QUIT => app.exit(0),
+            _ => {}
+        },
+        _ => {}
+    }
+}
+
+fn show_window(window: &Window) {
+    let _ = window.show();
+    let _ = window.unminimize();
+    let _ = window.set_focus();
+}
+
+/// 按上次的请求重新合并并覆盖上次的输出，结果通过 `tray-merge-complete` 事件通知前端。
+fn rerun_last(app: &AppHandle, window: Window) {
+    let Some((mut req, _)) = app
+        .state::<TrayState>()
+        .last_merge
+        .lock()
+        .ok()
+        .and_then(|last| last.clone())
+    else {
+        return;
+    };
+    req.on_conflict = Some(ConflictAction::Overwrite);
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || match crate::merge_invoices(&window, req.clone(), false) {
+        Ok(result) => {
+            remember(&app, &req, &result);
+            crate::notify::merge_complete(&window, &result);
+            let _ = window.emit("tray-merge-complete", &result);
+        }
+        Err(err) => crate::emit_warning(&window, "tray", err.to_string()),
+    });
+}
+
+/// 用系统默认程序打开文件。
+pub fn open_path(path: &Path) -> Result<(), MergeError> {
+    if !path.exists() {
+        return Err(MergeError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            path.display().to_string(),
+        )));
+    }
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]).arg(path);
+        command
+    } else if cfg!(target_os = "macos") {
+        let mut command = Command::new("open");
+        command.arg(path);
+        command
+    } else {
+        let mut command = Command::new("xdg-open");
+        command.arg(path);
+        command
+    };
+    hide_console_window(&mut command);
+    command.spawn()?;
+    Ok(())
+}
+
+/// 合并成功且不需要用户确认时记下请求，供托盘“重新执行”使用。
+pub fn remember(app: &AppHandle, req: &MergeRequest, result: &MergeResult) {
+    // 以插件形式嵌入其他应用时没有托盘状态
+    let Some(state) = app.try_state::<TrayState>() else {
+        return;
+    };
+    if result.success && result.needs_confirmation.is_none() {
+        state.record(req, &result.output_path);
+    }
+}
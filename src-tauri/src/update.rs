@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::MergeError;
+
+/// 可用更新的概要，`notes` 为发布时填写的更新说明。
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UpdateInfo {
+    pub current_version: String,
+    pub version: String,
+    pub notes: Option<String>,
+    pub date: Option<String>,
+}
+
+/// 查询更新服务器；已是最新版本时返回 `None`。
+/// 更新器默认未启用（`tauri.conf.json` 中的 `updater.active`），配置发布地址与签名公钥后才会联网，
+/// 未配置时返回 [`MergeError::UpdateNotConfigured`]，不去调用更新器。
+pub async fn check(app: AppHandle) -> Result<Option<UpdateInfo>, MergeError> {
+    ensure_configured(&app)?;
+    let update = app.updater().check().await.map_err(update_error)?;
+    if !update.is_update_available() {
+        return Ok(None);
+    }
+    Ok(Some(UpdateInfo {
+        current_version: update.current_version().to_string(),
+        version: update.latest_version().to_string(),
+        notes: update.body().cloned(),
+        date: update.date().map(|date| date.to_string()),
+    }))
+}
+
+/// 下载并安装最新版本，返回是否实际安装；Windows 上安装程序会接管并退出当前进程。
+pub async fn install(app: AppHandle) -> Result<bool, MergeError> {
+    ensure_configured(&app)?;
+    let update = app.updater().check().await.map_err(update_error)?;
+    if !update.is_update_available() {
+        return Ok(false);
+    }
+    update.download_and_install().await.map_err(update_error)?;
+    Ok(true)
+}
+
+fn ensure_configured(app: &AppHandle) -> Result<(), MergeError> {
+    let updater = &app.config().tauri.updater;
+    let has_endpoint = updater
+        .endpoints
+        .as_ref()
+        .is_some_and(|endpoints| !endpoints.is_empty());
+    if updater.active && has_endpoint && !updater.pubkey.trim().is_empty() {
+        Ok(())
+    } else {
+        Err(MergeError::UpdateNotConfigured)
+    }
+}
+
+fn update_error(err: tauri::updater::Error) -> MergeError {
+    MergeError::Update(err.to_string())
+}
@@ -0,0 +1,392 @@
+use lopdf::{Document, Object};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::{fs, path::Path};
+
+use crate::{
+    blank, compress::resolve, invoice_db::MergedInvoice, is_mislabeled, load_preview_image, phash,
+    pipeline_ext, InvoiceFile, MergeError, IMAGE_EXTENSIONS,
+};
+
+/// 单个文件的默认大小上限（MB）。更大的扫描归档可在设置中调高，设为 0 表示不限制
+pub const DEFAULT_MAX_FILE_MB: f64 = 1024.0;
+
+/// 空白与重复检测只需要小图
+const PREVIEW_SIDE: u32 = 256;
+/// CMS 签名属性中 messageDigest 的 OID（1.2.840.113549.1.9.4）编码
+const MESSAGE_DIGEST_OID: &[u8] = &[0x06, 0x09, 0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x04];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureStatus {
+    Unsigned,
+    /// 签名覆盖范围的摘要与签名中记录的 messageDigest 一致。
+    /// 未验证签名值本身与证书，不能据此认定签名有效
+    DigestMatches,
+    Broken,
+    /// 签名之后文件又被追加了签名未覆盖的内容（增量更新）
+    Modified,
+    /// 存在签名但格式无法识别，无法判断是否完整
+    Unknown,
+    /// 文件读取或解析失败，不知道是否带有签名
+    Unreadable,
+}
+
+/// PDF 内容来源：扫描件（仅图片）还是软件直接生成（含文字）。
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PdfContentKind {
+    Scanned,
+    Digital,
+    /// 部分页面为扫描图片，部分页面含文字
+    Mixed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileValidation {
+    pub path: String,
+    pub file_name: String,
+    pub signature: SignatureStatus,
+    /// 是否为 XFA 表单（合并后在多数阅读器中会显示空白）
+    pub xfa: bool,
+    /// 与列表中更靠前的某张图片视觉上几乎相同时，记录那张图片的路径
+    pub duplicate_of: Option<String>,
+    /// 图片几乎为纯色（误拍、全白/全黑帧）
+    pub near_blank: bool,
+    /// 扩展名与文件内容不符时，记录识别出的实际格式
+    pub detected_ext: Option<String>,
+    /// 仅 PDF：扫描件可建议 OCR，文字版通常无需压缩
+    pub content_kind: Option<PdfContentKind>,
+    /// 重复发票库中此前合并过同一发票号码的记录
+    pub previously_merged: Option<MergedInvoice>,
+    /// 超过单个文件大小上限，合并时会被拒绝；此时不再做其余内容检查
+    #[serde(default)]
+    pub too_large: bool,
+    pub warnings: Vec<String>,
+}
+
+/// 大小上限（字节）。`limit_mb` 未设置时取默认值，设为 0 或负数表示不限制。
+pub fn size_limit_bytes(limit_mb: Option<f64>) -> Option<u64> {
+    let limit_mb = limit_mb.unwrap_or(DEFAULT_MAX_FILE_MB);
+    (limit_mb > 0.0).then(|| (limit_mb * 1024.0 * 1024.0) as u64)
+}
+
+/// 超过上限的文件（例如被误改成 .pdf 的视频）直接拒绝，不去解析，以免整个合并卡住。
+pub fn check_size(size: u64, limit: Option<u64>) -> Result<(), MergeError> {
+    match limit {
+        Some(limit) if size > limit => Err(MergeError::FileTooLarge {
+            size_mb: size.div_ceil(1024 * 1024),
+            limit_mb: limit / 1024 / 1024,
+        }),
+        _ => Ok(()),
+    }
+}
+
+pub fn validate_files(files: &[InvoiceFile], size_limit: Option<u64>) -> Vec<FileValidation> {
+    let mut results: Vec<FileValidation> = files.iter().map(|file| validate_file(file, size_limit)).collect();
+    inspect_images(files, &mut results);
+    results
+}
+
+/// 解码图片一次，同时检查空白帧与重复拍摄。
+fn inspect_images(files: &[InvoiceFile], results: &mut [FileValidation]) {
+    let mut hashes: Vec<Option<u64>> = Vec::with_capacity(files.len());
+    for (file, result) in files.iter().zip(results.iter_mut()) {
+        let ext = pipeline_ext(&file.ext, Path::new(&file.path)).unwrap_or_default();
+        let image = (IMAGE_EXTENSIONS.contains(&ext.as_str()) && !result.too_large)
+            .then(|| load_preview_image(Path::new(&file.path), PREVIEW_SIDE).ok())
+            .flatten();
+        if let Some(image) = &image {
+            if blank::is_near_blank(image) {
+                result.near_blank = true;
+                result.warnings.push("图片几乎为纯色，可能是误拍".into());
+            }
+        }
+        hashes.push(image.map(|image| phash::dhash(&image)));
+    }
+
+    for (index, hash) in hashes.iter().enumerate() {
+        let Some(hash) = hash else {
+            continue;
+        };
+        let earlier = hashes[..index]
+            .iter()
+            .position(|other| other.is_some_and(|other| phash::is_near_duplicate(*hash, other)));
+        if let Some(earlier) = earlier {
+            results[index].duplicate_of = Some(files[earlier].path.clone());
+            results[index].warnings.push(format!(
+                "与 {} 的画面几乎相同，可能重复拍摄",
+                files[earlier].file_name
+            ));
+        }
+    }
+}
+
+fn validate_file(file: &InvoiceFile, size_limit: Option<u64>) -> FileValidation {
+    let size = fs::metadata(&file.path).map_or(file.size, |meta| meta.len());
+    if let Err(err) = check_size(size, size_limit) {
+        return FileValidation {
+            path: file.path.clone(),
+            file_name: file.file_name.clone(),
+            signature: SignatureStatus::Unsigned,
+            xfa: false,
+            duplicate_of: None,
+            near_blank: false,
+            detected_ext: None,
+            content_kind: None,
+            previously_merged: None,
+            too_large: true,
+            warnings: vec![format!("{err}，合并时将跳过该文件")],
+        };
+    }
+    let mut warnings = Vec::new();
+    let actual = pipeline_ext(&file.ext, Path::new(&file.path));
+    let detected_ext = actual.clone().filter(|actual| is_mislabeled(&file.ext, actual));
+    if let Some(detected) = &detected_ext {
+        warnings.push(format!(
+            "扩展名与内容不符，实际为 {} 文件，合并时将自动按实际格式处理",
+            detected.to_uppercase()
+        ));
+    }
+    let is_pdf = actual.as_deref() == Some("pdf");
+    let signature = if is_pdf {
+        check_signatures(Path::new(&file.path))
+    } else {
+        SignatureStatus::Unsigned
+    };
+    let xfa = is_pdf && pdf_has_xfa(Path::new(&file.path));
+    let content_kind = is_pdf.then(|| classify_pdf(Path::new(&file.path))).flatten();
+    if xfa {
+        warnings.push("该文件为 XFA 表单，合并后可能显示空白，建议开启栅格化".into());
+    }
+
+    match signature {
+        SignatureStatus::DigestMatches => {
+            warnings.push("该文件带有数字签名（仅核对了内容摘要，未验证证书），合并后签名将失效".into());
+        }
+        SignatureStatus::Unknown => {
+            warnings.push("该文件带有无法识别的数字签名，合并后签名将失效".into());
+        }
+        SignatureStatus::Unreadable => {
+            warnings.push("无法解析该 PDF，未能检查是否带有数字签名".into());
+        }
+        SignatureStatus::Broken => {
+            warnings.push("数字签名校验失败，文件可能在签名后被修改".into());
+        }
+        SignatureStatus::Modified => {
+            warnings.push("签名之后文件又被追加修改，签名未覆盖全部内容".into());
+        }
+        SignatureStatus::Unsigned => {}
+    }
+
+    FileValidation {
+        path: file.path.clone(),
+        file_name: file.file_name.clone(),
+        signature,
+        xfa,
+        duplicate_of: None,
+        near_blank: false,
+        detected_ext,
+        content_kind,
+        previously_merged: None,
+        too_large: false,
+        warnings,
+    }
+}
+
+pub fn pdf_has_xfa(path: &Path) -> bool {
+    Document::load(path)
+        .map(|doc| document_has_xfa(&doc))
+        .unwrap_or(false)
+}
+
+/// 判断目录的 AcroForm 中是否带有 XFA 数据。
+pub fn document_has_xfa(doc: &Document) -> bool {
+    doc.trailer
+        .get(b"Root")
+        .and_then(Object::as_reference)
+        .and_then(|root| doc.get_dictionary(root))
+        .and_then(|catalog| catalog.get(b"AcroForm"))
+        .ok()
+        .and_then(|acro_form| resolve(doc, acro_form))
+        .and_then(|acro_form| acro_form.as_dict().ok())
+        .is_some_and(|acro_form| acro_form.has(b"XFA"))
+}
+
+/// 逐页判断是否含有文字绘制指令：全部没有文字但有图片的视为扫描件。
+/// 只有矢量图形的页面按软件生成处理。
+pub fn classify_pdf(path: &Path) -> Option<PdfContentKind> {
+    let doc = Document::load(path).ok()?;
+    let (mut scanned, mut digital) = (0usize, 0usize);
+    for page_id in doc.get_pages().into_values() {
+        let content = doc.get_page_content(page_id).unwrap_or_default();
+        let has_text = !doc.get_page_fonts(page_id).is_empty()
+            && (contains_operator(&content, b"Tj") || contains_operator(&content, b"TJ"));
+        if !has_text && contains_operator(&content, b"Do") {
+            scanned += 1;
+        } else {
+            digital += 1;
+        }
+    }
+    match (scanned, digital) {
+        (0, 0) => None,
+        (_, 0) => Some(PdfContentKind::Scanned),
+        (0, _) => Some(PdfContentKind::Digital),
+        _ => Some(PdfContentKind::Mixed),
+    }
+}
+
+/// 内容流中是否出现某个操作符（前后须为空白或分隔符）。
+fn contains_operator(content: &[u8], operator: &[u8]) -> bool {
+    let is_boundary = |byte: Option<&u8>| {
+        byte.map_or(true, |byte| {
+            byte.is_ascii_whitespace() || b"()<>[]{}/%".contains(byte)
+        })
+    };
+    content
+        .windows(operator.len())
+        .enumerate()
+        .any(|(start, window)| {
+            window == operator
+                && is_boundary(start.checked_sub(1).and_then(|prev| content.get(prev)))
+                && is_boundary(content.get(start + operator.len()))
+        })
+}
+
+/// 校验 PDF 中每个签名的 ByteRange 摘要是否与签名中记录的 messageDigest 一致，
+/// 并检查最后一个签名是否覆盖到文件末尾。只比较摘要，不验证签名值与证书链。
+pub fn check_signatures(path: &Path) -> SignatureStatus {
+    let Ok(bytes) = fs::read(path) else {
+        return SignatureStatus::Unreadable;
+    };
+    let Ok(doc) = Document::load_mem(&bytes) else {
+        return SignatureStatus::Unreadable;
+    };
+
+    let mut status = SignatureStatus::Unsigned;
+    // 多个签名时，较早的签名只覆盖前面的版本，以覆盖最远的那个为准
+    let mut covered = 0;
+    for object in doc.objects.values() {
+        let Ok(dict) = object.as_dict() else {
+            continue;
+        };
+        let (Ok(byte_range), Ok(Object::String(contents, _))) =
+            (dict.get(b"ByteRange"), dict.get(b"Contents"))
+        else {
+            continue;
+        };
+        let Ok(byte_range) = byte_range.as_array() else {
+            continue;
+        };
+
+        let (result, end) = verify_signature(&bytes, byte_range, contents);
+        covered = covered.max(end);
+        status = match (status, result) {
+            (SignatureStatus::Broken, _) | (_, SignatureStatus::Broken) => SignatureStatus::Broken,
+            (SignatureStatus::Unknown, _) | (_, SignatureStatus::Unknown) => SignatureStatus::Unknown,
+            _ => SignatureStatus::DigestMatches,
+        };
+    }
+    if status != SignatureStatus::Unsigned
+        && status != SignatureStatus::Broken
+        && !only_whitespace(&bytes[covered..])
+    {
+        return SignatureStatus::Modified;
+    }
+    status
+}
+
+/// 部分软件在 `%%EOF` 后多写一个换行，不算追加内容
+fn only_whitespace(bytes: &[u8]) -> bool {
+    bytes
+        .iter()
+        .all(|byte| matches!(byte, b' ' | b'\t' | b'\r' | b'\n' | b'\0'))
+}
+
+/// 返回校验结果与该签名覆盖到的文件位置。
+fn verify_signature(bytes: &[u8], byte_range: &[Object], contents: &[u8]) -> (SignatureStatus, usize) {
+    let ranges: Vec<usize> = byte_range
+        .iter()
+        .filter_map(|obj| obj.as_i64().ok())
+        .filter_map(|value| usize::try_from(value).ok())
+        .collect();
+    let [start1, len1, start2, len2] = ranges[..] else {
+        return (SignatureStatus::Broken, 0);
+    };
+    if start1 != 0 || start1 + len1 > start2 || start2 + len2 > bytes.len() {
+        return (SignatureStatus::Broken, 0);
+    }
+    let end = start2 + len2;
+    (digest_status(bytes, [start1, len1, start2, len2], contents), end)
+}
+
+fn digest_status(bytes: &[u8], [start1, len1, start2, len2]: [usize; 4], contents: &[u8]) -> SignatureStatus {
+    let Some(expected) = find_message_digest(contents) else {
+        return SignatureStatus::Unknown;
+    };
+    let signed_parts = [&bytes[start1..start1 + len1], &bytes[start2..start2 + len2]];
+    let actual = match expected.len() {
+        20 => digest_parts::<Sha1>(&signed_parts),
+        32 => digest_parts::<Sha256>(&signed_parts),
+        48 => digest_parts::<Sha384>(&signed_parts),
+        64 => digest_parts::<Sha512>(&signed_parts),
+        _ => return SignatureStatus::Unknown,
+    };
+
+    if actual == expected {
+        SignatureStatus::DigestMatches
+    } else {
+        SignatureStatus::Broken
+    }
+}
+
+fn digest_parts<D: Digest>(parts: &[&[u8]]) -> Vec<u8> {
+    let mut hasher = D::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().to_vec()
+}
+
+/// 在 DER 编码的 CMS 结构中定位 messageDigest 属性：OID 之后紧跟 SET { OCTET STRING }。
+fn find_message_digest(der: &[u8]) -> Option<Vec<u8>> {
+    let position = der
+        .windows(MESSAGE_DIGEST_OID.len())
+        .position(|window| window == MESSAGE_DIGEST_OID)?;
+    let rest = &der[position + MESSAGE_DIGEST_OID.len()..];
+    if rest.len() < 4 || rest[0] != 0x31 || rest[2] != 0x04 {
+        return None;
+    }
+    let digest_len = rest[3] as usize;
+    rest.get(4..4 + digest_len).map(|digest| digest.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unreadable_pdfs_are_not_reported_as_signed() {
+        let dir = tempfile::tempdir().unwrap();
+        let broken = dir.path().join("broken.pdf");
+        fs::write(&broken, b"%PDF-1.7\nnot really a pdf").unwrap();
+        assert_eq!(check_signatures(&broken), SignatureStatus::Unreadable);
+        assert_eq!(
+            check_signatures(&dir.path().join("missing.pdf")),
+            SignatureStatus::Unreadable
+        );
+
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        doc.objects.insert(
+            pages_id,
+            lopdf::dictionary! { "Type" => "Pages", "Kids" => Vec::<Object>::new(), "Count" => 0 }.into(),
+        );
+        let catalog_id = doc.add_object(lopdf::dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+        let unsigned = dir.path().join("unsigned.pdf");
+        doc.save(&unsigned).unwrap();
+        assert_eq!(check_signatures(&unsigned), SignatureStatus::Unsigned);
+    }
+}
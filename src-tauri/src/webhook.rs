@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    path::Path,
+    time::Duration,
+};
+
+use crate::{replicate::file_sha256, FailedFile, MergeResult, PhaseTimings};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 合并完成后向团队看板/审批机器人发送通知。默认不配置即不联网；
+/// 为不引入 TLS 依赖，只支持内网 `http://` 地址。明文 HTTP 会暴露令牌，
+/// 因此配置了 `token` 时只允许发往本机回环地址（可由本机的 TLS 代理转发），其他地址直接拒绝。
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// 以 `Authorization: Bearer` 发送，可留空；非空时 `url` 必须指向 127.0.0.1、::1 等回环地址
+    pub token: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    output_path: &'a str,
+    sha256: Option<String>,
+    merged_files: usize,
+    page_count: usize,
+    skipped_files: &'a [String],
+    failures: &'a [FailedFile],
+    timings: &'a PhaseTimings,
+}
+
+impl WebhookConfig {
+    pub fn send(&self, result: &MergeResult) -> Result<(), String> {
+        let payload = Payload {
+            output_path: &result.output_path,
+            sha256: file_sha256(Path::new(&result.output_path)).ok(),
+            merged_files: result.merged_files,
+            page_count: result.page_count,
+            skipped_files: &result.skipped_files,
+            failures: &result.failures,
+            timings: &result.timings,
+        };
+        let body = serde_json::to_string(&payload).map_err(|err| err.to_string())?;
+        self.post(&body)
+    }
+
+    fn post(&self, body: &str) -> Result<(), String> {
+        let rest = self
+            .url
+            .trim()
+            .strip_prefix("http://")
+            .ok_or_else(|| format!("仅支持 http:// 地址: {}", self.url))?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+        let address = if authority.contains(':') {
+            authority.to_string()
+        } else {
+            format!("{authority}:80")
+        };
+        let socket = address
+            .to_socket_addrs()
+            .map_err(|err| err.to_string())?
+            .next()
+            .ok_or_else(|| format!("无法解析 {authority}"))?;
+        let token = self.token.as_deref().filter(|token| !token.is_empty());
+        if token.is_some() && !socket.ip().is_loopback() {
+            return Err(format!("令牌不会以明文 HTTP 发往非本机地址: {authority}"));
+        }
+
+        let mut stream = TcpStream::connect_timeout(&socket, TIMEOUT).map_err(|err| err.to_string())?;
+        stream
+            .set_read_timeout(Some(TIMEOUT))
+            .map_err(|err| err.to_string())?;
+        stream
+            .set_write_timeout(Some(TIMEOUT))
+            .map_err(|err| err.to_string())?;
+
+        let auth = token
+            .map(|token| format!("Authorization: Bearer {token}\r\n"))
+            .unwrap_or_default();
+        write!(
+            stream,
+            "POST {path} HTTP/1.1\r\nHost: {authority}\r\nContent-Type: application/json; charset=utf-8\r\n{auth}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+        .map_err(|err| err.to_string())?;
+
+        let mut response = String::new();
+        let _ = stream.take(4096).read_to_string(&mut response);
+        let status = response
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or_else(|| "无效的响应".to_string())?;
+        if (200..300).contains(&status) {
+            Ok(())
+        } else {
+            Err(format!("服务器返回 {status}"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_is_never_sent_to_remote_plain_http() {
+        let config = WebhookConfig {
+            url: "http://192.0.2.10:8080/hook".into(),
+            token: Some("secret".into()),
+        };
+        let err = config.post("{}").unwrap_err();
+        assert!(err.contains("明文"), "带令牌发往非回环地址应在连接前拒绝: {err}");
+    }
+}
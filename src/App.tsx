@@ -4,7 +4,15 @@ import { open as openDialog } from "@tauri-apps/api/dialog";
 import { listen } from "@tauri-apps/api/event";
 import MergeSummaryDialog from "@components/MergeSummaryDialog";
 import FileList from "@components/FileList";
-import type { InvoiceFile, MergeResult, ProgressPayload } from "@shared-types/index";
+import type {
+  ConflictAction,
+  DeepLink,
+  InvoiceFile,
+  MergeResult,
+  OrderImport,
+  PasswordRequest,
+  ProgressPayload
+} from "@shared-types/index";
 import { formatBytes } from "@lib/format";
 import { useFilePreviews } from "@lib/useFilePreviews";
 import type { FilePreview } from "@lib/useFilePreviews";
@@ -62,6 +70,15 @@ function App() {
   const [isMerging, setIsMerging] = useState(false);
   const [progress, setProgress] = useState(0);
   const [customName, setCustomName] = useState("");
+  const [coverPdf, setCoverPdf] = useState<string | null>(null);
+  const [outputDir, setOutputDir] = useState<string | null>(null);
+  const [compressionQuality, setCompressionQuality] = useState(0);
+  const [targetSizeMb, setTargetSizeMb] = useState("");
+  const [orderText, setOrderText] = useState("");
+  const [passwordRequest, setPasswordRequest] = useState<PasswordRequest | null>(null);
+  const [passwordInput, setPasswordInput] = useState("");
+  const [conflict, setConflict] = useState<{ path: string; req: Record<string, unknown> } | null>(null);
+  const [pendingLink, setPendingLink] = useState<DeepLink | null>(null);
   const [dialog, setDialog] = useState<DialogState>(defaultDialog);
   const [selectedMap, setSelectedMap] = useState<Record<string, boolean>>({});
   const [viewMode, setViewMode] = useState<ViewMode>("grid");
@@ -69,12 +86,14 @@ function App() {
   const [themeMode, setThemeMode] = useState<ThemeMode>("dark");
   const [activeTheme, setActiveTheme] = useState<ThemeAppearance>("dark");
   const [showSettings, setShowSettings] = useState(false);
+  const [contextMenuEnabled, setContextMenuEnabled] = useState(false);
+  const [trayMode, setTrayMode] = useState(false);
   const [showSortMenu, setShowSortMenu] = useState(false);
   const [statusState, setStatusState] = useState<StatusState>({ kind: "idle" });
   const [pageSelections, setPageSelections] = useState<Record<string, number>>({});
 
   const t = translations[lang];
-  const { previews, loading: previewLoading } = useFilePreviews(files);
+  const { previews, loading: previewLoading } = useFilePreviews(files, folderPath);
   const accentPalette = useMemo(
     () =>
       files.reduce<Record<string, string>>((map, file, index) => {
@@ -106,9 +125,10 @@ function App() {
 
   useEffect(() => {
     const unlistenPromise = listen<ProgressPayload>("merge-progress", (event) => {
-      const { current, total, phase } = event.payload;
+      const { current, total, processed_bytes, total_bytes, phase } = event.payload;
       if (!total) return;
-      setProgress(Math.round((current / total) * 100));
+      const ratio = total_bytes > 0 ? processed_bytes / total_bytes : current / total;
+      setProgress(Math.round(ratio * 100));
       setStatusState({ kind: "progress", phase, current, total });
     });
 
@@ -117,6 +137,23 @@ function App() {
     };
   }, []);
 
+  useEffect(() => {
+    const unlistenPromise = listen<PasswordRequest>("password-required", (event) => {
+      setPasswordInput("");
+      setPasswordRequest(event.payload);
+    });
+
+    return () => {
+      unlistenPromise.then((unlisten) => unlisten());
+    };
+  }, []);
+
+  const answerPassword = useCallback((password: string | null) => {
+    setPasswordRequest(null);
+    setPasswordInput("");
+    invoke("provide_pdf_password_cmd", { password }).catch(console.error);
+  }, []);
+
   useEffect(() => {
     setSelectedMap((prev) => {
       const next: Record<string, boolean> = {};
@@ -140,12 +177,7 @@ function App() {
     });
   }, [files, previewMap]);
 
-  const selectFolder = useCallback(async () => {
-    const folder = await openDialog({ directory: true, multiple: false });
-    if (!folder || Array.isArray(folder)) {
-      return;
-    }
-
+  const loadFolder = useCallback(async (folder: string) => {
     setStatusState({ kind: "scanning" });
     try {
       const result = await invoke<InvoiceFile[]>("scan_folder_cmd", { folderPath: folder });
@@ -154,12 +186,63 @@ function App() {
       setFiles(initial);
       setSortConfig({ field: "file_name", direction: "asc" });
       setStatusState({ kind: "found", count: result.length });
+      return result;
     } catch (error) {
       console.error(error);
       setStatusState({ kind: "error", message: t.statusText.scanError });
+      return null;
     }
   }, [t.statusText.scanError]);
 
+  const selectFolder = useCallback(async () => {
+    const folder = await openDialog({ directory: true, multiple: false });
+    if (!folder || Array.isArray(folder)) {
+      return;
+    }
+    await loadFolder(folder);
+  }, [loadFolder]);
+
+  const selectCover = useCallback(async () => {
+    const cover = await openDialog({ multiple: false, filters: [{ name: "PDF", extensions: ["pdf"] }] });
+    if (!cover || Array.isArray(cover)) {
+      return;
+    }
+    setCoverPdf(cover);
+  }, []);
+
+  const selectOutputDir = useCallback(async () => {
+    const dir = await openDialog({ directory: true, multiple: false });
+    if (!dir || Array.isArray(dir)) {
+      return;
+    }
+    setOutputDir(dir);
+  }, []);
+
+  useEffect(() => {
+    invoke<string | null>("launch_folder_cmd")
+      .then((folder) => {
+        if (folder) loadFolder(folder);
+      })
+      .catch(console.error);
+    invoke<boolean>("context_menu_status_cmd").then(setContextMenuEnabled).catch(console.error);
+  }, []);
+
+  const toggleTrayMode = useCallback(async () => {
+    const next = !trayMode;
+    await invoke("set_tray_mode_cmd", { enabled: next });
+    setTrayMode(next);
+  }, [trayMode]);
+
+  const toggleContextMenu = useCallback(async () => {
+    const next = !contextMenuEnabled;
+    try {
+      await invoke("set_context_menu_cmd", { enabled: next });
+      setContextMenuEnabled(next);
+    } catch (error) {
+      console.error(error);
+    }
+  }, [contextMenuEnabled]);
+
   const selectedFiles = useMemo(
     () => files.filter((file) => selectedMap[file.path] ?? true),
     [files, selectedMap]
@@ -189,25 +272,21 @@ function App() {
     [files]
   );
 
-  const handleMerge = useCallback(async () => {
-    if (!folderPath || !selectedFiles.length) return;
-
+  const runMerge = useCallback(async (req: Record<string, unknown>) => {
     setIsMerging(true);
     setProgress(0);
     setDialog(defaultDialog);
     setStatusState({ kind: "merging" });
 
     try {
-      const result = await invoke<MergeResult>("merge_invoices_cmd", {
-        req: {
-          folder_path: folderPath,
-          files: selectedFiles,
-          sort_mode: sortConfig ? (sortConfig.field === "modified_ts" ? "ModifiedAsc" : "FileNameAsc") : "Custom",
-          output_file_name: customName.trim() ? customName.trim() : null
-        }
-      });
+      const result = await invoke<MergeResult>("merge_invoices_cmd", { req });
 
-      if (result.success) {
+      if (result.cancelled) {
+        setStatusState({ kind: "idle" });
+      } else if (result.needs_confirmation) {
+        setConflict({ path: result.needs_confirmation, req });
+        setStatusState({ kind: "idle" });
+      } else if (result.success) {
         const failText = result.failed_files.length ? ` (${result.failed_files.length} failed)` : "";
         setDialog({
           open: true,
@@ -242,7 +321,75 @@ function App() {
     } finally {
       setIsMerging(false);
     }
-  }, [folderPath, selectedFiles, sortConfig, customName, t.successMsg, t.successTitle, t.statusText.mergeError]);
+  }, [t.successMsg, t.successTitle, t.statusText.mergeError]);
+
+  const resolveConflict = useCallback(
+    (action: ConflictAction) => {
+      const pending = conflict;
+      setConflict(null);
+      if (pending && action !== "cancel") {
+        runMerge({ ...pending.req, on_conflict: action });
+      }
+    },
+    [conflict, runMerge]
+  );
+
+  const cancelMerge = useCallback(() => {
+    invoke("cancel_merge_cmd").catch(console.error);
+  }, []);
+
+  const handleMerge = useCallback(async () => {
+    if (!folderPath || !selectedFiles.length) return;
+
+    await runMerge({
+      folder_path: folderPath,
+      files: selectedFiles,
+      sort_mode: sortConfig ? (sortConfig.field === "modified_ts" ? "ModifiedAsc" : "FileNameAsc") : "Custom",
+      output_file_name: customName.trim() ? customName.trim() : null,
+      cover_pdf: coverPdf,
+      output_dir: outputDir,
+      compression_quality: compressionQuality > 0 ? compressionQuality : null,
+      max_output_mb: Number(targetSizeMb) > 0 ? Number(targetSizeMb) : null
+    });
+  }, [
+    folderPath,
+    selectedFiles,
+    sortConfig,
+    customName,
+    coverPdf,
+    outputDir,
+    compressionQuality,
+    targetSizeMb,
+    runMerge
+  ]);
+
+  // 链接可能来自任意网页，只记下来等用户确认，不自动扫描或合并
+  useEffect(() => {
+    invoke<DeepLink | null>("launch_link_cmd")
+      .then((link) => setPendingLink(link))
+      .catch((error) => {
+        console.error(error);
+        setStatusState({ kind: "error", message: String(error) });
+      });
+  }, []);
+
+  const resolveLink = useCallback(
+    async (accepted: boolean) => {
+      const link = pendingLink;
+      setPendingLink(null);
+      if (!link || !accepted) return;
+      const scanned = await loadFolder(link.folder);
+      if (!scanned?.length) return;
+      await runMerge({
+        folder_path: link.folder,
+        files: scanned,
+        sort_mode: link.sort_mode,
+        descending: link.descending,
+        output_file_name: link.output_file_name
+      });
+    },
+    [pendingLink, loadFolder, runMerge]
+  );
 
   const closeDialog = useCallback(() => setDialog(defaultDialog), []);
 
@@ -269,6 +416,37 @@ function App() {
     setSortConfig(null);
   }, []);
 
+  const exportOrder = useCallback(async () => {
+    if (!folderPath) return;
+    try {
+      await invoke<string>("export_order_cmd", { folderPath, files });
+      setShowSortMenu(false);
+    } catch (error) {
+      console.error(error);
+      setStatusState({ kind: "error", message: String(error) });
+    }
+  }, [folderPath, files]);
+
+  const importOrder = useCallback(async () => {
+    if (!folderPath) return;
+    try {
+      const result = await invoke<OrderImport>("import_order_cmd", {
+        folderPath,
+        files,
+        text: orderText.trim() ? orderText : null
+      });
+      handleReorder(result.files);
+      setOrderText("");
+      setShowSortMenu(false);
+      if (result.unmatched.length) {
+        setStatusState({ kind: "error", message: `${t.orderUnmatched}: ${result.unmatched.join(", ")}` });
+      }
+    } catch (error) {
+      console.error(error);
+      setStatusState({ kind: "error", message: String(error) });
+    }
+  }, [folderPath, files, orderText, handleReorder, t.orderUnmatched]);
+
   const themeStyles: ThemeStyles =
     activeTheme === "dark"
       ? {
@@ -480,6 +658,30 @@ function App() {
                         ))}
                       </div>
                     </div>
+
+                    <div className={`p-2 rounded-xl ${activeTheme === "dark" ? "bg-white/5" : "bg-slate-50"}`}>
+                      <span className={`text-xs font-bold uppercase tracking-wider mb-2 block ${themeStyles.textSub}`}>
+                        {t.contextMenu}
+                      </span>
+                      <button
+                        onClick={toggleContextMenu}
+                        className={`w-full py-1.5 text-xs font-medium rounded-md transition ${
+                          contextMenuEnabled
+                            ? "bg-indigo-600 text-white shadow-lg shadow-indigo-500/25"
+                            : themeStyles.textSub
+                        }`}
+                      >
+                        {contextMenuEnabled ? t.contextMenuOn : t.contextMenuOff}
+                      </button>
+                      <button
+                        onClick={toggleTrayMode}
+                        className={`w-full mt-2 py-1.5 text-xs font-medium rounded-md transition ${
+                          trayMode ? "bg-indigo-600 text-white shadow-lg shadow-indigo-500/25" : themeStyles.textSub
+                        }`}
+                      >
+                        {t.trayMode}
+                      </button>
+                    </div>
                   </div>
                 </>
               ) : null}
@@ -528,6 +730,55 @@ function App() {
                 />
                 <span className={`absolute right-3 top-3 text-xs font-mono ${themeStyles.textSub}`}>.pdf</span>
               </div>
+              <div className={`flex items-center gap-2 text-xs ${themeStyles.textSub}`}>
+                <span>{t.coverPdf}:</span>
+                <button onClick={selectCover} className="truncate max-w-[240px] text-violet-400 hover:underline">
+                  {coverPdf ? coverPdf.split(/[\\/]/).pop() : t.selectCover}
+                </button>
+                {coverPdf && (
+                  <button onClick={() => setCoverPdf(null)} className="hover:text-rose-400">
+                    ×
+                  </button>
+                )}
+              </div>
+              <div className={`flex items-center gap-2 text-xs ${themeStyles.textSub}`}>
+                <span>{t.outputDir}:</span>
+                <button
+                  onClick={selectOutputDir}
+                  title={outputDir ?? undefined}
+                  className="truncate max-w-[240px] text-violet-400 hover:underline"
+                >
+                  {outputDir ? outputDir.split(/[\\/]/).pop() : t.sameAsSource}
+                </button>
+                {outputDir && (
+                  <button onClick={() => setOutputDir(null)} className="hover:text-rose-400">
+                    ×
+                  </button>
+                )}
+              </div>
+              <div className={`flex items-center gap-2 text-xs ${themeStyles.textSub}`}>
+                <span>{t.compression}:</span>
+                <input
+                  type="range"
+                  min={0}
+                  max={100}
+                  step={5}
+                  value={compressionQuality}
+                  onChange={(event) => setCompressionQuality(Number(event.target.value))}
+                  className="w-28 accent-violet-500"
+                />
+                <span className="w-10 text-violet-400">
+                  {compressionQuality > 0 ? compressionQuality : t.compressionOff}
+                </span>
+                <input
+                  type="number"
+                  min={0}
+                  value={targetSizeMb}
+                  onChange={(event) => setTargetSizeMb(event.target.value)}
+                  placeholder={t.targetSizeMb}
+                  className={`w-28 rounded-lg px-2 py-1 border focus:outline-none ${themeStyles.inputBg}`}
+                />
+              </div>
             </div>
             <div className="space-y-2">
               <label className="text-xs font-semibold uppercase tracking-widest opacity-0 select-none flex items-center gap-2">
@@ -595,6 +846,27 @@ function App() {
                           {option.label}
                         </button>
                       ))}
+                      <div className={`mt-2 pt-2 border-t space-y-2 ${activeTheme === "dark" ? "border-white/10" : "border-slate-200"}`}>
+                        <textarea
+                          value={orderText}
+                          onChange={(event) => setOrderText(event.target.value)}
+                          placeholder={t.orderPastePlaceholder}
+                          rows={3}
+                          className={`w-full text-xs rounded-xl px-3 py-2 border focus:outline-none ${themeStyles.inputBg}`}
+                        />
+                        <button
+                          onClick={importOrder}
+                          className={`w-full text-left text-sm px-3 py-2 rounded-xl transition ${themeStyles.textMain}`}
+                        >
+                          {t.importOrder}
+                        </button>
+                        <button
+                          onClick={exportOrder}
+                          className={`w-full text-left text-sm px-3 py-2 rounded-xl transition ${themeStyles.textMain}`}
+                        >
+                          {t.exportOrder}
+                        </button>
+                      </div>
                     </div>
                   </>
                 ) : null}
@@ -662,6 +934,14 @@ function App() {
               />
             </div>
           </div>
+          {isMerging && (
+            <button
+              onClick={cancelMerge}
+              className={`whitespace-nowrap px-4 py-3 rounded-xl text-sm border transition ${themeStyles.toolbarBtn}`}
+            >
+              {t.cancelMerge}
+            </button>
+          )}
           <button
             onClick={handleMerge}
             disabled={!selectedCount || !folderPath || isMerging}
@@ -700,6 +980,96 @@ function App() {
         variant={dialog.variant}
         theme={activeTheme}
       />
+      {passwordRequest && (
+        <div className="fixed inset-0 z-50 flex items-center justify-center bg-black/50">
+          <div className={`w-96 rounded-2xl border p-6 space-y-4 ${activeTheme === "dark" ? "bg-[#1a1d24] border-white/10" : "bg-white border-slate-200"}`}>
+            <p className={`text-sm font-semibold ${themeStyles.textHead}`}>{t.passwordTitle}</p>
+            <p className={`text-xs break-all ${themeStyles.textSub}`}>
+              {passwordRequest.file_name}
+              {passwordRequest.attempt > 1 ? ` — ${t.passwordWrong}` : ""}
+            </p>
+            <input
+              type="password"
+              autoFocus
+              value={passwordInput}
+              onChange={(event) => setPasswordInput(event.target.value)}
+              onKeyDown={(event) => {
+                if (event.key === "Enter") answerPassword(passwordInput);
+              }}
+              className={`w-full text-sm rounded-xl px-4 py-2 border focus:outline-none focus:ring-2 focus:ring-violet-500/40 ${themeStyles.inputBg}`}
+            />
+            <div className="flex justify-end gap-2">
+              <button
+                onClick={() => answerPassword(null)}
+                className={`px-4 py-2 rounded-xl text-sm border transition ${themeStyles.toolbarBtn}`}
+              >
+                {t.passwordSkip}
+              </button>
+              <button
+                onClick={() => answerPassword(passwordInput)}
+                className={`px-4 py-2 rounded-xl text-sm font-semibold text-white bg-gradient-to-r ${themeStyles.accentGradient}`}
+              >
+                {t.passwordSubmit}
+              </button>
+            </div>
+          </div>
+        </div>
+      )}
+      {conflict && (
+        <div className="fixed inset-0 z-50 flex items-center justify-center bg-black/50">
+          <div className={`w-96 rounded-2xl border p-6 space-y-4 ${activeTheme === "dark" ? "bg-[#1a1d24] border-white/10" : "bg-white border-slate-200"}`}>
+            <p className={`text-sm font-semibold ${themeStyles.textHead}`}>{t.conflictTitle}</p>
+            <p className={`text-xs break-all ${themeStyles.textSub}`}>{conflict.path}</p>
+            <div className="flex justify-end gap-2">
+              <button
+                onClick={() => resolveConflict("cancel")}
+                className={`px-4 py-2 rounded-xl text-sm border transition ${themeStyles.toolbarBtn}`}
+              >
+                {t.cancelMerge}
+              </button>
+              <button
+                onClick={() => resolveConflict("overwrite")}
+                className={`px-4 py-2 rounded-xl text-sm border transition ${themeStyles.toolbarBtn}`}
+              >
+                {t.conflictOverwrite}
+              </button>
+              <button
+                onClick={() => resolveConflict("rename")}
+                className={`px-4 py-2 rounded-xl text-sm font-semibold text-white bg-gradient-to-r ${themeStyles.accentGradient}`}
+              >
+                {t.conflictRename}
+              </button>
+            </div>
+          </div>
+        </div>
+      )}
+      {pendingLink && (
+        <div className="fixed inset-0 z-50 flex items-center justify-center bg-black/50">
+          <div className={`w-96 rounded-2xl border p-6 space-y-4 ${activeTheme === "dark" ? "bg-[#1a1d24] border-white/10" : "bg-white border-slate-200"}`}>
+            <p className={`text-sm font-semibold ${themeStyles.textHead}`}>{t.linkTitle}</p>
+            <p className={`text-xs break-all ${themeStyles.textSub}`}>
+              {t.linkFolder}: {pendingLink.folder}
+            </p>
+            <p className={`text-xs break-all ${themeStyles.textSub}`}>
+              {t.linkOutput}: {pendingLink.output_file_name ?? t.linkDefaultOutput}
+            </p>
+            <div className="flex justify-end gap-2">
+              <button
+                onClick={() => resolveLink(false)}
+                className={`px-4 py-2 rounded-xl text-sm border transition ${themeStyles.toolbarBtn}`}
+              >
+                {t.cancelMerge}
+              </button>
+              <button
+                onClick={() => resolveLink(true)}
+                className={`px-4 py-2 rounded-xl text-sm font-semibold text-white bg-gradient-to-r ${themeStyles.accentGradient}`}
+              >
+                {t.mergeExport}
+              </button>
+            </div>
+          </div>
+        </div>
+      )}
       <style>{`
         .custom-scrollbar::-webkit-scrollbar {
           width: 0;
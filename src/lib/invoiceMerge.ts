@@ -0,0 +1,66 @@
+import { invoke } from "@tauri-apps/api/tauri";
+import type {
+  CleanupReport,
+  ConflictAction,
+  DroppedPaths,
+  FileValidation,
+  InvoiceFile,
+  MergePlan,
+  MergeRequest,
+  MergeResult,
+  ScanPage
+} from "@shared-types/index";
+
+const PLUGIN = "plugin:invoice-merge";
+
+function call<T>(command: string, args?: Record<string, unknown>): Promise<T> {
+  return invoke<T>(`${PLUGIN}|${command}`, args);
+}
+
+export function scanFolder(folderPath: string, extraExtensions?: string[], enrich?: boolean) {
+  return call<InvoiceFile[]>("scan_folder_cmd", { folderPath, extraExtensions, enrich });
+}
+
+export function scanFolderStream(folderPath: string, extraExtensions?: string[]) {
+  return call<number>("scan_folder_stream_cmd", { folderPath, extraExtensions });
+}
+
+export function scanFolderPage(folderPath: string, offset: number, limit: number, extraExtensions?: string[]) {
+  return call<ScanPage>("scan_folder_page_cmd", { folderPath, extraExtensions, offset, limit });
+}
+
+export function validateFiles(files: InvoiceFile[]) {
+  return call<FileValidation[]>("validate_files_cmd", { files });
+}
+
+export function validateDroppedPaths(paths: string[], existing: string[], folderPath?: string) {
+  return call<DroppedPaths>("validate_dropped_paths_cmd", { paths, existing, folderPath });
+}
+
+export function planMerge(req: MergeRequest) {
+  return call<MergePlan>("plan_merge_cmd", { req });
+}
+
+export function mergeInvoices(req: MergeRequest) {
+  return call<MergeResult>("merge_invoices_cmd", { req });
+}
+
+export function previewMerge(req: MergeRequest) {
+  return call<MergeResult>("preview_merge_cmd", { req });
+}
+
+export function commitMerge(previewPath: string, keep: boolean, onConflict?: ConflictAction) {
+  return call<MergeResult>("commit_merge_cmd", { previewPath, keep, onConflict });
+}
+
+export function cancelMerge() {
+  return call<void>("cancel_merge_cmd");
+}
+
+export function providePdfPassword(password: string | null) {
+  return call<boolean>("provide_pdf_password_cmd", { password });
+}
+
+export function cleanTemp(tempDir?: string, maxAgeHours?: number) {
+  return call<CleanupReport>("clean_temp_cmd", { tempDir, maxAgeHours });
+}
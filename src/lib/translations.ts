@@ -18,12 +18,35 @@ export const translations = {
     merging: "正在合并",
     into: "输出到",
     mergeExport: "合并 & 导出",
+    cancelMerge: "取消",
+    passwordTitle: "该 PDF 已加密，请输入打开密码",
+    passwordWrong: "密码错误，请重试",
+    passwordSkip: "跳过此文件",
+    passwordSubmit: "确定",
+    conflictTitle: "输出文件已存在",
+    conflictOverwrite: "覆盖",
+    conflictRename: "自动重命名",
+    linkTitle: "外部链接请求合并以下文件夹，是否继续？",
+    linkFolder: "文件夹",
+    linkOutput: "输出文件名",
+    linkDefaultOutput: "默认名称",
     successTitle: "合并成功！",
     successMsg: "文件已成功合并并保存为",
     close: "关闭",
     open: "打开文件",
     theme: "主题外观",
     language: "语言设置",
+    contextMenu: "右键菜单",
+    contextMenuOn: "已添加到文件夹右键菜单",
+    contextMenuOff: "添加到文件夹右键菜单",
+    trayMode: "关闭窗口时最小化到托盘",
+    coverPdf: "封面",
+    selectCover: "选择封面 PDF",
+    outputDir: "输出目录",
+    sameAsSource: "与源文件夹相同",
+    compression: "压缩画质",
+    compressionOff: "不压缩",
+    targetSizeMb: "大小上限 MB",
     searchPlaceholder: "搜索路径...",
     selectFolder: "选择文件夹",
     emptyStateNoFolder: "尚未选择发票文件夹。",
@@ -32,6 +55,10 @@ export const translations = {
     sortTitle: "排序方式",
     sortFileNameAsc: "按文件名（A-Z）",
     sortModifiedAsc: "按修改时间（旧→新）",
+    importOrder: "导入顺序",
+    exportOrder: "导出当前顺序",
+    orderPastePlaceholder: "粘贴文件名列表，留空则读取文件夹中的合并顺序.txt",
+    orderUnmatched: "以下文件未找到",
     previewLoading: "预览生成中…",
     previewUnavailable: "无法生成预览",
     pageIndicator: "第 {current} / {total} 页",
@@ -74,12 +101,35 @@ export const translations = {
     merging: "Merging",
     into: "into",
     mergeExport: "Merge & Export",
+    cancelMerge: "Cancel",
+    passwordTitle: "This PDF is encrypted. Enter its password",
+    passwordWrong: "Wrong password, try again",
+    passwordSkip: "Skip this file",
+    passwordSubmit: "OK",
+    conflictTitle: "The output file already exists",
+    conflictOverwrite: "Overwrite",
+    conflictRename: "Rename automatically",
+    linkTitle: "An external link asks to merge this folder. Continue?",
+    linkFolder: "Folder",
+    linkOutput: "Output name",
+    linkDefaultOutput: "Default name",
     successTitle: "Success!",
     successMsg: "Files successfully merged into",
     close: "Close",
     open: "Open File",
     theme: "Appearance",
     language: "Language",
+    contextMenu: "Context Menu",
+    contextMenuOn: "Added to folder context menu",
+    contextMenuOff: "Add to folder context menu",
+    trayMode: "Minimize to tray on close",
+    coverPdf: "Cover",
+    selectCover: "Choose cover PDF",
+    outputDir: "Output folder",
+    sameAsSource: "Same as source",
+    compression: "Compression",
+    compressionOff: "Off",
+    targetSizeMb: "Max size MB",
     searchPlaceholder: "Search path...",
     selectFolder: "Choose Folder",
     emptyStateNoFolder: "No folder selected yet.",
@@ -88,6 +138,10 @@ export const translations = {
     sortTitle: "Sort",
     sortFileNameAsc: "File Name (A-Z)",
     sortModifiedAsc: "Modified Date (Oldest first)",
+    importOrder: "Import order",
+    exportOrder: "Export current order",
+    orderPastePlaceholder: "Paste a list of file names, or leave empty to read 合并顺序.txt from the folder",
+    orderUnmatched: "Not found",
     previewLoading: "Rendering preview…",
     previewUnavailable: "Preview unavailable",
     pageIndicator: "Page {current}/{total}",
@@ -1,12 +1,14 @@
 import { useEffect, useState } from "react";
-import { convertFileSrc } from "@tauri-apps/api/tauri";
+import { convertFileSrc, invoke } from "@tauri-apps/api/tauri";
 import { readBinaryFile } from "@tauri-apps/api/fs";
 import { GlobalWorkerOptions, getDocument } from "pdfjs-dist";
 import pdfWorkerSrc from "pdfjs-dist/build/pdf.worker.min.mjs?url";
 import type { InvoiceFile } from "@shared-types/index";
 
 const IMAGE_EXTENSIONS = ["jpg", "jpeg", "png", "bmp", "gif", "tiff", "webp", "heic"];
+const WEBVIEW_UNSUPPORTED = ["tiff", "heic"];
 const PDF_PREVIEW_SCALE = 0.45;
+const THUMBNAIL_MAX_PX = 480;
 
 GlobalWorkerOptions.workerSrc = pdfWorkerSrc;
 
@@ -23,7 +25,7 @@ export interface FilePreview {
   error?: string;
 }
 
-export const useFilePreviews = (files: InvoiceFile[]) => {
+export const useFilePreviews = (files: InvoiceFile[], folderPath: string) => {
   const [previews, setPreviews] = useState<FilePreview[]>([]);
   const [loading, setLoading] = useState(false);
 
@@ -46,6 +48,19 @@ export const useFilePreviews = (files: InvoiceFile[]) => {
         const ext = file.ext.toLowerCase();
 
         try {
+          if (WEBVIEW_UNSUPPORTED.includes(ext)) {
+            const png = await invoke<string>("get_thumbnail_cmd", {
+              folderPath,
+              path: file.path,
+              maxPx: THUMBNAIL_MAX_PX
+            });
+            next.push({
+              file,
+              pages: [{ pageNumber: 1, url: `data:image/png;base64,${png}`, width: 0, height: 0 }]
+            });
+            continue;
+          }
+
           if (IMAGE_EXTENSIONS.includes(ext)) {
             next.push({
               file,
@@ -89,7 +104,7 @@ export const useFilePreviews = (files: InvoiceFile[]) => {
     return () => {
       cancelled = true;
     };
-  }, [files]);
+  }, [files, folderPath]);
 
   return { previews, loading };
 };
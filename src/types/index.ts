@@ -1,22 +1,362 @@
+export interface InvoiceInfo {
+  invoice_number: string;
+  issue_date: string;
+  total_amount: string;
+  seller_name: string;
+  buyer_name: string;
+}
+
 export type InvoiceFile = {
+  id: string;
   path: string;
   file_name: string;
   ext: string;
   modified_ts: number;
   size: number;
+  invoice_info?: InvoiceInfo | null;
+  page_size?: PageSize | null;
+  page_rotations?: Record<number, number>;
+  page_ranges?: string | null;
+  details?: FileDetails | null;
 };
 
-export type SortMode = "FileNameAsc" | "ModifiedAsc" | "Custom";
+export interface FileDetails {
+  width?: number | null;
+  height?: number | null;
+  page_count?: number | null;
+  encrypted: boolean;
+}
+
+export interface RejectedPath {
+  path: string;
+  reason: string;
+}
+
+export interface OrderImport {
+  files: InvoiceFile[];
+  unmatched: string[];
+  unlisted: number;
+}
+
+export interface DroppedPaths {
+  accepted: InvoiceFile[];
+  rejected: RejectedPath[];
+}
+
+export interface ScanPage {
+  files: InvoiceFile[];
+  total: number;
+  offset: number;
+}
+
+export type PageSize = "A4" | "A5" | "A3" | "Letter";
+
+export type PageOrientation = "portrait" | "landscape" | "auto";
+
+export interface NUpLayout {
+  per_page?: 2 | 4 | 8;
+  gutter_mm?: number;
+  cut_lines?: boolean;
+}
+
+export interface PageLayout {
+  orientation?: PageOrientation;
+  fit_to_image?: boolean;
+  auto_rotate?: boolean;
+}
+
+export type SortMode = "FileNameAsc" | "ModifiedAsc" | "InvoiceNumberAsc" | "Custom";
+
+export interface DeepLink {
+  folder: string;
+  sort_mode: SortMode;
+  descending: boolean;
+  output_file_name?: string | null;
+}
+
+export interface AppConfig {
+  default_sort?: SortMode | null;
+  page_size?: PageSize | null;
+  output_template?: string | null;
+  dpi?: number | null;
+  allowlist: string[];
+  profiles: Record<string, MergeProfile>;
+  post_merge_hook?: PostMergeHook | null;
+  webhook?: WebhookConfig | null;
+  duplicate_db?: string | null;
+  max_threads?: number | null;
+  max_file_mb?: number | null;
+  decode_timeout_secs?: number | null;
+}
+
+export interface WebhookConfig {
+  url: string;
+  token?: string | null;
+}
+
+export interface PostMergeHook {
+  command: string;
+  args: string[];
+  timeout_secs?: number | null;
+}
+
+export interface MergeProfile {
+  page_size?: PageSize | null;
+  downsample?: DownsampleOptions | null;
+  compression_quality?: number | null;
+  max_output_mb?: number | null;
+  output_template?: string | null;
+  crop_to_content: boolean;
+  skip_blank_images: boolean;
+  auto_enhance: boolean;
+  remove_shadows: boolean;
+  correct_perspective: boolean;
+  denoise: boolean;
+  failure_placeholders: boolean;
+  failure_appendix: boolean;
+  summary_page: boolean;
+}
+
+export interface Policy {
+  forced_output_dir?: string | null;
+  forbidden: string[];
+}
+
+export interface HttpApiInfo {
+  port: number;
+  token: string;
+}
+
+export interface CrashReport {
+  file_name: string;
+  created_ts: number;
+  content: string;
+}
+
+export interface UpdateInfo {
+  current_version: string;
+  version: string;
+  notes?: string | null;
+  date?: string | null;
+}
+
+export interface AttachmentStamp {
+  prefix: string;
+  start: number;
+  font_size: number;
+}
+
+export interface SourceFooter {
+  font_size: number;
+  position: StampPosition;
+}
+
+export type StampPosition = "top_left" | "top_right" | "bottom_left" | "bottom_center" | "bottom_right";
+
+export interface SplitOptions {
+  max_mb?: number | null;
+  max_files?: number | null;
+}
+
+export interface BatesNumbering {
+  prefix: string;
+  start: number;
+  digits: number;
+  position: StampPosition;
+  font_size: number;
+}
+
+export type InsertRule = {
+  pdf?: string | null;
+  label?: string | null;
+} & ({ at: "before_first"; contains: string } | { at: "after_every"; every: number });
+
+export interface MergeRequest {
+  folder_path: string;
+  files?: InvoiceFile[];
+  file_ids?: string[];
+  sort_mode: SortMode;
+  page_size?: PageSize | null;
+  page_layout?: PageLayout;
+  n_up?: NUpLayout | null;
+  crop_to_content?: boolean;
+  crop_padding_mm?: number | null;
+  descending?: boolean;
+  output_file_name?: string | null;
+  downsample?: DownsampleOptions | null;
+  max_output_mb?: number | null;
+  oversize_warning_mb?: number | null;
+  match_source_mtime?: boolean;
+  cache_limit_mb?: number | null;
+  temp_dir?: string | null;
+  output_dir?: string | null;
+  fallback_output_dir?: string | null;
+  rasterize_xfa?: boolean;
+  skip_blank_images?: boolean;
+  auto_enhance?: boolean;
+  remove_shadows?: boolean;
+  correct_perspective?: boolean;
+  denoise?: boolean;
+  failure_placeholders?: boolean;
+  failure_appendix?: boolean;
+  summary_page?: boolean;
+  on_conflict?: ConflictAction | null;
+  profile?: string | null;
+  secondary_output_dir?: string | null;
+  append_to?: string | null;
+  cover_pdf?: string | null;
+  insertions?: InsertRule[];
+  attachment_stamp?: AttachmentStamp | null;
+  bates?: BatesNumbering | null;
+  source_footer?: SourceFooter | null;
+  split?: SplitOptions | null;
+  tagged_pdf?: boolean;
+  max_threads?: number | null;
+  max_file_mb?: number | null;
+  decode_timeout_secs?: number | null;
+  page_ranges?: string | null;
+}
+
+export interface DownsampleOptions {
+  threshold_dpi: number;
+  target_dpi: number;
+  jpeg_quality: number;
+  binarize?: boolean;
+}
+
+export type PageSourceKind = "source" | "cover" | "insert" | "placeholder" | "appendix" | "existing" | "summary";
+
+export interface PageRange {
+  start_page: number;
+  end_page: number;
+  file_name: string;
+  path: string;
+  kind: PageSourceKind;
+}
 
 export interface MergeResult {
   success: boolean;
   output_path: string;
+  output_paths: string[];
   failed_files: string[];
+  failures: FailedFile[];
+  merged_files: number;
+  page_count: number;
   message?: string | null;
+  size_target_met?: boolean | null;
+  oversize: boolean;
+  output_fallback_used: boolean;
+  skipped_files: string[];
+  timings: PhaseTimings;
+  target_path?: string | null;
+  needs_confirmation?: string | null;
+  conflict_resolution?: ConflictAction | null;
+  cancelled?: boolean;
+  secondary_output_path?: string | null;
+  secondary_output_error?: string | null;
+  bates_last?: string | null;
+  page_map: PageRange[];
+}
+
+export type ConflictAction = "overwrite" | "rename" | "cancel" | "fail";
+
+export type FailureStage = "scan" | "convert";
+
+export type FailureKind =
+  | "missing"
+  | "outside_folder"
+  | "too_large"
+  | "io"
+  | "decode"
+  | "timeout"
+  | "encrypted"
+  | "pdf"
+  | "convert"
+  | "unsupported";
+
+export interface FailedFile {
+  file_name: string;
+  path: string;
+  stage: FailureStage;
+  kind: FailureKind;
+  message: string;
+}
+
+export interface PhaseTimings {
+  scan_ms: number;
+  convert_ms: number;
+  merge_ms: number;
+  write_ms: number;
+  total_ms: number;
 }
 
 export interface ProgressPayload {
   current: number;
   total: number;
+  processed_bytes: number;
+  total_bytes: number;
   phase: "scan" | "convert" | "merge" | "write";
 }
+
+export interface PasswordRequest {
+  path: string;
+  file_name: string;
+  attempt: number;
+}
+
+export interface FileConvertedPayload {
+  index: number;
+  file_name: string;
+  pdf_paths: string[];
+}
+
+export interface WarningPayload {
+  kind: string;
+  message: string;
+}
+
+export type PdfContentKind = "scanned" | "digital" | "mixed";
+
+export type SignatureStatus = "unsigned" | "digest_matches" | "broken" | "modified" | "unknown" | "unreadable";
+
+export interface FileValidation {
+  path: string;
+  file_name: string;
+  signature: SignatureStatus;
+  xfa: boolean;
+  duplicate_of?: string | null;
+  near_blank: boolean;
+  detected_ext?: string | null;
+  content_kind?: PdfContentKind | null;
+  previously_merged?: MergedInvoice | null;
+  too_large?: boolean;
+  warnings: string[];
+}
+
+export interface MergedInvoice {
+  invoice_number: string;
+  file_name: string;
+  output_path: string;
+  merged_at: string;
+  user: string;
+  machine: string;
+}
+
+export interface PlanEntry {
+  path: string;
+  file_name: string;
+  page_count: number;
+  start_page?: number | null;
+  end_page?: number | null;
+  error?: string | null;
+}
+
+export interface MergePlan {
+  entries: PlanEntry[];
+  total_pages: number;
+}
+
+export interface CleanupReport {
+  removed_entries: number;
+  freed_bytes: number;
+}